@@ -0,0 +1,340 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use clap::{ArgMatches, Args, Command, FromArgMatches};
+
+use super::pass::{AnalysisPass, PassContext, RangeArgs};
+use super::stats::ratio;
+use super::words;
+use crate::core::{Event, Field, Isa, snapshot_at};
+
+#[derive(Args)]
+struct BpStatsArgs {
+    /// PHT entries of the model (power of 2); nzea's `phtSize`
+    #[arg(long, default_value_t = 64)]
+    pht_size: usize,
+
+    /// BTB entries of the model (power of 2); nzea's `btbSize`
+    #[arg(long, default_value_t = 16)]
+    btb_size: usize,
+
+    /// Worst-predicted branches to list
+    #[arg(long, default_value_t = 10)]
+    top: usize,
+
+    /// Also print every branch PC, not only the worst ones
+    #[arg(long)]
+    all: bool,
+
+    #[command(flatten)]
+    range: RangeArgs,
+}
+
+const SIGNALS: &[&str] = &[
+    "ifu.out.valid",
+    "ifu.out.ready",
+    "ifu.out.pc",
+    "ifu.out.inst",
+    "ifu.out.pred_next_pc",
+    "bp.update.valid",
+    "bp.update.pc",
+    "bp.update.taken",
+    "bp.update.target",
+    "rob.do_flush",
+];
+
+/// BRANCH, JAL and JALR opcodes: the instructions BRU resolves.
+const CONTROL_OPCODES: [u64; 3] = [0x63, 0x6f, 0x67];
+
+/// BTB lookup result for a taken branch, before the BTB learns it.
+#[derive(Clone, Copy)]
+enum BtbLookup {
+    /// Tag and target match.
+    Hit,
+    /// Tag matches, the target changed (JALR, or a tag shared with another PC).
+    WrongTarget,
+    /// The entry holds another branch's tag.
+    Alias,
+    /// The entry was never written.
+    Miss,
+}
+
+/// Software PHT + BTB with the IFU's indexing, trained by the same updates.
+struct Predictor {
+    pht: Vec<u8>,
+    btb: Vec<Option<(u64, u64)>>,
+    /// The PHT takes one update per three cycles and drops the ones in between.
+    pht_busy_until: Option<usize>,
+}
+
+impl Predictor {
+    fn new(pht_size: usize, btb_size: usize) -> Self {
+        Predictor {
+            pht: vec![0; pht_size],
+            btb: vec![None; btb_size],
+            pht_busy_until: None,
+        }
+    }
+
+    fn pht_index(&self, pc: u64) -> usize {
+        (pc >> 2) as usize & (self.pht.len() - 1)
+    }
+
+    fn btb_index(&self, pc: u64) -> usize {
+        (pc >> 2) as usize & (self.btb.len() - 1)
+    }
+
+    /// Tag bits above the BTB index.
+    fn btb_tag(&self, pc: u64) -> u64 {
+        pc >> (2 + self.btb.len().trailing_zeros())
+    }
+
+    fn counter(&self, pc: u64) -> u8 {
+        self.pht[self.pht_index(pc)]
+    }
+
+    fn lookup(&self, pc: u64, target: u64) -> BtbLookup {
+        match self.btb[self.btb_index(pc)] {
+            None => BtbLookup::Miss,
+            Some((tag, _)) if tag != self.btb_tag(pc) => BtbLookup::Alias,
+            Some((_, t)) if t != target => BtbLookup::WrongTarget,
+            Some(_) => BtbLookup::Hit,
+        }
+    }
+
+    /// Next PC the IFU would predict: taken only on a set counter MSB and a BTB tag match.
+    fn predict(&self, pc: u64) -> u64 {
+        match self.btb[self.btb_index(pc)] {
+            Some((tag, target)) if self.counter(pc) >= 2 && tag == self.btb_tag(pc) => target,
+            _ => pc.wrapping_add(4),
+        }
+    }
+
+    /// Train on the update of cycle `cycle`; false if the PHT dropped it.
+    fn update(&mut self, cycle: usize, pc: u64, taken: bool, target: u64) -> bool {
+        if taken {
+            let (i, tag) = (self.btb_index(pc), self.btb_tag(pc));
+            self.btb[i] = Some((tag, target));
+        }
+        if self.pht_busy_until.is_some_and(|c| cycle <= c) {
+            return false;
+        }
+        self.pht_busy_until = Some(cycle + 2);
+        let i = self.pht_index(pc);
+        self.pht[i] = match taken {
+            true => (self.pht[i] + 1).min(3),
+            false => self.pht[i].saturating_sub(1),
+        };
+        true
+    }
+}
+
+/// Resolved instances of one branch PC.
+#[derive(Default)]
+struct Branch {
+    inst: Option<u64>,
+    count: usize,
+    taken: usize,
+    /// Instances matched with a fetch, and how many of those were mispredicted.
+    predicted: usize,
+    mispredicts: usize,
+    model_mispredicts: usize,
+}
+
+impl Branch {
+    fn accuracy(&self) -> Option<f64> {
+        ratio(self.predicted - self.mispredicts, self.predicted)
+    }
+}
+
+/// `bp-stats`: branch prediction accuracy, BTB and PHT behaviour from BRU updates.
+pub struct BpStats;
+
+impl AnalysisPass for BpStats {
+    fn name(&self) -> &'static str {
+        "bp-stats"
+    }
+
+    fn command(&self) -> Command {
+        BpStatsArgs::augment_args(Command::new(self.name()).about(
+            "Branch prediction report: match IFU predictions with BRU updates for accuracy overall and per branch PC, replay the updates through a PHT/BTB model for BTB hit/miss/alias rates and PHT counter distribution, and list the worst-predicted branches",
+        ))
+    }
+
+    fn output(&self) -> &'static str {
+        "SUMMARY (branches, taken, predicted, mispredicts, accuracy, model_mispredicts, model_accuracy, pht_dropped), BTB (lookups, hits, hit_rate, wrong_target, aliases, alias_rate, misses, miss_rate), PHT per counter value (counter, lookups, share), WORST per branch (rank, pc, disasm, count, taken, mispredicts, accuracy, model_mispredicts); with --all a BRANCH per PC with the same fields"
+    }
+
+    fn signals(&self) -> &'static [&'static str] {
+        SIGNALS
+    }
+
+    fn run(
+        &self,
+        ctx: &mut PassContext<'_>,
+        args: &ArgMatches,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let a = BpStatsArgs::from_arg_matches(args)?;
+        let (start, end) = a.range.or_last(ctx)?;
+        for (size, what) in [(a.pht_size, "--pht-size"), (a.btb_size, "--btb-size")] {
+            if !size.is_power_of_two() {
+                return Err(format!("{} must be a power of 2", what).into());
+            }
+        }
+        if !ctx.has("bp.update.valid") || !ctx.has("bp.update.pc") || !ctx.has("bp.update.taken") {
+            return Err("Could not find bp.update.valid / pc / taken".into());
+        }
+        let fetched = ctx.has("ifu.out.pred_next_pc") && ctx.has("ifu.out.pc");
+        let (wf, tb, sigs, isa, out) = (&*ctx.wf, ctx.tb, ctx.sigs, ctx.isa, &mut *ctx.out);
+        if !fetched {
+            out.note("ifu.out.pred_next_pc not in the trace: only the model predicts")?;
+        }
+
+        let samples = tb.cycles_in(start, end);
+        out.note(&format!(
+            "Branch prediction, t={}..{}, PHT {} / BTB {} entries",
+            start, end, a.pht_size, a.btb_size
+        ))?;
+
+        let mut model = Predictor::new(a.pht_size, a.btb_size);
+        // Predicted next PCs of fetched, unresolved instances, oldest first.
+        let mut in_flight: HashMap<u64, VecDeque<u64>> = HashMap::new();
+        let mut branches: BTreeMap<u64, Branch> = BTreeMap::new();
+        let mut btb = [0usize; 4];
+        let mut counters = [0usize; 4];
+        let mut pht_dropped = 0usize;
+        for (cycle, s) in samples.iter().enumerate() {
+            let snap = snapshot_at(wf, sigs, s.idx);
+            if snap.is_high("bp.update.valid")
+                && let (Some(pc), Some(taken)) = (
+                    snap.u64("bp.update.pc"),
+                    snap.get("bp.update.taken").and_then(|v| v.as_bool()),
+                )
+            {
+                let target = snap.u64("bp.update.target").unwrap_or(0);
+                let next = if taken { target } else { pc.wrapping_add(4) };
+                let b = branches.entry(pc).or_default();
+                b.count += 1;
+                b.taken += usize::from(taken);
+                if let Some(pred) = in_flight.get_mut(&pc).and_then(|q| q.pop_front()) {
+                    b.predicted += 1;
+                    b.mispredicts += usize::from(pred != next);
+                }
+                b.model_mispredicts += usize::from(model.predict(pc) != next);
+                counters[model.counter(pc) as usize] += 1;
+                if taken {
+                    btb[model.lookup(pc, target) as usize] += 1;
+                }
+                if !model.update(cycle, pc, taken, target) {
+                    pht_dropped += 1;
+                }
+            }
+            // Fetched instructions not resolved by now are on the wrong path.
+            if snap.is_high("rob.do_flush") {
+                in_flight.clear();
+            }
+            if fetched && snap.is_high("ifu.out.valid") && !snap.is_low("ifu.out.ready") {
+                let inst = snap.u64("ifu.out.inst");
+                let control = inst.is_none_or(|w| CONTROL_OPCODES.contains(&(w & 0x7f)));
+                if let (true, Some(pc), Some(pred)) = (
+                    control,
+                    snap.u64("ifu.out.pc"),
+                    snap.u64("ifu.out.pred_next_pc"),
+                ) {
+                    in_flight.entry(pc).or_default().push_back(pred);
+                    if inst.is_some() {
+                        branches.entry(pc).or_default().inst = inst;
+                    }
+                }
+            }
+        }
+
+        let sum = |f: fn(&Branch) -> usize| branches.values().map(f).sum::<usize>();
+        let count = sum(|b| b.count);
+        let predicted = sum(|b| b.predicted);
+        let mispredicts = sum(|b| b.mispredicts);
+        let model_mispredicts = sum(|b| b.model_mispredicts);
+        out.event(
+            &Event::new("SUMMARY")
+                .with("branches", count)
+                .with("taken", sum(|b| b.taken))
+                .with("predicted", predicted)
+                .with("mispredicts", mispredicts)
+                .with("accuracy", ratio(predicted - mispredicts, predicted))
+                .with("model_mispredicts", model_mispredicts)
+                .with("model_accuracy", ratio(count - model_mispredicts, count))
+                .with("pht_dropped", pht_dropped),
+        )?;
+        if fetched && predicted < count {
+            out.note(&format!(
+                "{} updates without a fetch in range: not in accuracy",
+                count - predicted
+            ))?;
+        }
+
+        out.note("\nBTB lookups of taken branches (model):")?;
+        let lookups: usize = btb.iter().sum();
+        let share = |n: usize| ratio(n, lookups);
+        out.event(
+            &Event::new("BTB")
+                .with("lookups", lookups)
+                .with("hits", btb[BtbLookup::Hit as usize])
+                .with("hit_rate", share(btb[BtbLookup::Hit as usize]))
+                .with("wrong_target", btb[BtbLookup::WrongTarget as usize])
+                .with("aliases", btb[BtbLookup::Alias as usize])
+                .with("alias_rate", share(btb[BtbLookup::Alias as usize]))
+                .with("misses", btb[BtbLookup::Miss as usize])
+                .with("miss_rate", share(btb[BtbLookup::Miss as usize])),
+        )?;
+
+        out.note("\nPHT counter at each lookup (model; 2 and 3 predict taken):")?;
+        for (counter, &n) in counters.iter().enumerate() {
+            out.event(
+                &Event::new("PHT")
+                    .with("counter", counter)
+                    .with("lookups", n)
+                    .with("share", ratio(n, count)),
+            )?;
+        }
+
+        // Worst first: most mispredictions, then the lowest accuracy.
+        let mut worst: Vec<(&u64, &Branch)> = branches
+            .iter()
+            .filter(|(_, b)| b.mispredicts + b.model_mispredicts > 0)
+            .collect();
+        worst.sort_by(|(_, x), (_, y)| {
+            (y.mispredicts, y.model_mispredicts)
+                .cmp(&(x.mispredicts, x.model_mispredicts))
+                .then(x.accuracy().partial_cmp(&y.accuracy()).unwrap())
+        });
+        out.note(&format!("\nTop {} worst-predicted branches:", a.top))?;
+        if worst.is_empty() {
+            out.note("  (no mispredictions)")?;
+        }
+        for (rank, &(&pc, b)) in worst.iter().take(a.top).enumerate() {
+            out.event(&branch_event(
+                Event::new("WORST").with("rank", rank + 1),
+                &isa,
+                pc,
+                b,
+            ))?;
+        }
+
+        if a.all {
+            out.note("\nAll branches:")?;
+            for (&pc, b) in &branches {
+                out.event(&branch_event(Event::new("BRANCH"), &isa, pc, b))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn branch_event(ev: Event, isa: &Isa, pc: u64, b: &Branch) -> Event {
+    ev.with("pc", Field::Hex(pc))
+        .with("disasm", words::disasm(isa, Some(pc), b.inst))
+        .with("count", b.count)
+        .with("taken", b.taken)
+        .with("mispredicts", b.mispredicts)
+        .with("accuracy", b.accuracy())
+        .with("model_mispredicts", b.model_mispredicts)
+}
@@ -0,0 +1,116 @@
+use super::pass::{AnalysisPass, PassContext};
+use crate::core::{Event, TimeArg, find_time_idx_at_or_before, snapshot_at};
+use clap::{ArgMatches, Args, Command, FromArgMatches};
+
+#[derive(Args)]
+struct ListArgs {
+    /// Filter signals by name substring
+    #[arg(short, long)]
+    grep: Option<String>,
+}
+
+/// `list`: signal names in the hierarchy.
+pub struct List;
+
+impl AnalysisPass for List {
+    fn name(&self) -> &'static str {
+        "list"
+    }
+
+    fn command(&self) -> Command {
+        ListArgs::augment_args(
+            Command::new(self.name())
+                .visible_alias("ls")
+                .about("List all signal names (optionally filtered by substring)"),
+        )
+    }
+
+    fn output(&self) -> &'static str {
+        "SIGNAL events (index, name), one per signal"
+    }
+
+    fn run(
+        &self,
+        ctx: &mut PassContext<'_>,
+        args: &ArgMatches,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let a = ListArgs::from_arg_matches(args)?;
+        let hierarchy = ctx.wf.hierarchy();
+        for var in hierarchy.iter_vars() {
+            let name = var.full_name(hierarchy);
+            if a.grep.as_ref().is_none_or(|g| name.contains(g)) {
+                ctx.out.event(
+                    &Event::new("SIGNAL")
+                        .with("index", var.signal_ref().index())
+                        .with("name", name),
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+struct AtArgs {
+    /// Time to sample: cycle (c1234), time with unit (1234ns) or raw timescale units
+    time: TimeArg,
+
+    /// Filter signals by name substring
+    #[arg(short, long)]
+    grep: Option<String>,
+}
+
+/// `at`: values of (grep-matched) signals at one time.
+pub struct At;
+
+impl AnalysisPass for At {
+    fn name(&self) -> &'static str {
+        "at"
+    }
+
+    fn command(&self) -> Command {
+        AtArgs::augment_args(
+            Command::new(self.name())
+                .about("Print signal values at this time (c1234, 1234ns or timescale units)"),
+        )
+    }
+
+    fn output(&self) -> &'static str {
+        "VALUE events (name, value), one per matched signal at the last sample <= time"
+    }
+
+    fn wants_signal(&self, name: &str, args: &ArgMatches) -> bool {
+        args.get_one::<String>("grep")
+            .is_none_or(|g| name.contains(g.as_str()))
+    }
+
+    fn run(
+        &self,
+        ctx: &mut PassContext<'_>,
+        args: &ArgMatches,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let a = AtArgs::from_arg_matches(args)?;
+        let time = a.time.resolve(ctx.tb)?;
+        let Some(idx) = find_time_idx_at_or_before(ctx.time_table, time) else {
+            ctx.out
+                .note(&format!("No time <= {} in waveform", a.time))?;
+            return Ok(());
+        };
+        let t = ctx.time_table[idx as usize];
+        // Cycle only when the time table entry is a cycle sample.
+        let cycle = ctx
+            .tb
+            .at_or_before(t)
+            .filter(|s| s.idx == idx)
+            .and_then(|s| s.cycle);
+        ctx.out
+            .note(&format!("\nValues at time {} (idx {}):", a.time, idx))?;
+        for (name, val) in snapshot_at(ctx.wf, ctx.sigs, idx).iter() {
+            ctx.out.event(&Event {
+                cycle,
+                ..Event::at(t, "VALUE").with("name", name).with("value", val)
+            })?;
+        }
+        Ok(())
+    }
+}
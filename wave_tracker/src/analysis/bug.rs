@@ -1,70 +1,113 @@
-use wellen::simple::Waveform;
-use wellen::{SignalRef, Time};
-
-/// Bug scan: find cycles where PR is both in FreeList (any buf slot) AND RMT table_1 (sp) = PR.
-pub fn bug_scan_pr_in_both(
-    wf: &mut Waveform,
-    time_table: &[Time],
-    sig_refs: &[(String, SignalRef)],
+use clap::{ArgMatches, Args, Command, FromArgMatches};
+use wellen::Time;
+
+use super::pass::{AnalysisPass, PassContext, RangeArgs};
+use crate::core::{Event, NumArg, Value, value_at};
+
+#[derive(Args)]
+struct BugScanArgs {
+    /// Physical register (e.g. 2, PR2, 0b000010)
+    #[arg(long, default_value = "PR2")]
+    pr: NumArg,
+
+    #[command(flatten)]
+    range: RangeArgs,
+}
+
+/// `bug-scan`: PR both in FreeList buf and RMT(sp).
+pub struct BugScan;
+
+impl AnalysisPass for BugScan {
+    fn name(&self) -> &'static str {
+        "bug-scan"
+    }
+
+    fn command(&self) -> Command {
+        BugScanArgs::augment_args(Command::new(self.name()).about(
+            "Find cycles where PR is both in FreeList buf AND RMT(sp)=PR (default --to: from+500)",
+        ))
+    }
+
+    fn output(&self) -> &'static str {
+        "BUG events (idx, pr, rmt_sp, slots: buf slots holding the PR); BUF_UNKNOWN events (idx, pr, slots) when RMT(sp)=PR and buf slots are X/Z"
+    }
+
+    fn signals(&self) -> &'static [&'static str] {
+        &["freelist.buf[i]", "rmt.map[i]"]
+    }
+
+    fn run(
+        &self,
+        ctx: &mut PassContext<'_>,
+        args: &ArgMatches,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let a = BugScanArgs::from_arg_matches(args)?;
+        let (start, end) = a.range.span(ctx.tb, 500)?;
+        bug_scan_pr_in_both(ctx, start, end, a.pr.get())
+    }
+}
+
+/// Bug scan: find cycles where PR is both in FreeList (any buf slot) AND RMT(sp) = PR.
+fn bug_scan_pr_in_both(
+    ctx: &mut PassContext<'_>,
     start: Time,
     end: Time,
-    pr_bin: &str,
+    pr: u64,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    if sig_refs.is_empty() {
-        return Err("Could not find freeList.buf_* or rmt.table_1 signals".into());
+    if !ctx.has("freelist.buf[") {
+        return Err("Could not find freelist.buf[i] signals".into());
     }
-    if !sig_refs.iter().any(|(n, _)| n.contains("table_1")) {
-        return Err("Could not find rmt.table_1".into());
+    let (wf, tb, sig_refs, out) = (&*ctx.wf, ctx.tb, ctx.sigs, &mut *ctx.out);
+    // rmt.map[i] maps x(i+1), so sp (x2) is rmt.map[1].
+    const RMT_SP: &str = "rmt.map[1]";
+    if !sig_refs.iter().any(|(n, _)| n == RMT_SP) {
+        return Err(format!("Could not find {}", RMT_SP).into());
     }
 
-    let to_load: Vec<SignalRef> = sig_refs.iter().map(|(_, sr)| *sr).collect();
-    wf.load_signals(&to_load);
+    out.note(&format!("Bug scan: PR{} in FreeList buf AND RMT(sp)", pr))?;
+    out.note("Cycles where both true:\n")?;
 
-    println!(
-        "Bug scan: PR={} in FreeList buf AND RMT(sp)=table_1",
-        pr_bin
-    );
-    println!("Cycles where both true:\n");
+    for s in tb.range(start, end) {
+        let idx = s.idx;
 
-    for (i, &t) in time_table.iter().enumerate() {
-        if t < start {
-            continue;
-        }
-        if t > end {
-            break;
-        }
-        let idx = i as u32;
-
-        let mut table_1_val = String::new();
-        let mut pr_in_buf = false;
-        let mut buf_slots_with_pr: Vec<String> = Vec::new();
+        let mut sp_val: Option<Value> = None;
+        let mut buf_slots_with_pr: Vec<&str> = Vec::new();
+        let mut unknown_slots: Vec<&str> = Vec::new();
 
         for (name, sig_ref) in sig_refs.iter() {
-            if let Some(sig) = wf.get_signal(*sig_ref) {
-                if let Some(offset) = sig.get_offset(idx) {
-                    let val = sig.get_value_at(&offset, 0).to_string();
-                    let val_trim = val.trim();
-                    if name.contains("table_1") {
-                        table_1_val = val_trim.to_string();
-                    } else if val_trim == pr_bin || val_trim.ends_with(pr_bin) {
-                        pr_in_buf = true;
-                        buf_slots_with_pr.push(name.clone());
-                    }
-                }
+            if name != RMT_SP && !name.starts_with("freelist.buf[") {
+                continue;
+            }
+            let Some(val) = value_at(wf, *sig_ref, idx) else {
+                continue;
+            };
+            if name == RMT_SP {
+                sp_val = Some(val);
+            } else if val == pr {
+                buf_slots_with_pr.push(name);
+            } else if val.is_unknown() {
+                unknown_slots.push(name);
             }
         }
 
-        let rmt_has_pr = table_1_val == pr_bin;
+        let rmt_has_pr = sp_val.as_ref().is_some_and(|v| *v == pr);
 
-        if pr_in_buf && rmt_has_pr {
-            println!(
-                "t={} idx={} *** BUG: PR{} in FreeList AND table_1(sp)=PR{}",
-                t, idx, pr_bin, pr_bin
-            );
-            println!("  rmt.table_1: {}", table_1_val);
-            for slot in &buf_slots_with_pr {
-                println!("  {}: {} (contains PR)", slot, pr_bin);
-            }
+        if !buf_slots_with_pr.is_empty() && rmt_has_pr {
+            out.event(
+                &Event::on(s, "BUG")
+                    .with("idx", idx)
+                    .with("pr", pr)
+                    .with("rmt_sp", sp_val.as_ref())
+                    .with("slots", buf_slots_with_pr.join(";")),
+            )?;
+        }
+        if rmt_has_pr && !unknown_slots.is_empty() {
+            out.event(
+                &Event::on(s, "BUF_UNKNOWN")
+                    .with("idx", idx)
+                    .with("pr", pr)
+                    .with("slots", unknown_slots.join(";")),
+            )?;
         }
     }
 
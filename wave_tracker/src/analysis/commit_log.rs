@@ -0,0 +1,139 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::LazyLock;
+
+use clap::{ArgMatches, Args, Command, FromArgMatches};
+use wellen::Time;
+
+use super::commits::{self, Commit};
+use super::dbus;
+use super::pass::{AnalysisPass, PassContext, RangeArgs};
+use super::tracker::InstructionTracker;
+use super::words;
+use crate::core::{Event, Field, Isa, NumArg};
+
+/// Layout of a written commit log.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LogStyle {
+    /// Spike `--log-commits` style lines
+    #[default]
+    Spike,
+    /// One JSON object per commit
+    Json,
+}
+
+#[derive(Args)]
+struct CommitLogArgs {
+    /// Write the log to this file instead of emitting COMMIT events
+    path: Option<PathBuf>,
+
+    /// Layout of the file written to PATH
+    #[arg(long, value_enum, default_value_t, requires = "path")]
+    style: LogStyle,
+
+    /// PC of the first commit when the trace does not show it (CoreConfig.defaultPc);
+    /// bare digits are hex
+    #[arg(long, value_parser = NumArg::parse_hex, default_value = "80000000")]
+    reset_pc: NumArg,
+
+    #[command(flatten)]
+    range: RangeArgs,
+}
+
+static SIGNALS: LazyLock<Vec<&'static str>> =
+    LazyLock::new(|| [commits::SIGNALS, InstructionTracker::SIGNALS, dbus::SIGNALS].concat());
+
+/// `commit-log`: the architectural commit stream (what `commit_trace` sees).
+pub struct CommitLog;
+
+impl AnalysisPass for CommitLog {
+    fn name(&self) -> &'static str {
+        "commit-log"
+    }
+
+    fn command(&self) -> Command {
+        CommitLogArgs::augment_args(Command::new(self.name()).about(
+            "Rebuild the commit stream from commit_msg (pc, next_pc, rd / CSR writes, load / store with its address and store data from the dbus); with PATH write it as a Spike-style or JSON log",
+        ))
+    }
+
+    fn output(&self) -> &'static str {
+        "COMMIT events (pc, inst, disasm, next_pc, rd, rd_value, csr, csr_value, mem, mem_addr, mem_data) in the window, or the log at PATH; then a summary"
+    }
+
+    fn signals(&self) -> &'static [&'static str] {
+        &SIGNALS
+    }
+
+    fn run(
+        &self,
+        ctx: &mut PassContext<'_>,
+        args: &ArgMatches,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let a = CommitLogArgs::from_arg_matches(args)?;
+        let (start, end) = a.range.or_last(ctx)?;
+        let tracker = InstructionTracker::build(ctx.wf, ctx.tb, ctx.sigs, ctx.layout);
+        let mut all = commits::commits(ctx.wf, ctx.tb, ctx.sigs, &tracker, a.reset_pc.get());
+        if ctx.has("dbus.req.valid") && ctx.has("lsu.mem_req.rob_id") {
+            let log = dbus::accesses(ctx.wf, ctx.tb, ctx.sigs, 0, Time::MAX);
+            commits::attach_accesses(&mut all, &tracker, &log);
+        }
+        let selected: Vec<&Commit> = all
+            .iter()
+            .filter(|c| (start..=end).contains(&c.at.time))
+            .collect();
+
+        let out = &mut *ctx.out;
+        match &a.path {
+            Some(path) => {
+                let file = File::create(path)
+                    .map_err(|e| format!("Cannot create {}: {}", path.display(), e))?;
+                let mut w = BufWriter::new(file);
+                for c in &selected {
+                    match a.style {
+                        LogStyle::Spike => writeln!(w, "{}", c.spike_line())?,
+                        LogStyle::Json => writeln!(w, "{}", c.json())?,
+                    }
+                }
+                w.flush()?;
+                out.note(&format!(
+                    "Wrote {} commits (t={}..{}) to {}",
+                    selected.len(),
+                    start,
+                    end,
+                    path.display()
+                ))?;
+            }
+            None => {
+                out.note(&format!("Commit log, t={}..{}\n", start, end))?;
+                for c in &selected {
+                    out.event(&commit_event(&ctx.isa, c))?;
+                }
+                out.note(&format!("\n{} commits", selected.len()))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn commit_event(isa: &Isa, c: &Commit) -> Event {
+    Event::on(&c.at, "COMMIT")
+        .with("pc", c.pc.map_or(Field::Null, Field::Hex))
+        .with("inst", c.inst.map_or(Field::Null, Field::Hex))
+        .with("disasm", words::disasm(isa, c.pc, c.inst))
+        .with("next_pc", c.next_pc.map_or(Field::Null, Field::Hex))
+        .with("rd", c.rd.map(|(rd, _)| rd))
+        .with("rd_value", c.rd.map_or(Field::Null, |(_, v)| Field::Hex(v)))
+        .with(
+            "csr",
+            c.csr.map_or(Field::Null, |(addr, _)| Field::Hex(addr)),
+        )
+        .with(
+            "csr_value",
+            c.csr.map_or(Field::Null, |(_, v)| Field::Hex(v)),
+        )
+        .with("mem", c.mem.map(|m| m.name()))
+        .with("mem_addr", c.mem_addr.map_or(Field::Null, Field::Hex))
+        .with("mem_data", c.mem_data.map_or(Field::Null, Field::Hex))
+}
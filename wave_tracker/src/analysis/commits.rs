@@ -0,0 +1,535 @@
+//! The architectural commit stream, rebuilt from the `commit_msg` port that
+//! `CommitDpiBridge` hands to `commit_trace`.
+//!
+//! `CommitMsg` has no PC: each commit's PC is the previous commit's `next_pc`. The first
+//! one (and any after an unknown `next_pc`) takes the PC of the instruction the
+//! [`InstructionTracker`] saw commit on that edge; the first falls back to the reset PC.
+
+use std::collections::HashMap;
+
+use serde_json::json;
+use wellen::{SignalRef, simple::Waveform};
+
+use super::dbus::{Access, Dir, Owners};
+use super::tracker::{Instruction, InstructionTracker, Stage, stamp};
+use crate::core::{Sample, Timebase, disasm::csr_name, snapshot_at};
+
+/// CSR address per `CsrType` (None, Mstatus, Mtvec, Mepc, Mcause, Mscratch).
+const CSR_TYPE_ADDR: [Option<u64>; 6] = [
+    None,
+    Some(0x300),
+    Some(0x305),
+    Some(0x341),
+    Some(0x342),
+    Some(0x340),
+];
+
+/// Memory access of a committed instruction (`mem_count` / `is_load`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemKind {
+    Load,
+    Store,
+}
+
+impl MemKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            MemKind::Load => "load",
+            MemKind::Store => "store",
+        }
+    }
+}
+
+/// One committed instruction.
+#[derive(Clone, Debug)]
+pub struct Commit {
+    pub at: Sample,
+    pub pc: Option<u64>,
+    /// Instruction word, when the tracker saw it fetched or decoded.
+    pub inst: Option<u64>,
+    pub next_pc: Option<u64>,
+    /// `(rd, value)` for a write to x1..x31.
+    pub rd: Option<(u64, u64)>,
+    /// `(address, value)` of the CSR write.
+    pub csr: Option<(u64, u64)>,
+    pub mem: Option<MemKind>,
+    /// Byte address of the load / store, from its dbus access (see [`attach_accesses`]).
+    pub mem_addr: Option<u64>,
+    /// Data a store wrote, from its dbus access.
+    pub mem_data: Option<u64>,
+    /// Index of the instruction in [`InstructionTracker::records`].
+    pub seq: Option<usize>,
+}
+
+impl Commit {
+    /// Spike `--log-commits` style: `core   0: 3 0x80000014 (0x00108093) x1  0x00000001`,
+    /// then ` c832_mscratch 0x...` for a CSR write (decimal address, as Spike prints it),
+    /// ` mem 0x...` for a load and ` mem 0x... 0x...` for a store (` load` / ` store` while
+    /// the address or store data is unknown), and `; c48` (the cycle, or the time without a
+    /// clock). An unknown instruction word prints as 0.
+    pub fn spike_line(&self) -> String {
+        let mut line = format!(
+            "core   0: 3 0x{:08x} (0x{:08x})",
+            self.pc.unwrap_or(0),
+            self.inst.unwrap_or(0)
+        );
+        if let Some((rd, value)) = self.rd {
+            line += &format!(" x{:<2} 0x{:08x}", rd, value);
+        }
+        if let Some((addr, value)) = self.csr {
+            line += &format!(" c{}_{} 0x{:08x}", addr, csr_name(addr as u32), value);
+        }
+        match (self.mem, self.mem_addr, self.mem_data) {
+            (Some(MemKind::Load), Some(addr), _) => line += &format!(" mem 0x{:08x}", addr),
+            (Some(MemKind::Store), Some(addr), Some(data)) => {
+                line += &format!(" mem 0x{:08x} 0x{:08x}", addr, data)
+            }
+            (Some(mem), _, _) => line += &format!(" {}", mem.name()),
+            (None, _, _) => {}
+        }
+        line + &format!(" ; c{}", stamp(&self.at))
+    }
+
+    /// One JSON object (see the commit log section of the docs); absent values are `null`.
+    pub fn json(&self) -> serde_json::Value {
+        json!({
+            "cycle": self.at.cycle,
+            "time": self.at.time,
+            "pc": self.pc,
+            "inst": self.inst,
+            "next_pc": self.next_pc,
+            "rd": self.rd.map(|(rd, _)| rd),
+            "rd_value": self.rd.map(|(_, v)| v),
+            "csr": self.csr.map(|(addr, _)| addr),
+            "csr_value": self.csr.map(|(_, v)| v),
+            "mem": self.mem.map(MemKind::name),
+            "mem_addr": self.mem_addr,
+            "mem_data": self.mem_data,
+        })
+    }
+}
+
+/// Schema keys of the commit port; callers also load [`InstructionTracker::SIGNALS`] for
+/// PCs and instruction words.
+pub const SIGNALS: &[&str] = &[
+    "commit.msg.valid",
+    "commit.msg.next_pc",
+    "commit.msg.rd_index",
+    "commit.msg.rd_value",
+    "commit.msg.mem_count",
+    "commit.msg.is_load",
+    "commit.msg.csr_type",
+    "commit.msg.csr_data",
+];
+
+/// Every commit in the trace, in order.
+pub fn commits(
+    wf: &Waveform,
+    tb: &Timebase,
+    sigs: &[(String, SignalRef)],
+    tracker: &InstructionTracker,
+    reset_pc: u64,
+) -> Vec<Commit> {
+    let by_sample: HashMap<u32, &Instruction> = tracker
+        .records()
+        .iter()
+        .filter_map(|i| i.at(Stage::Commit).map(|s| (s.idx, i)))
+        .collect();
+
+    let mut out: Vec<Commit> = Vec::new();
+    for s in tb.samples() {
+        let snap = snapshot_at(wf, sigs, s.idx);
+        if !snap.is_high("commit.msg.valid") {
+            continue;
+        }
+        let tracked = by_sample.get(&s.idx);
+        let pc = match out.last() {
+            Some(prev) => prev.next_pc.or(tracked.and_then(|i| i.pc)),
+            None => tracked.and_then(|i| i.pc).or(Some(reset_pc)),
+        };
+        let rd = snap
+            .u64("commit.msg.rd_index")
+            .filter(|&rd| rd != 0)
+            .map(|rd| (rd, snap.u64("commit.msg.rd_value").unwrap_or(0)));
+        let csr = snap
+            .u64("commit.msg.csr_type")
+            .and_then(|t| CSR_TYPE_ADDR.get(t as usize).copied().flatten())
+            .map(|addr| (addr, snap.u64("commit.msg.csr_data").unwrap_or(0)));
+        let mem = (snap.u64("commit.msg.mem_count").unwrap_or(0) > 0).then(|| {
+            if snap.is_high("commit.msg.is_load") {
+                MemKind::Load
+            } else {
+                MemKind::Store
+            }
+        });
+        out.push(Commit {
+            at: *s,
+            pc,
+            inst: tracked.and_then(|i| i.inst),
+            next_pc: snap.u64("commit.msg.next_pc"),
+            rd,
+            csr,
+            mem,
+            mem_addr: None,
+            mem_data: None,
+            seq: tracked.map(|i| i.seq),
+        });
+    }
+    out
+}
+
+/// Fill in [`Commit::mem_addr`] / [`Commit::mem_data`] from `log` (see
+/// [`dbus::accesses`](super::dbus::accesses)): the last access in the commit's direction
+/// that its instruction made and that was not flushed. Loads get no data, as in Spike.
+pub fn attach_accesses(commits: &mut [Commit], tracker: &InstructionTracker, log: &[Access]) {
+    let owners = Owners::new(tracker);
+    // Later accesses (a replayed load) replace earlier ones.
+    let by_seq: HashMap<usize, &Access> = log
+        .iter()
+        .filter(|acc| !acc.flushed)
+        .filter_map(|acc| owners.of(acc).map(|i| (i.seq, acc)))
+        .collect();
+    for c in commits {
+        let dir = match c.mem {
+            Some(MemKind::Load) => Dir::Read,
+            Some(MemKind::Store) => Dir::Write,
+            None => continue,
+        };
+        let Some(acc) = c
+            .seq
+            .and_then(|seq| by_seq.get(&seq))
+            .filter(|acc| acc.dir == dir)
+        else {
+            continue;
+        };
+        c.mem_addr = Some(acc.byte_addr());
+        c.mem_data = if dir == Dir::Write { acc.value() } else { None };
+    }
+}
+
+/// One commit of a reference log.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RefCommit {
+    /// 1-based line number in the log.
+    pub line: usize,
+    pub pc: u64,
+    /// `(rd, value)` for a write to x1..x31.
+    pub rd: Option<(u64, u64)>,
+    pub mem: Option<MemKind>,
+    /// Operands of Spike's `mem <addr> [<data>]`: the byte address, and the data of a store.
+    pub mem_addr: Option<u64>,
+    pub mem_data: Option<u64>,
+}
+
+fn hex(tok: &str) -> Option<u64> {
+    u64::from_str_radix(tok.strip_prefix("0x")?, 16).ok()
+}
+
+/// Parse one Spike `--log-commits` line (`core   0: 3 0x... (0x...) x1  0x... mem 0x...`),
+/// including the lines [`Commit::spike_line`] writes. `None` for other lines, such as
+/// Spike's instruction trace (`core   0: 0x... (0x...) addi ...`, no privilege level).
+/// `line` is left at 0 for [`parse_ref`] to fill in.
+fn parse_spike(line: &str) -> Option<RefCommit> {
+    let line = line.split(';').next()?;
+    let mut toks = line.split_whitespace();
+    if toks.next()? != "core" {
+        return None;
+    }
+    toks.next()?; // hart, e.g. `0:`
+    let privilege = toks.next()?;
+    if privilege.len() != 1 || !privilege.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let pc = hex(toks.next()?)?;
+    toks.next()?; // (0x<inst>)
+    let toks: Vec<&str> = toks.collect();
+    let (mut rd, mut mem, mut mem_addr, mut mem_data) = (None, None, None, None);
+    let mut i = 0;
+    while i < toks.len() {
+        let tok = toks[i];
+        match tok {
+            "load" => mem = Some(MemKind::Load),
+            "store" => mem = Some(MemKind::Store),
+            // `mem <addr>` for a load, `mem <addr> <data>` for a store.
+            "mem" => {
+                let operands: Vec<u64> =
+                    toks[i + 1..].iter().take(2).map_while(|t| hex(t)).collect();
+                mem = Some(if operands.len() >= 2 {
+                    MemKind::Store
+                } else {
+                    MemKind::Load
+                });
+                mem_addr = operands.first().copied();
+                mem_data = operands.get(1).copied();
+                i += operands.len();
+            }
+            _ => {
+                if let Some(r) = tok.strip_prefix('x').and_then(|r| r.parse::<u64>().ok()) {
+                    let value = toks.get(i + 1).and_then(|t| hex(t))?;
+                    if r != 0 {
+                        rd = Some((r, value));
+                    }
+                    i += 1;
+                } else if toks.get(i + 1).is_some_and(|t| t.starts_with("0x")) {
+                    // Other register files and CSRs (`f1`, `c768_mstatus`): not compared.
+                    i += 1;
+                }
+            }
+        }
+        i += 1;
+    }
+    Some(RefCommit {
+        line: 0,
+        pc,
+        rd,
+        mem,
+        mem_addr,
+        mem_data,
+    })
+}
+
+/// Parse one JSON line as written by [`Commit::json`].
+fn parse_json(line: &str) -> Result<RefCommit, String> {
+    let v: serde_json::Value = serde_json::from_str(line).map_err(|e| e.to_string())?;
+    let num = |key: &str| v.get(key).and_then(|n| n.as_u64());
+    let pc = num("pc").ok_or("missing \"pc\"")?;
+    let rd = num("rd")
+        .filter(|&rd| rd != 0)
+        .map(|rd| (rd, num("rd_value").unwrap_or(0)));
+    let mem = match v.get("mem").and_then(|m| m.as_str()) {
+        Some("load") => Some(MemKind::Load),
+        Some("store") => Some(MemKind::Store),
+        Some(other) => return Err(format!("unknown \"mem\" value `{}`", other)),
+        None => None,
+    };
+    Ok(RefCommit {
+        line: 0,
+        pc,
+        rd,
+        mem,
+        mem_addr: num("mem_addr"),
+        mem_data: num("mem_data"),
+    })
+}
+
+/// Parse a reference commit log: Spike `--log-commits` text (other lines are skipped)
+/// or JSON Lines as written by `commit-log --style json`, told apart per line.
+pub fn parse_ref(text: &str) -> Result<Vec<RefCommit>, String> {
+    let mut out = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let line_no = n + 1;
+        let trimmed = line.trim_start();
+        let parsed = if trimmed.starts_with('{') {
+            Some(parse_json(trimmed).map_err(|e| format!("line {}: {}", line_no, e))?)
+        } else {
+            parse_spike(trimmed)
+        };
+        if let Some(c) = parsed {
+            out.push(RefCommit { line: line_no, ..c });
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(
+        pc: u64,
+        rd: Option<(u64, u64)>,
+        mem: Option<(MemKind, u64, Option<u64>)>,
+    ) -> RefCommit {
+        RefCommit {
+            line: 0,
+            pc,
+            rd,
+            mem: mem.map(|(kind, _, _)| kind),
+            mem_addr: mem.map(|(_, addr, _)| addr),
+            mem_data: mem.and_then(|(_, _, data)| data),
+        }
+    }
+
+    #[test]
+    fn spike_lines() {
+        use MemKind::*;
+        let cases = [
+            (
+                "core   0: 3 0x80000014 (0x00108093) x1  0x00000001",
+                Some(commit(0x8000_0014, Some((1, 1)), None)),
+            ),
+            (
+                "core   0: 3 0x80000030 (0x34039473) x8  0x00000000 c832_mscratch 0x00000001",
+                Some(commit(0x8000_0030, Some((8, 0)), None)),
+            ),
+            (
+                "core   0: 3 0x80000020 (0x00022283) x5  0x00001101 mem 0x80000400",
+                Some(commit(
+                    0x8000_0020,
+                    Some((5, 0x1101)),
+                    Some((Load, 0x8000_0400, None)),
+                )),
+            ),
+            (
+                "core   0: 3 0x8000001c (0x00322023) mem 0x80000400 0x00001101",
+                Some(commit(
+                    0x8000_001c,
+                    None,
+                    Some((Store, 0x8000_0400, Some(0x1101))),
+                )),
+            ),
+            // No rd: a branch, and a write to x0.
+            (
+                "core   0: 3 0x80000038 (0x00049463)",
+                Some(commit(0x8000_0038, None, None)),
+            ),
+            (
+                "core   0: 3 0x80000000 (0x00000013) x0  0x00000000",
+                Some(commit(0x8000_0000, None, None)),
+            ),
+            // Floating-point writes are skipped; what follows ; is a comment.
+            (
+                "core   0: 3 0x80000040 (0x0000a007) f0  0x3f800000 mem 0x80000408 ; c48",
+                Some(commit(0x8000_0040, None, Some((Load, 0x8000_0408, None)))),
+            ),
+            // commit-log without dbus operands.
+            (
+                "core   0: 3 0x8000001c (0x00322023) store ; c18",
+                Some(RefCommit {
+                    mem: Some(Store),
+                    ..commit(0x8000_001c, None, None)
+                }),
+            ),
+            // Spike's -l instruction trace has no privilege level.
+            ("core   0: 0x80000014 (0x00108093) addi    ra, ra, 1", None),
+            ("", None),
+            ("x1 0x00000001", None),
+            // An x register without its value is malformed.
+            ("core   0: 3 0x80000014 (0x00108093) x1", None),
+        ];
+        for (line, want) in cases {
+            assert_eq!(parse_spike(line), want, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn json_lines() {
+        let full = r#"{"cycle":18,"time":212,"pc":2147483676,"inst":3285027,"next_pc":2147483680,"rd":null,"rd_value":null,"csr":null,"csr_value":null,"mem":"store","mem_addr":2147484672,"mem_data":4353}"#;
+        assert_eq!(
+            parse_json(full),
+            Ok(commit(
+                0x8000_001c,
+                None,
+                Some((MemKind::Store, 0x8000_0400, Some(0x1101)))
+            ))
+        );
+        // Absent fields are the same as null; rd without rd_value reads 0.
+        let cases = [
+            (r#"{"pc":2147483668}"#, Ok(commit(0x8000_0014, None, None))),
+            (
+                r#"{"pc":2147483668,"rd":1}"#,
+                Ok(commit(0x8000_0014, Some((1, 0)), None)),
+            ),
+            (
+                r#"{"pc":2147483668,"rd":0,"rd_value":5}"#,
+                Ok(commit(0x8000_0014, None, None)),
+            ),
+            (
+                r#"{"pc":2147483668,"mem":"load"}"#,
+                Ok(RefCommit {
+                    mem: Some(MemKind::Load),
+                    ..commit(0x8000_0014, None, None)
+                }),
+            ),
+            (r#"{"rd":1}"#, Err("missing \"pc\"".to_string())),
+            (
+                r#"{"pc":1,"mem":"amo"}"#,
+                Err("unknown \"mem\" value `amo`".to_string()),
+            ),
+        ];
+        for (line, want) in cases {
+            assert_eq!(parse_json(line), want, "{}", line);
+        }
+        assert!(parse_json("{\"pc\":").is_err());
+    }
+
+    #[test]
+    fn ref_logs() {
+        let text = "\
+bbl loader
+core   0: 0x80000000 (0x00000093) li      ra, 0
+core   0: 3 0x80000000 (0x00000093) x1  0x00000000
+  {\"pc\":2147483652,\"rd\":2,\"rd_value\":7}
+core   0: 3 0x80000008 (0x00000013)
+";
+        let got = parse_ref(text).unwrap();
+        let lines: Vec<(usize, u64)> = got.iter().map(|c| (c.line, c.pc)).collect();
+        assert_eq!(
+            lines,
+            [(3, 0x8000_0000), (4, 0x8000_0004), (5, 0x8000_0008)]
+        );
+        assert_eq!(got[1].rd, Some((2, 7)));
+
+        let err = parse_ref("core   0: 3 0x80000000 (0x00000093)\n{\"rd\":1}\n").unwrap_err();
+        assert_eq!(err, "line 2: missing \"pc\"");
+    }
+
+    #[test]
+    fn spike_line_round_trip() {
+        let at = Sample {
+            idx: 0,
+            time: 212,
+            cycle: Some(18),
+        };
+        let base = Commit {
+            at,
+            pc: Some(0x8000_001c),
+            inst: Some(0x0032_2023),
+            next_pc: Some(0x8000_0020),
+            rd: None,
+            csr: None,
+            mem: None,
+            mem_addr: None,
+            mem_data: None,
+            seq: None,
+        };
+        let cases = [
+            base.clone(),
+            Commit {
+                rd: Some((5, 0x1101)),
+                csr: Some((0x340, 1)),
+                ..base.clone()
+            },
+            Commit {
+                rd: Some((5, 0x1101)),
+                mem: Some(MemKind::Load),
+                mem_addr: Some(0x8000_0400),
+                ..base.clone()
+            },
+            Commit {
+                mem: Some(MemKind::Store),
+                mem_addr: Some(0x8000_0400),
+                mem_data: Some(0x1101),
+                ..base.clone()
+            },
+            // Without the store data, the line says `store` and drops the address.
+            Commit {
+                mem: Some(MemKind::Store),
+                mem_addr: Some(0x8000_0400),
+                ..base.clone()
+            },
+        ];
+        for c in cases {
+            let line = c.spike_line();
+            let r = parse_spike(&line).unwrap_or_else(|| panic!("{:?} did not parse", line));
+            assert_eq!(Some(r.pc), c.pc, "{}", line);
+            assert_eq!(r.rd, c.rd, "{}", line);
+            assert_eq!(r.mem, c.mem, "{}", line);
+            assert_eq!(r.mem_data, c.mem_data, "{}", line);
+            let addr = c
+                .mem_addr
+                .filter(|_| c.mem != Some(MemKind::Store) || c.mem_data.is_some());
+            assert_eq!(r.mem_addr, addr, "{}", line);
+        }
+    }
+}
@@ -0,0 +1,257 @@
+//! Data bus accesses, rebuilt from the dbus between MemUnit and `DbusDpiBridge` /
+//! `DbusMemBridge`: each request paired with its response.
+//!
+//! Responses are matched by the `user` tag MemUnit puts on the request (rob_id, `LsuOp`,
+//! address low bits, p_rd), or in order when the trace has no `user`. A `dbus.resp.flush`
+//! drops the open requests, as the bridges clear their pipeline.
+
+use std::collections::{HashMap, VecDeque};
+
+use wellen::{SignalRef, Time, simple::Waveform};
+
+use super::pass::has_signal;
+use super::tracker::{Instruction, InstructionTracker, Stage};
+use crate::core::{Sample, Snapshot, Timebase, snapshot_at};
+
+/// Schema keys of the dbus and of MemUnit's request (rob_id, byte address, `LsuOp`).
+pub const SIGNALS: &[&str] = &[
+    "dbus.req.valid",
+    "dbus.req.ready",
+    "dbus.req.addr",
+    "dbus.req.wdata",
+    "dbus.req.wen",
+    "dbus.req.wstrb",
+    "dbus.req.user",
+    "dbus.resp.valid",
+    "dbus.resp.ready",
+    "dbus.resp.data",
+    "dbus.resp.user",
+    "dbus.resp.flush",
+    "lsu.mem_req.rob_id",
+    "lsu.mem_req.addr",
+    "lsu.mem_req.op",
+];
+
+/// `LsuOp` one-hot encodings of the loads, with their access size in bytes.
+const LOAD_SIZES: [(u64, u32); 5] = [
+    (1 << 0, 1),
+    (1 << 1, 2),
+    (1 << 2, 4),
+    (1 << 3, 1),
+    (1 << 4, 2),
+];
+
+/// Direction of a data bus access.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dir {
+    Read,
+    Write,
+}
+
+impl Dir {
+    pub fn name(self) -> &'static str {
+        match self {
+            Dir::Read => "read",
+            Dir::Write => "write",
+        }
+    }
+}
+
+/// One request on the data bus and, once seen, its response.
+pub struct Access {
+    pub req: Sample,
+    /// Position of the request sample in the scan; latencies are differences of positions.
+    pub req_pos: usize,
+    pub dir: Dir,
+    /// Word address on the bus (MemUnit clears the low two bits).
+    pub addr: u64,
+    /// Byte lanes: `wstrb` for a write, from the load's `LsuOp` and address for a read.
+    pub mask: u64,
+    pub wdata: Option<u64>,
+    pub user: Option<u64>,
+    pub rob_id: Option<u64>,
+    /// Position and `data` of the response.
+    pub resp: Option<(usize, Option<u64>)>,
+    pub flushed: bool,
+}
+
+impl Access {
+    fn read(snap: &Snapshot, s: &Sample, pos: usize) -> Option<Self> {
+        let addr = snap.u64("dbus.req.addr")?;
+        let write = snap.is_high("dbus.req.wen");
+        let mask = if write {
+            snap.u64("dbus.req.wstrb").unwrap_or(0xf)
+        } else {
+            load_mask(snap.u64("lsu.mem_req.op"), snap.u64("lsu.mem_req.addr"))
+        };
+        Some(Access {
+            req: *s,
+            req_pos: pos,
+            dir: if write { Dir::Write } else { Dir::Read },
+            addr: addr & !3,
+            mask,
+            wdata: snap.u64("dbus.req.wdata"),
+            user: snap.u64("dbus.req.user"),
+            rob_id: snap.u64("lsu.mem_req.rob_id"),
+            resp: None,
+            flushed: false,
+        })
+    }
+
+    /// Word carrying the data: `wdata`, or the response's `data`.
+    pub fn word(&self) -> Option<u64> {
+        match self.dir {
+            Dir::Write => self.wdata,
+            Dir::Read => self.resp.and_then(|(_, data)| data),
+        }
+    }
+
+    /// Address of the lowest byte accessed.
+    pub fn byte_addr(&self) -> u64 {
+        self.addr + u64::from(self.mask.trailing_zeros().min(3))
+    }
+
+    /// Bytes accessed.
+    pub fn size(&self) -> u32 {
+        self.mask.count_ones()
+    }
+
+    /// The accessed bytes of [`word`](Self::word), shifted down.
+    pub fn value(&self) -> Option<u64> {
+        let lane = self.byte_addr() - self.addr;
+        self.word()
+            .map(|w| w >> (8 * lane) & low_bytes(self.size()))
+    }
+
+    /// `(address, byte)` of each accessed byte, when the data is known.
+    pub fn bytes(&self) -> impl Iterator<Item = (u64, u8)> + '_ {
+        let word = self.word();
+        (0..4u64)
+            .filter(|b| self.mask >> b & 1 == 1)
+            .filter_map(move |b| word.map(|w| (self.addr + b, (w >> (8 * b)) as u8)))
+    }
+
+    pub fn touches(&self, byte: u64) -> bool {
+        byte & !3 == self.addr && self.mask >> (byte & 3) & 1 == 1
+    }
+
+    pub fn status(&self) -> &'static str {
+        match (self.resp, self.flushed) {
+            (Some(_), _) => "done",
+            (None, true) => "flushed",
+            (None, false) => "pending",
+        }
+    }
+}
+
+/// Byte lanes of a load: its size from `LsuOp`, shifted to the address; the whole word
+/// without them.
+fn load_mask(op: Option<u64>, addr: Option<u64>) -> u64 {
+    let size = op.and_then(|op| LOAD_SIZES.iter().find(|(o, _)| *o == op).map(|&(_, n)| n));
+    match (size, addr) {
+        (Some(n), Some(addr)) => ((1 << n) - 1) << (addr & 3),
+        _ => 0xf,
+    }
+}
+
+/// Mask of the low `n` bytes of a value; all ones from 8 bytes up.
+pub fn low_bytes(n: u32) -> u64 {
+    1u64.checked_shl(8 * n).map_or(u64::MAX, |m| m - 1)
+}
+
+/// `0x__12ab__`: the word with bytes outside `mask` blanked.
+pub fn masked_word(word: u64, mask: u64) -> String {
+    let bytes: String = (0..4)
+        .rev()
+        .map(|b| match mask >> b & 1 {
+            1 => format!("{:02x}", word >> (8 * b) & 0xff),
+            _ => "__".to_string(),
+        })
+        .collect();
+    format!("0x{}", bytes)
+}
+
+/// Every request fired in `start..=end`, in order, with responses up to the end of the trace.
+pub fn accesses(
+    wf: &Waveform,
+    tb: &Timebase,
+    sigs: &[(String, SignalRef)],
+    start: Time,
+    end: Time,
+) -> Vec<Access> {
+    let by_tag = has_signal(sigs, "dbus.req.user") && has_signal(sigs, "dbus.resp.user");
+    let samples = tb.cycles().iter().filter(|s| s.time >= start);
+
+    let mut log: Vec<Access> = Vec::new();
+    // Indices into `log` of requests without a response, oldest first.
+    let mut in_flight: VecDeque<usize> = VecDeque::new();
+    for (pos, s) in samples.enumerate() {
+        if s.time > end && in_flight.is_empty() {
+            break;
+        }
+        let snap = snapshot_at(wf, sigs, s.idx);
+        // A response answers an older request, so it goes first.
+        if snap.is_high("dbus.resp.valid") && !snap.is_low("dbus.resp.ready") {
+            let k = match snap.u64("dbus.resp.user") {
+                Some(user) if by_tag => in_flight.iter().position(|&i| log[i].user == Some(user)),
+                _ => (!in_flight.is_empty()).then_some(0),
+            };
+            if let Some(i) = k.and_then(|k| in_flight.remove(k)) {
+                log[i].resp = Some((pos, snap.u64("dbus.resp.data")));
+            }
+        }
+        if snap.is_high("dbus.resp.flush") {
+            for i in in_flight.drain(..) {
+                log[i].flushed = true;
+            }
+        }
+        if s.time <= end
+            && snap.is_high("dbus.req.valid")
+            && !snap.is_low("dbus.req.ready")
+            && let Some(access) = Access::read(&snap, s, pos)
+        {
+            in_flight.push_back(log.len());
+            log.push(access);
+        }
+    }
+    log
+}
+
+/// The load / store behind an access: the instruction in the ROB under its rob_id.
+pub struct Owners<'a>(HashMap<u64, Vec<&'a Instruction>>);
+
+impl<'a> Owners<'a> {
+    pub fn new(tracker: &'a InstructionTracker) -> Self {
+        let mut by_rob_id: HashMap<u64, Vec<&Instruction>> = HashMap::new();
+        for i in tracker.records() {
+            if let (Some(rob_id), Some(_)) = (i.rob_id, i.at(Stage::Dispatch)) {
+                by_rob_id.entry(rob_id).or_default().push(i);
+            }
+        }
+        Owners(by_rob_id)
+    }
+
+    /// Instruction dispatched under the access's rob_id and not yet gone at its request.
+    pub fn of(&self, acc: &Access) -> Option<&'a Instruction> {
+        self.0.get(&acc.rob_id?)?.iter().copied().find(|i| {
+            i.at(Stage::Dispatch)
+                .is_some_and(|d| d.time <= acc.req.time)
+                && i.last().is_none_or(|l| l.time >= acc.req.time)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn low_bytes_masks() {
+        assert_eq!(low_bytes(0), 0);
+        assert_eq!(low_bytes(1), 0xff);
+        assert_eq!(low_bytes(4), 0xffff_ffff);
+        assert_eq!(low_bytes(7), 0x00ff_ffff_ffff_ffff);
+        assert_eq!(low_bytes(8), u64::MAX);
+        assert_eq!(low_bytes(9), u64::MAX);
+    }
+}
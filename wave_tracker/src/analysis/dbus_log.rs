@@ -0,0 +1,141 @@
+use std::sync::LazyLock;
+
+use clap::{ArgMatches, Args, Command, FromArgMatches};
+
+use super::dbus::{self, Dir, Owners};
+use super::pass::{AnalysisPass, PassContext, RangeArgs};
+use super::tracker::InstructionTracker;
+use super::words;
+use crate::core::{Event, Field, NumArg};
+
+#[derive(Args)]
+struct DbusLogArgs {
+    /// Only reads or only writes
+    #[arg(long, value_enum)]
+    dir: Option<Dir>,
+
+    /// Only accesses touching this byte address; bare digits are hex (e.g. 80000400)
+    #[arg(long, value_parser = NumArg::parse_hex)]
+    addr: Option<NumArg>,
+
+    /// Only accesses of the load / store that held this rob_id (e.g. 7, 0x7)
+    #[arg(long)]
+    rob_id: Option<NumArg>,
+
+    #[command(flatten)]
+    range: RangeArgs,
+}
+
+static SIGNALS: LazyLock<Vec<&'static str>> =
+    LazyLock::new(|| [dbus::SIGNALS, InstructionTracker::SIGNALS].concat());
+
+/// `dbus-log`: every data bus request paired with its response.
+pub struct DbusLog;
+
+impl AnalysisPass for DbusLog {
+    fn name(&self) -> &'static str {
+        "dbus-log"
+    }
+
+    fn command(&self) -> Command {
+        DbusLogArgs::augment_args(Command::new(self.name()).about(
+            "Memory access log: pair each dbus request with its response by user tag (else in order), with direction, address, byte-masked data, latency and the rob_id / PC of the load or store",
+        ))
+    }
+
+    fn output(&self) -> &'static str {
+        "DBUS per request in the window (n, dir, addr, size, value, data, latency, status, rob_id, pc, disasm), then SUMMARY (reads, writes, flushed, pending, mean_latency, max_latency)"
+    }
+
+    fn signals(&self) -> &'static [&'static str] {
+        &SIGNALS
+    }
+
+    fn run(
+        &self,
+        ctx: &mut PassContext<'_>,
+        args: &ArgMatches,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let a = DbusLogArgs::from_arg_matches(args)?;
+        let (start, end) = a.range.or_last(ctx)?;
+        if !ctx.has("dbus.req.valid") || !ctx.has("dbus.req.addr") {
+            return Err("Could not find dbus.req.valid / addr".into());
+        }
+        if !ctx.has("dbus.resp.valid") {
+            ctx.out
+                .note("dbus.resp.valid not in the trace: no read data or latency")?;
+        }
+        if !ctx.has("lsu.mem_req.rob_id") {
+            ctx.out
+                .note("lsu.mem_req.rob_id not in the trace: no rob_id / PC")?;
+        }
+        let tracker = InstructionTracker::build(ctx.wf, ctx.tb, ctx.sigs, ctx.layout);
+        let (wf, tb, sigs, isa, out) = (&*ctx.wf, ctx.tb, ctx.sigs, ctx.isa, &mut *ctx.out);
+        let log = dbus::accesses(wf, tb, sigs, start, end);
+        let owners = Owners::new(&tracker);
+
+        out.note(&format!("Data bus accesses, t={}..{}:", start, end))?;
+        let (mut reads, mut writes, mut flushed, mut pending) = (0usize, 0usize, 0usize, 0usize);
+        let mut latencies = Vec::new();
+        let mut n = 0usize;
+        for acc in &log {
+            if a.dir.is_some_and(|d| d != acc.dir)
+                || a.addr.is_some_and(|b| !acc.touches(b.get()))
+                || a.rob_id.is_some_and(|r| acc.rob_id != Some(r.get()))
+            {
+                continue;
+            }
+            match acc.dir {
+                Dir::Read => reads += 1,
+                Dir::Write => writes += 1,
+            }
+            flushed += usize::from(acc.status() == "flushed");
+            pending += usize::from(acc.status() == "pending");
+            let latency = acc.resp.map(|(pos, _)| pos - acc.req_pos);
+            latencies.extend(latency);
+            let inst = owners.of(acc);
+            n += 1;
+            out.event(
+                &Event::on(&acc.req, "DBUS")
+                    .with("n", n)
+                    .with("dir", acc.dir.name())
+                    .with("addr", Field::Hex(acc.byte_addr()))
+                    .with("size", acc.size())
+                    .with("value", acc.value().map_or(Field::Null, Field::Hex))
+                    .with(
+                        "data",
+                        acc.word()
+                            .map_or(Field::Null, |w| Field::Text(dbus::masked_word(w, acc.mask))),
+                    )
+                    .with("latency", latency)
+                    .with("status", acc.status())
+                    .with("rob_id", acc.rob_id)
+                    .with(
+                        "pc",
+                        inst.and_then(|i| i.pc).map_or(Field::Null, Field::Hex),
+                    )
+                    .with(
+                        "disasm",
+                        words::disasm(&isa, inst.and_then(|i| i.pc), inst.and_then(|i| i.inst)),
+                    ),
+            )?;
+        }
+        if n == 0 {
+            out.note("  (no access in range)")?;
+        }
+        out.event(
+            &Event::new("SUMMARY")
+                .with("reads", reads)
+                .with("writes", writes)
+                .with("flushed", flushed)
+                .with("pending", pending)
+                .with(
+                    "mean_latency",
+                    (!latencies.is_empty())
+                        .then(|| latencies.iter().sum::<usize>() as f64 / latencies.len() as f64),
+                )
+                .with("max_latency", latencies.iter().max().copied()),
+        )?;
+        Ok(())
+    }
+}
@@ -1,82 +1,86 @@
-use wellen::simple::Waveform;
-use wellen::{SignalRef, Time};
+use clap::{ArgMatches, Args, Command, FromArgMatches};
+use wellen::Time;
+
+use super::pass::{AnalysisPass, PassContext, RangeArgs};
+use crate::core::{Event, Sample, show, snapshot_at};
+
+#[derive(Args)]
+struct DeadlockArgs {
+    #[command(flatten)]
+    range: RangeArgs,
+}
+
+/// `deadlock`: first PRF-IQ mismatch plus the surrounding prf_write/bypass/IQ timeline.
+pub struct Deadlock;
+
+impl AnalysisPass for Deadlock {
+    fn name(&self) -> &'static str {
+        "deadlock"
+    }
+
+    fn command(&self) -> Command {
+        DeadlockArgs::augment_args(Command::new(self.name()).about(
+            "Find first PRF-IQ mismatch, then dump prf_write/bypass/iq state for t-25..t+2 (default --to: from+5000)",
+        ))
+    }
+
+    fn output(&self) -> &'static str {
+        "FIRST_MISMATCH event (entry, src), then a STATE event per sample in t-25..t+2 (prf_write, bypass, in_fire, in_p_rs2, bank_2, entry valid/p_rs2/rs2_ready, mismatch)"
+    }
+
+    fn signals(&self) -> &'static [&'static str] {
+        &[
+            "iq.valid[i]",
+            "iq.entry[i].p_rs1",
+            "iq.entry[i].p_rs2",
+            "iq.entry[i].rs1_ready",
+            "iq.entry[i].rs2_ready",
+            "prf.bank[b].ready[i]",
+            "iq.prf_write[i].valid",
+            "iq.prf_write[i].addr",
+            "iq.bypass[i].valid",
+            "iq.bypass[i].addr",
+            "iq.in.valid",
+            "iq.in.ready",
+            "iq.in.p_rs2",
+        ]
+    }
+
+    fn run(
+        &self,
+        ctx: &mut PassContext<'_>,
+        args: &ArgMatches,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let a = DeadlockArgs::from_arg_matches(args)?;
+        let (start, end) = a.range.span(ctx.tb, 5000)?;
+        deadlock_analysis(ctx, start, end)
+    }
+}
 
 /// Deadlock analysis: find first PRF-IQ mismatch, then dump prf_write/bypass/IQ state around it.
-pub fn deadlock_analysis(
-    wf: &mut Waveform,
-    time_table: &[Time],
+fn deadlock_analysis(
+    ctx: &mut PassContext<'_>,
     start: Time,
     end: Time,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let hierarchy = wf.hierarchy();
-    let mut sigs: Vec<(String, SignalRef)> = Vec::new();
-    for var in hierarchy.iter_vars() {
-        let name = var.full_name(hierarchy);
-        if name.contains("core.iq.valids_")
-            || (name.contains("core.iq.entries_")
-                && (name.contains("p_rs1")
-                    || name.contains("p_rs2")
-                    || name.contains("rs1_ready")
-                    || name.contains("rs2_ready")))
-            || name.contains("core.isu.bank_ready_")
-            || (name.contains("core.iq.io_prf_write_")
-                && (name.contains("_valid") || name.contains("_bits_addr")))
-            || (name.contains("core.iq.io_bypass_level1_")
-                && (name.contains("_valid") || name.contains("_bits_addr")))
-            || (name.contains("core.iq.io_in_")
-                && (name.contains("valid")
-                    || name.contains("ready")
-                    || name.contains("bits_p_rs1")
-                    || name.contains("bits_p_rs2")
-                    || name.contains("bits_rs1_ready")
-                    || name.contains("bits_rs2_ready")))
-        {
-            sigs.push((name, var.signal_ref()));
-        }
-    }
-    let to_load: Vec<SignalRef> = sigs.iter().map(|(_, sr)| *sr).collect();
-    wf.load_signals(&to_load);
-
-    let get_val = |name_contains: &str, vals: &[(String, String)]| -> Option<String> {
-        vals.iter()
-            .find(|(n, _)| n.contains(name_contains))
-            .map(|(_, v)| v.clone())
-    };
+    let (wf, tb, sigs, out) = (&*ctx.wf, ctx.tb, ctx.sigs, &mut *ctx.out);
+    let layout = ctx.layout;
 
-    const PR2_BIN: &str = "000010";
-    let mut first_mismatch: Option<(Time, usize, &str)> = None;
-    for (i, &t) in time_table.iter().enumerate() {
-        if t < start || t > end {
-            continue;
-        }
-        let idx = i as u32;
-        let mut vals: Vec<(String, String)> = Vec::new();
-        for (name, sig_ref) in &sigs {
-            if let Some(sig) = wf.get_signal(*sig_ref) {
-                if let Some(offset) = sig.get_offset(idx) {
-                    let val = sig.get_value_at(&offset, 0).to_string();
-                    vals.push((name.clone(), val));
-                }
-            }
-        }
-        for entry in 0..8 {
-            let valid = get_val(&format!("iq.valids_{}", entry), &vals).map_or(false, |v| v == "1");
-            if !valid {
+    const PR2: u64 = 2;
+    let mut first_mismatch: Option<(&Sample, usize, &str)> = None;
+    for s in tb.range(start, end) {
+        let snap = snapshot_at(wf, sigs, s.idx);
+        for entry in 0..layout.iq_entries {
+            if !snap.is_high(&format!("iq.valid[{}]", entry)) {
                 continue;
             }
-            let p_rs2 = get_val(&format!("iq.entries_{}_p_rs2", entry), &vals);
-            let rs2_ready = get_val(&format!("iq.entries_{}_rs2_ready", entry), &vals)
-                .map_or(false, |v| v == "1");
-            if let Some(pr_bin) = &p_rs2 {
-                if pr_bin.trim() == PR2_BIN && !rs2_ready {
-                    let bank = 0;
-                    let idx_bank = 2;
-                    let bank_suffix = format!("bank_ready_{}_{}", bank, idx_bank);
-                    let prf_ready = get_val(&bank_suffix, &vals).map_or(false, |v| v == "1");
-                    if prf_ready {
-                        first_mismatch = Some((t, entry, "rs2"));
-                        break;
-                    }
+            let p_rs2 = snap.u64(&format!("iq.entry[{}].p_rs2", entry));
+            let rs2_ready = snap.is_high(&format!("iq.entry[{}].rs2_ready", entry));
+            if p_rs2 == Some(PR2) && !rs2_ready {
+                let (bank, idx_bank) = layout.prf_bank(PR2);
+                if snap.is_high(&format!("prf.bank[{}].ready[{}]", bank, idx_bank)) {
+                    first_mismatch = Some((s, entry, "rs2"));
+                    break;
                 }
             }
         }
@@ -85,81 +89,66 @@ pub fn deadlock_analysis(
         }
     }
 
-    let Some((t_mismatch, entry, src)) = first_mismatch else {
-        println!("No PRF-IQ mismatch found in range.");
+    let Some((s_mismatch, entry, src)) = first_mismatch else {
+        out.note("No PRF-IQ mismatch found in range.")?;
         return Ok(());
     };
 
-    println!(
-        "First PRF-IQ mismatch at t={}, entry={}, {} (PR2/sp)",
-        t_mismatch, entry, src
-    );
-    println!(
+    out.event(
+        &Event::on(s_mismatch, "FIRST_MISMATCH")
+            .with("entry", entry)
+            .with("src", src)
+            .with("p_rs", PR2),
+    )?;
+    out.note(&format!(
         "\n--- Timeline t-25 to t+2 (prf_write, bypass, IQ entry {}, in_fire) ---\n",
         entry
-    );
+    ))?;
 
+    let t_mismatch = s_mismatch.time;
     let t_start = t_mismatch.saturating_sub(25);
     let t_end = t_mismatch + 2;
 
-    for (i, &t) in time_table.iter().enumerate() {
-        if t < t_start || t > t_end {
-            continue;
-        }
-        let idx = i as u32;
-        let mut vals: Vec<(String, String)> = Vec::new();
-        for (name, sig_ref) in &sigs {
-            if let Some(sig) = wf.get_signal(*sig_ref) {
-                if let Some(offset) = sig.get_offset(idx) {
-                    let val = sig.get_value_at(&offset, 0).to_string();
-                    vals.push((name.clone(), val));
-                }
-            }
-        }
+    for s in tb.range(t_start, t_end) {
+        let t = s.time;
+        let snap = snapshot_at(wf, sigs, s.idx);
 
         let mut prf_writes: Vec<String> = Vec::new();
-        for port in 0..6 {
-            let v = get_val(&format!("iq.io_prf_write_{}_valid", port), &vals);
-            let a = get_val(&format!("iq.io_prf_write_{}_bits_addr", port), &vals);
-            if v.as_deref() == Some("1") {
-                prf_writes.push(format!("P{}:{}", port, a.as_deref().unwrap_or("?")));
+        for port in 0..layout.prf_write_ports {
+            if snap.is_high(&format!("iq.prf_write[{}].valid", port)) {
+                let a = snap.get(&format!("iq.prf_write[{}].addr", port));
+                prf_writes.push(format!("P{}:{}", port, show(a)));
             }
         }
         let mut bypass: Vec<String> = Vec::new();
-        for port in 0..6 {
-            let v = get_val(&format!("iq.io_bypass_level1_{}_valid", port), &vals);
-            let a = get_val(&format!("iq.io_bypass_level1_{}_bits_addr", port), &vals);
-            if v.as_deref() == Some("1") {
-                bypass.push(format!("B{}:{}", port, a.as_deref().unwrap_or("?")));
+        for port in 0..layout.prf_write_ports {
+            if snap.is_high(&format!("iq.bypass[{}].valid", port)) {
+                let a = snap.get(&format!("iq.bypass[{}].addr", port));
+                bypass.push(format!("B{}:{}", port, show(a)));
             }
         }
-        let in_valid = get_val("iq.io_in_valid", &vals).map_or(false, |v| v == "1");
-        let in_ready = get_val("iq.io_in_ready", &vals).map_or(false, |v| v == "1");
-        let in_fire = in_valid && in_ready;
-        let in_p_rs2 = get_val("iq.io_in_bits_p_rs2", &vals)
-            .or_else(|| get_val("iq.io_in_bits_r_p_rs2", &vals));
-
-        let e_valid = get_val(&format!("iq.valids_{}", entry), &vals).map_or(false, |v| v == "1");
-        let e_p_rs2 = get_val(&format!("iq.entries_{}_p_rs2", entry), &vals);
-        let e_rs2_ready =
-            get_val(&format!("iq.entries_{}_rs2_ready", entry), &vals).map_or(false, |v| v == "1");
-        let bank_2 = get_val("bank_ready_0_2", &vals).map_or(false, |v| v == "1");
-
-        let prf_w = if prf_writes.is_empty() {
-            ".".to_string()
-        } else {
-            prf_writes.join(" ")
-        };
-        let byp = if bypass.is_empty() {
-            ".".to_string()
-        } else {
-            bypass.join(" ")
-        };
-        let mark = if t == t_mismatch { " <-- MISMATCH" } else { "" };
-        println!(
-            "t={:4} prf_write=[{}] bypass=[{}] in_fire={} in_p_rs2={:?} bank_2={} entry{}: valid={} p_rs2={:?} rs2_ready={}{}",
-            t, prf_w, byp, in_fire, in_p_rs2, bank_2, entry, e_valid, e_p_rs2, e_rs2_ready, mark
-        );
+        let in_fire = snap.is_high("iq.in.valid") && snap.is_high("iq.in.ready");
+        let in_p_rs2 = snap.get("iq.in.p_rs2");
+
+        let e_valid = snap.is_high(&format!("iq.valid[{}]", entry));
+        let e_p_rs2 = snap.get(&format!("iq.entry[{}].p_rs2", entry));
+        let e_rs2_ready = snap.is_high(&format!("iq.entry[{}].rs2_ready", entry));
+        let (bank, idx_bank) = layout.prf_bank(PR2);
+        let bank_2 = snap.is_high(&format!("prf.bank[{}].ready[{}]", bank, idx_bank));
+
+        out.event(
+            &Event::on(s, "STATE")
+                .with("prf_write", prf_writes.join(";"))
+                .with("bypass", bypass.join(";"))
+                .with("in_fire", in_fire)
+                .with("in_p_rs2", in_p_rs2)
+                .with("bank_2", bank_2)
+                .with("entry", entry)
+                .with("valid", e_valid)
+                .with("p_rs2", e_p_rs2)
+                .with("rs2_ready", e_rs2_ready)
+                .with("mismatch", t == t_mismatch),
+        )?;
     }
     Ok(())
 }
@@ -0,0 +1,339 @@
+use std::path::PathBuf;
+use std::sync::LazyLock;
+
+use clap::{ArgMatches, Args, Command, FromArgMatches};
+use wellen::Time;
+
+use super::commits::{self, Commit, MemKind, RefCommit};
+use super::dbus;
+use super::lifecycle::insn_event;
+use super::pass::{AnalysisPass, PassContext};
+use super::rename::{self, FreeList, RenameState};
+use super::tracker::{Instruction, InstructionTracker};
+use super::words;
+use crate::core::{Event, Field, Isa, NumArg, Sink, Snapshot, snapshot_at};
+
+#[derive(Args)]
+struct DifftestArgs {
+    /// Reference commit log: Spike --log-commits text or commit-log JSON Lines
+    #[arg(long = "ref", value_name = "PATH")]
+    reference: PathBuf,
+
+    /// PC of the first commit when the trace does not show it (CoreConfig.defaultPc);
+    /// bare digits are hex
+    #[arg(long, value_parser = NumArg::parse_hex, default_value = "80000000")]
+    reset_pc: NumArg,
+}
+
+static SIGNALS: LazyLock<Vec<&'static str>> = LazyLock::new(|| {
+    [
+        commits::SIGNALS,
+        InstructionTracker::SIGNALS,
+        rename::SIGNALS,
+        dbus::SIGNALS,
+    ]
+    .concat()
+});
+
+/// `difftest`: compare the wave commit stream against a reference log.
+pub struct Difftest;
+
+impl AnalysisPass for Difftest {
+    fn name(&self) -> &'static str {
+        "difftest"
+    }
+
+    fn command(&self) -> Command {
+        DifftestArgs::augment_args(Command::new(self.name()).about(
+            "Compare the commit stream against a reference log; at the first divergence (pc, rd write, load / store, address, store data) dump the instruction's lifecycle, RMT / FreeList and source producers",
+        ))
+    }
+
+    fn output(&self) -> &'static str {
+        "DIVERGENCE (n, field, pc, disasm, wave, ref, ref_line), then INSN for the instruction, RMT (arch, p_rd) and FREELIST at its commit, and PRODUCER (src, p_rs, then the producer's INSN fields); or a note that the streams match"
+    }
+
+    fn signals(&self) -> &'static [&'static str] {
+        &SIGNALS
+    }
+
+    fn run(
+        &self,
+        ctx: &mut PassContext<'_>,
+        args: &ArgMatches,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let a = DifftestArgs::from_arg_matches(args)?;
+        let text = std::fs::read_to_string(&a.reference)
+            .map_err(|e| format!("Cannot read {}: {}", a.reference.display(), e))?;
+        let reference =
+            commits::parse_ref(&text).map_err(|e| format!("{}: {}", a.reference.display(), e))?;
+
+        let tracker = InstructionTracker::build(ctx.wf, ctx.tb, ctx.sigs, ctx.layout);
+        let mut wave = commits::commits(ctx.wf, ctx.tb, ctx.sigs, &tracker, a.reset_pc.get());
+        if ctx.has("dbus.req.valid") && ctx.has("lsu.mem_req.rob_id") {
+            let log = dbus::accesses(ctx.wf, ctx.tb, ctx.sigs, 0, Time::MAX);
+            commits::attach_accesses(&mut wave, &tracker, &log);
+        } else {
+            ctx.out.note("dbus.req.* / lsu.mem_req.rob_id not in the trace: memory addresses and store data are not compared")?;
+        }
+        let isa = ctx.isa;
+        let out = &mut *ctx.out;
+        let Some(first) = wave.first() else {
+            out.note("No commits in the trace")?;
+            return Ok(());
+        };
+        // The reference may start earlier (boot code): align on the first wave PC.
+        let skip = reference
+            .iter()
+            .position(|r| Some(r.pc) == first.pc)
+            .ok_or_else(|| {
+                format!(
+                    "First wave commit (pc {}) not found in {}",
+                    Field::from(first.pc.map(Field::Hex)),
+                    a.reference.display()
+                )
+            })?;
+        out.note(&format!(
+            "Comparing {} wave commits with {} from line {}\n",
+            wave.len(),
+            a.reference.display(),
+            reference[skip].line
+        ))?;
+
+        let pairs = wave.iter().zip(&reference[skip..]).enumerate();
+        let Some((n, c, r, field)) = pairs
+            .filter_map(|(n, (c, r))| mismatch(c, r).map(|field| (n, c, r, field)))
+            .next()
+        else {
+            let matched = wave.len().min(reference.len() - skip);
+            out.note(&format!("No divergence: {} commits match", matched))?;
+            if matched < wave.len() {
+                out.note(&format!(
+                    "The reference ends after {} commits; {} wave commits not compared",
+                    matched,
+                    wave.len() - matched
+                ))?;
+            }
+            return Ok(());
+        };
+
+        let (wave_value, ref_value) = match field {
+            "pc" => (show_hex(c.pc), show_hex(Some(r.pc))),
+            "rd" => (show_rd(c.rd), show_rd(r.rd)),
+            "mem_addr" => (show_hex(c.mem_addr), show_hex(r.mem_addr)),
+            "mem_data" => (show_hex(c.mem_data), show_hex(r.mem_data)),
+            _ => (show_mem(c.mem), show_mem(r.mem)),
+        };
+        out.event(
+            &Event::on(&c.at, "DIVERGENCE")
+                .with("n", n)
+                .with("field", field)
+                .with("pc", c.pc.map_or(Field::Null, Field::Hex))
+                .with("disasm", words::disasm(&isa, c.pc, c.inst))
+                .with("wave", wave_value)
+                .with("ref", ref_value)
+                .with("ref_line", r.line),
+        )?;
+
+        let inst = c.seq.map(|seq| &tracker.records()[seq]);
+        match inst {
+            Some(inst) => {
+                out.note("\nLifecycle:")?;
+                out.event(&insn_event(&isa, inst))?;
+            }
+            None => out.note("\nLifecycle: the tracker did not see this instruction")?,
+        }
+
+        out.note(&format!("\nRename state at t={}:", c.at.time))?;
+        let snap = snapshot_at(ctx.wf, ctx.sigs, c.at.idx);
+        dump_rename(out, &snap, c, ctx.layout.freelist_slots)?;
+
+        if let Some(inst) = inst {
+            out.note("\nSource producers:")?;
+            dump_producers(out, &isa, &tracker, inst)?;
+        }
+        Ok(())
+    }
+}
+
+/// First field in which the wave commit differs from the reference. The memory address
+/// and store data are compared when both sides know them.
+fn mismatch(c: &Commit, r: &RefCommit) -> Option<&'static str> {
+    let low = |v: u64| v & 0xffff_ffff;
+    let differs =
+        |a: Option<u64>, b: Option<u64>| matches!((a, b), (Some(a), Some(b)) if low(a) != low(b));
+    if c.pc != Some(r.pc) {
+        Some("pc")
+    } else if c.rd.map(|(rd, v)| (rd, low(v))) != r.rd.map(|(rd, v)| (rd, low(v))) {
+        Some("rd")
+    } else if c.mem != r.mem {
+        Some("mem")
+    } else if differs(c.mem_addr, r.mem_addr) {
+        Some("mem_addr")
+    } else if differs(c.mem_data, r.mem_data) {
+        Some("mem_data")
+    } else {
+        None
+    }
+}
+
+fn show_hex(v: Option<u64>) -> String {
+    v.map_or("?".into(), |v| format!("0x{:08x}", v))
+}
+
+fn show_rd(rd: Option<(u64, u64)>) -> String {
+    rd.map_or("none".into(), |(rd, v)| format!("x{}=0x{:08x}", rd, v))
+}
+
+fn show_mem(mem: Option<MemKind>) -> String {
+    mem.map_or("none", MemKind::name).into()
+}
+
+/// RMT mappings, then the free PRs (bitmap) or the ring buffer between head and tail.
+fn dump_rename(
+    out: &mut dyn Sink,
+    snap: &Snapshot,
+    c: &Commit,
+    slots: usize,
+) -> std::io::Result<()> {
+    let state = RenameState::read(snap, slots);
+    if state.rmt.is_empty() {
+        out.note("  (no RMT signals in the trace)")?;
+    }
+    for (i, pr) in state.rmt.iter().enumerate() {
+        // rmt[i] maps x(i+1).
+        out.event(
+            &Event::on(&c.at, "RMT")
+                .with("arch", i + 1)
+                .with("p_rd", *pr),
+        )?;
+    }
+
+    let show = |pr: &Option<u64>| pr.map_or("?".into(), |pr| pr.to_string());
+    match &state.free_list {
+        FreeList::Bitmap { free, .. } => {
+            let list: Vec<String> = free.iter().map(|p| p.to_string()).collect();
+            out.event(
+                &Event::on(&c.at, "FREELIST")
+                    .with("count", free.len())
+                    .with("free", list.join(" ")),
+            )?;
+        }
+        FreeList::Ring { head, tail, free } => {
+            let list: Vec<String> = free.iter().map(|(_, pr)| show(pr)).collect();
+            out.event(
+                &Event::on(&c.at, "FREELIST")
+                    .with("head", *head)
+                    .with("tail", *tail)
+                    .with("count", list.len())
+                    .with("free", list.join(" ")),
+            )?;
+        }
+        FreeList::Missing => out.note("  (no FreeList signals in the trace)")?,
+    }
+    Ok(())
+}
+
+/// For each source PR, the youngest older instruction that allocated it.
+fn dump_producers(
+    out: &mut dyn Sink,
+    isa: &Isa,
+    tracker: &InstructionTracker,
+    inst: &Instruction,
+) -> std::io::Result<()> {
+    let Some(renamed) = inst.first() else {
+        return Ok(());
+    };
+    for (src, p_rs) in [("rs1", inst.p_rs1), ("rs2", inst.p_rs2)] {
+        let Some(p_rs) = p_rs.filter(|&p| p != 0) else {
+            continue;
+        };
+        let producer = tracker.records()[..inst.seq]
+            .iter()
+            .rev()
+            .find(|i| i.p_rd == Some(p_rs));
+        match producer {
+            Some(p) => {
+                let mut ev = Event {
+                    kind: "PRODUCER",
+                    ..insn_event(isa, p)
+                };
+                ev.fields.splice(
+                    0..0,
+                    [("src".into(), src.into()), ("p_rs".into(), p_rs.into())],
+                );
+                out.event(&ev)?;
+            }
+            None => out.event(
+                &Event::on(renamed, "PRODUCER")
+                    .with("src", src)
+                    .with("p_rs", p_rs)
+                    .with("seq", Field::Null),
+            )?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Sample;
+
+    fn store(addr: Option<u64>, data: Option<u64>) -> Commit {
+        Commit {
+            at: Sample {
+                idx: 0,
+                time: 212,
+                cycle: Some(18),
+            },
+            pc: Some(0x8000_001c),
+            inst: Some(0x0032_2023),
+            next_pc: Some(0x8000_0020),
+            rd: None,
+            csr: None,
+            mem: Some(MemKind::Store),
+            mem_addr: addr,
+            mem_data: data,
+            seq: None,
+        }
+    }
+
+    fn reference(mem: Option<MemKind>, addr: Option<u64>, data: Option<u64>) -> RefCommit {
+        RefCommit {
+            line: 1,
+            pc: 0x8000_001c,
+            rd: None,
+            mem,
+            mem_addr: addr,
+            mem_data: data,
+        }
+    }
+
+    #[test]
+    fn memory_operands() {
+        let c = store(Some(0x8000_0400), Some(0x1101));
+        let r = |addr, data| reference(Some(MemKind::Store), addr, data);
+        assert_eq!(mismatch(&c, &r(Some(0x8000_0400), Some(0x1101))), None);
+        assert_eq!(
+            mismatch(&c, &r(Some(0x8000_0404), Some(0x1101))),
+            Some("mem_addr")
+        );
+        assert_eq!(
+            mismatch(&c, &r(Some(0x8000_0400), Some(0x1102))),
+            Some("mem_data")
+        );
+        // The kind goes first; an operand either side lacks is not compared.
+        assert_eq!(
+            mismatch(&c, &reference(Some(MemKind::Load), Some(0x8000_0404), None)),
+            Some("mem")
+        );
+        assert_eq!(mismatch(&c, &r(None, None)), None);
+        assert_eq!(mismatch(&store(None, None), &r(Some(0), Some(0))), None);
+        // Only the low 32 bits count, as for rd.
+        assert_eq!(
+            mismatch(&c, &r(Some(0xffff_ffff_8000_0400), Some(0x1101))),
+            None
+        );
+    }
+}
@@ -1,256 +1,230 @@
-use wellen::simple::Waveform;
-use wellen::{SignalRef, Time};
+use std::sync::LazyLock;
 
-/// Check dispatch sync: when rob_id,p_rd dispatched, did ROB+IQ+LSQ all fire? Trace LSQ lifecycle.
-pub fn dispatch_lsq_check(
-    wf: &mut Waveform,
-    time_table: &[Time],
-    rid_bin: &str,
-    pr_bin: &str,
-    start: Time,
-    end: Option<Time>,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let hierarchy = wf.hierarchy();
-    let mut sigs: Vec<(String, SignalRef)> = Vec::new();
-    for var in hierarchy.iter_vars() {
-        let name = var.full_name(hierarchy);
-        if name.contains("rob.enq_req_valid")
-            || name.contains("rob.enq_req_ready")
-            || name.contains("rob.enq_rob_id")
-            || name.contains("rob.enq_req_bits_p_rd")
-            || (name.contains("iq.io_in")
-                && (name.contains("valid")
-                    || name.contains("ready")
-                    || name.contains("rob_id")
-                    || name.contains("p_rd")
-                    || name.contains("fu_type")
-                    || name.contains("lsq_id")
-                    || name.contains("pc")))
-            || (name.contains("idu.io_in") && (name.contains("pc") || name.contains("inst")))
-            || (name.contains("ls_alloc")
-                && (name.contains("valid")
-                    || name.contains("ready")
-                    || name.contains("rob_id")
-                    || name.contains("p_rd")
-                    || name.contains("lsq_id")))
-            || (name.contains("memUnit.ls_slots_")
-                && (name.contains("rob_id")
-                    || name.contains("valid")
-                    || name.contains("data_ready")))
-            || (name.contains("memUnit.io_ls_write")
-                && (name.contains("valid") || name.contains("ready") || name.contains("lsq_id")))
-            || (name.contains("iq.entries_")
-                && (name.contains("fu_type") || name.contains("lsq_id") || name.contains("rob_id")))
-            || name.contains("iq.valids_")
-        {
-            sigs.push((name, var.signal_ref()));
-        }
+use clap::{ArgMatches, Args, Command, FromArgMatches};
+use wellen::Time;
+
+use super::pass::{AnalysisPass, PassContext, RangeArgs};
+use super::words::{self, InstWords};
+use crate::core::{Event, Field, NumArg, Value, snapshot_at};
+
+#[derive(Args)]
+struct DispatchLsqArgs {
+    /// rob_id (e.g. 7, 0x7, 0b0111)
+    rob_id: NumArg,
+
+    /// Physical register (e.g. 37, PR37, 0b100101)
+    p_rd: NumArg,
+
+    #[command(flatten)]
+    range: RangeArgs,
+}
+
+static SIGNALS: LazyLock<Vec<&'static str>> = LazyLock::new(|| {
+    [
+        &[
+            "rob.enq.valid",
+            "rob.enq.ready",
+            "rob.enq.rob_id",
+            "rob.enq.p_rd",
+            "iq.in.valid",
+            "iq.in.rob_id",
+            "iq.in.p_rd",
+            "iq.in.fu_type",
+            "iq.in.pc",
+            "lsq.alloc.valid",
+            "lsq.alloc.ready",
+            "lsq.alloc.rob_id",
+            "lsq.alloc.p_rd",
+            "lsq.alloc.lsq_id",
+            "lsq.slot[i].valid",
+            "lsq.slot[i].rob_id",
+            "lsq.slot[i].data_ready",
+            "lsq.write.valid",
+            "lsq.write.lsq_id",
+            "iq.valid[i]",
+            "iq.entry[i].rob_id",
+            "iq.entry[i].fu_type",
+            "iq.entry[i].lsq_id",
+        ],
+        words::SIGNALS,
+    ]
+    .concat()
+});
+
+/// `dispatch-lsq`: ROB/IQ/LSQ dispatch sync and LSQ slot lifecycle for one instruction.
+pub struct DispatchLsq;
+
+impl AnalysisPass for DispatchLsq {
+    fn name(&self) -> &'static str {
+        "dispatch-lsq"
+    }
+
+    fn command(&self) -> Command {
+        DispatchLsqArgs::augment_args(Command::new(self.name()).about(
+            "Check LSQ dispatch sync: when rob_id,p_rd dispatched, did ROB+IQ+LSQ all fire?",
+        ))
+    }
+
+    fn output(&self) -> &'static str {
+        "DISPATCH events (rob_enq, iq_in, ls_alloc, ls_valid, ls_ready, fu_type, lsq_id, pc, disasm) per matching enqueue, LSU_NOT_ALLOCATED when an LSU op missed LS_ALLOC, IDU_INST (pc, inst, disasm), IQ_ENTRY state after dispatch, then LSQ_SLOT events for the slot lifecycle"
+    }
+
+    fn signals(&self) -> &'static [&'static str] {
+        &SIGNALS
     }
-    let to_load: Vec<SignalRef> = sigs.iter().map(|(_, sr)| *sr).collect();
-    wf.load_signals(&to_load);
 
-    let get_val = |name_contains: &str, vals: &[(String, String)]| -> Option<String> {
-        vals.iter()
-            .find(|(n, _)| n.contains(name_contains))
-            .map(|(_, v)| v.clone())
-    };
+    fn run(
+        &self,
+        ctx: &mut PassContext<'_>,
+        args: &ArgMatches,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let a = DispatchLsqArgs::from_arg_matches(args)?;
+        let (start, end) = a.range.or_last(ctx)?;
+        dispatch_lsq_check(ctx, a.rob_id.get(), a.p_rd.get(), start, end)
+    }
+}
 
-    let rid_trim = rid_bin.trim();
-    let pr_trim = pr_bin.trim();
-    let rid_num = u32::from_str_radix(rid_trim, 2).unwrap_or(99);
-    let matches_rid = |v: Option<&String>| {
-        v.and_then(|s| u32::from_str_radix(s.trim(), 2).ok())
-            .map(|n| n == rid_num)
-            .unwrap_or(false)
-    };
-    let matches_pr = |v: Option<&String>| v.as_deref().map(|s| s.trim()) == Some(pr_trim);
+/// Check dispatch sync: when rob_id,p_rd dispatched, did ROB+IQ+LSQ all fire? Trace LSQ lifecycle.
+fn dispatch_lsq_check(
+    ctx: &mut PassContext<'_>,
+    rid: u64,
+    pr: u64,
+    start: Time,
+    end_t: Time,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (wf, tb, sigs, out) = (&*ctx.wf, ctx.tb, ctx.sigs, &mut *ctx.out);
+    let layout = ctx.layout;
+    let words = InstWords::collect(wf, tb, sigs);
 
-    let last_t = time_table.last().copied().unwrap_or(0);
-    let end_t = end.unwrap_or(last_t);
+    let matches_rid = |v: Option<&Value>| v.is_some_and(|v| *v == rid);
+    let matches_pr = |v: Option<&Value>| v.is_some_and(|v| *v == pr);
 
-    println!(
+    out.note(&format!(
         "Dispatch+LSQ check for rob_id={} p_rd={}, t={}..{}\n",
-        rid_trim, pr_trim, start, end_t
-    );
+        rid, pr, start, end_t
+    ))?;
 
-    let mut dispatch_lsq_id: Option<String> = None;
-    for (i, &t) in time_table.iter().enumerate() {
-        if t < start || t > end_t {
-            continue;
-        }
-        let idx = i as u32;
-        let mut vals: Vec<(String, String)> = Vec::new();
-        for (name, sig_ref) in &sigs {
-            if let Some(sig) = wf.get_signal(*sig_ref) {
-                if let Some(offset) = sig.get_offset(idx) {
-                    let val = sig.get_value_at(&offset, 0).to_string();
-                    vals.push((name.clone(), val));
-                }
-            }
-        }
+    const FU_LSU: u64 = 2;
+    let mut dispatch_lsq_id: Option<u64> = None;
+    for s in tb.range(start, end_t) {
+        let idx = s.idx;
+        let snap = snapshot_at(wf, sigs, idx);
 
-        let rob_valid = get_val("rob.enq_req_valid", &vals).map_or(false, |v| v == "1");
-        let rob_ready = get_val("rob.enq_req_ready", &vals).map_or(false, |v| v == "1");
-        let rob_rid = get_val("rob.enq_rob_id", &vals);
-        let rob_p_rd = get_val("rob.enq_req_bits_p_rd", &vals);
-        let iq_valid = get_val("iq.io_in_valid", &vals).map_or(false, |v| v == "1");
-        let iq_rid = get_val("iq.io_in_bits_rob_id", &vals);
-        let iq_p_rd = get_val("iq.io_in_bits_p_rd", &vals);
-        let iq_fu = get_val("iq.io_in_bits_fu_type", &vals);
-        let iq_pc = get_val("iq.io_in_bits_pc", &vals);
-        let _iq_lsq = get_val("iq.io_in_bits_lsq_id", &vals);
-        let ls_valid = get_val("ls_alloc_valid", &vals).map_or(false, |v| v == "1");
-        let ls_ready = get_val("ls_alloc_ready", &vals).map_or(false, |v| v == "1");
-        let ls_rid = get_val("ls_alloc_bits_rob_id", &vals);
-        let ls_p_rd = get_val("ls_alloc_bits_p_rd", &vals);
-        let ls_lsq_id = get_val("ls_alloc_lsq_id", &vals);
+        let rob_valid = snap.is_high("rob.enq.valid");
+        let rob_ready = snap.is_high("rob.enq.ready");
+        let rob_rid = snap.get("rob.enq.rob_id");
+        let rob_p_rd = snap.get("rob.enq.p_rd");
+        let iq_valid = snap.is_high("iq.in.valid");
+        let iq_rid = snap.get("iq.in.rob_id");
+        let iq_p_rd = snap.get("iq.in.p_rd");
+        let iq_fu = snap.get("iq.in.fu_type");
+        let iq_pc = snap.get("iq.in.pc");
+        let ls_valid = snap.is_high("lsq.alloc.valid");
+        let ls_ready = snap.is_high("lsq.alloc.ready");
+        let ls_rid = snap.get("lsq.alloc.rob_id");
+        let ls_p_rd = snap.get("lsq.alloc.p_rd");
+        let ls_lsq_id = snap.get("lsq.alloc.lsq_id");
 
-        if rob_valid && rob_ready && matches_rid(rob_rid.as_ref()) && matches_pr(rob_p_rd.as_ref())
-        {
+        if rob_valid && rob_ready && matches_rid(rob_rid) && matches_pr(rob_p_rd) {
             let rob_fire = true;
-            let iq_fire = iq_valid && matches_rid(iq_rid.as_ref()) && matches_pr(iq_p_rd.as_ref());
-            let ls_fire = ls_valid
-                && ls_ready
-                && matches_rid(ls_rid.as_ref())
-                && matches_pr(ls_p_rd.as_ref());
-            let fu_type = iq_fu.as_deref().map(|s| s.trim()).unwrap_or("?");
-            let lsq_id = ls_lsq_id.as_deref().map(|s| s.trim()).unwrap_or("?");
-            let pc_hex = iq_pc
-                .as_ref()
-                .and_then(|s| u32::from_str_radix(s.trim(), 2).ok())
-                .map(|n| format!("0x{:08x}", n))
-                .unwrap_or_else(|| "?".into());
+            let iq_fire = iq_valid && matches_rid(iq_rid) && matches_pr(iq_p_rd);
+            let ls_fire = ls_valid && ls_ready && matches_rid(ls_rid) && matches_pr(ls_p_rd);
             if ls_fire {
-                dispatch_lsq_id = Some(lsq_id.to_string());
+                dispatch_lsq_id = ls_lsq_id.and_then(Value::as_u64);
             }
-            println!(
-                "t={} DISPATCH: ROB_ENQ={} IQ_IN={} LS_ALLOC={} (valid={} ready={}) fu_type={} lsq_id={} pc={}",
-                t, rob_fire, iq_fire, ls_fire, ls_valid, ls_ready, fu_type, lsq_id, pc_hex
-            );
-            if iq_fire && !ls_fire && (fu_type == "010" || fu_type == "10") {
-                println!(
-                    "  *** MISMATCH: LSU instruction but LS_ALLOC did NOT fire! Instruction in ROB+IQ but NOT in LSQ! ***"
-                );
+            out.event(
+                &Event::on(s, "DISPATCH")
+                    .with("rob_enq", rob_fire)
+                    .with("iq_in", iq_fire)
+                    .with("ls_alloc", ls_fire)
+                    .with("ls_valid", ls_valid)
+                    .with("ls_ready", ls_ready)
+                    .with("fu_type", iq_fu)
+                    .with("lsq_id", ls_lsq_id)
+                    .with("pc", Field::hex(iq_pc))
+                    .with(
+                        "disasm",
+                        words.disasm(&ctx.isa, iq_pc.and_then(Value::as_u64)),
+                    ),
+            )?;
+            if iq_fire && !ls_fire && iq_fu.is_some_and(|v| *v == FU_LSU) {
+                // LSU instruction in ROB+IQ but not in LSQ.
+                out.event(&Event::on(s, "LSU_NOT_ALLOCATED").with("rob_id", rid))?;
             }
             if iq_fire && !ls_fire {
-                println!(
-                    "  >>> Checking IQ entry fu_type in next cycles (instruction may be LSU but ls_alloc.valid was false)"
-                );
+                out.note(
+                    "  >>> Checking IQ entry fu_type in next cycles (instruction may be LSU but ls_alloc.valid was false)",
+                )?;
             }
         }
     }
 
-    println!("\n--- IDU input inst when PC=0x80005cb0 (t=108..118) ---");
-    let pc_target = 0x80005cb0u32;
-    let pc_bin = format!("{:032b}", pc_target);
-    for (i, &t) in time_table.iter().enumerate() {
-        if t < start || t > end_t || t < 108 || t > 118 {
-            continue;
-        }
-        let idx = i as u32;
-        let mut vals: Vec<(String, String)> = Vec::new();
-        for (name, sig_ref) in &sigs {
-            if let Some(sig) = wf.get_signal(*sig_ref) {
-                if let Some(offset) = sig.get_offset(idx) {
-                    let val = sig.get_value_at(&offset, 0).to_string();
-                    vals.push((name.clone(), val));
-                }
-            }
-        }
-        let idu_pc =
-            get_val("idu.io_in_bits_pc", &vals).or_else(|| get_val("iq.io_in_bits_pc", &vals));
-        if idu_pc.as_deref().map(|s| s.trim()) == Some(pc_bin.as_str()) {
-            let inst = get_val("idu.io_in_bits_inst", &vals)
-                .or_else(|| get_val("idu.io_in_bits_r_inst", &vals));
-            let inst_hex = inst
-                .as_ref()
-                .and_then(|s| u32::from_str_radix(s.trim(), 2).ok())
-                .map(|n| format!("0x{:08x}", n))
-                .unwrap_or_else(|| "?".into());
-            println!(
-                "  t={} idu.io_in pc=0x{:08x} inst={}",
-                t, pc_target, inst_hex
-            );
+    out.note("\n--- IDU input inst when PC=0x80005cb0 (t=108..118) ---")?;
+    let pc_target = 0x80005cb0u64;
+    for s in tb.range(start.max(108), end_t.min(118)) {
+        let idx = s.idx;
+        let snap = snapshot_at(wf, sigs, idx);
+        let idu_pc = snap.get("idu.in.pc").or_else(|| snap.get("iq.in.pc"));
+        if idu_pc.is_some_and(|v| *v == pc_target) {
+            let inst = snap.get("idu.in.inst");
+            out.event(
+                &Event::on(s, "IDU_INST")
+                    .with("pc", Field::Hex(pc_target))
+                    .with("inst", Field::hex(inst))
+                    .with(
+                        "disasm",
+                        words::disasm(&ctx.isa, Some(pc_target), inst.and_then(Value::as_u64)),
+                    ),
+            )?;
         }
     }
 
-    println!(
+    out.note(&format!(
         "\n--- IQ entry state for rob_id={} after dispatch (t=113..125) ---\n",
-        rid_trim
-    );
-    for (i, &t) in time_table.iter().enumerate() {
-        if t < start || t > end_t || t < 113 || t > 125 {
-            continue;
-        }
-        let idx = i as u32;
-        let mut vals: Vec<(String, String)> = Vec::new();
-        for (name, sig_ref) in &sigs {
-            if let Some(sig) = wf.get_signal(*sig_ref) {
-                if let Some(offset) = sig.get_offset(idx) {
-                    let val = sig.get_value_at(&offset, 0).to_string();
-                    vals.push((name.clone(), val));
-                }
-            }
-        }
-        for entry in 0..8 {
-            let valid = get_val(&format!("iq.valids_{}", entry), &vals).map_or(false, |v| v == "1");
-            let e_rid = get_val(&format!("iq.entries_{}_rob_id", entry), &vals);
-            if valid && matches_rid(e_rid.as_ref()) {
-                let e_fu: String = get_val(&format!("iq.entries_{}_fu_type", entry), &vals)
-                    .as_deref()
-                    .map(|s| s.trim().to_string())
-                    .unwrap_or_else(|| "?".into());
-                let e_lsq: String = get_val(&format!("iq.entries_{}_lsq_id", entry), &vals)
-                    .as_deref()
-                    .map(|s| s.trim().to_string())
-                    .unwrap_or_else(|| "?".into());
-                println!(
-                    "t={} IQ entry{}: fu_type={} lsq_id={} (LSU=010)",
-                    t, entry, e_fu, e_lsq
-                );
+        rid
+    ))?;
+    for s in tb.range(start.max(113), end_t.min(125)) {
+        let idx = s.idx;
+        let snap = snapshot_at(wf, sigs, idx);
+        for entry in 0..layout.iq_entries {
+            let valid = snap.is_high(&format!("iq.valid[{}]", entry));
+            let e_rid = snap.get(&format!("iq.entry[{}].rob_id", entry));
+            if valid && matches_rid(e_rid) {
+                let e_fu = snap.get(&format!("iq.entry[{}].fu_type", entry));
+                let e_lsq = snap.get(&format!("iq.entry[{}].lsq_id", entry));
+                out.event(
+                    &Event::on(s, "IQ_ENTRY")
+                        .with("entry", entry)
+                        .with("fu_type", e_fu)
+                        .with("lsq_id", e_lsq),
+                )?;
             }
         }
     }
 
-    if let Some(ref lsq_id) = dispatch_lsq_id {
-        println!(
+    if let Some(lsq_num) = dispatch_lsq_id {
+        out.note(&format!(
             "\n--- Tracing LSQ slot {} (rob_id={}) lifecycle ---\n",
-            lsq_id, rid_trim
-        );
-        let lsq_num: usize = u32::from_str_radix(lsq_id.trim(), 2).unwrap_or(99) as usize;
+            lsq_num, rid
+        ))?;
 
-        for (i, &t) in time_table.iter().enumerate() {
-            if t < start || t > end_t {
-                continue;
-            }
-            let idx = i as u32;
-            let mut vals: Vec<(String, String)> = Vec::new();
-            for (name, sig_ref) in &sigs {
-                if let Some(sig) = wf.get_signal(*sig_ref) {
-                    if let Some(offset) = sig.get_offset(idx) {
-                        let val = sig.get_value_at(&offset, 0).to_string();
-                        vals.push((name.clone(), val));
-                    }
-                }
-            }
-            let slot_valid =
-                get_val(&format!("ls_slots_{}_valid", lsq_num), &vals).map_or(false, |v| v == "1");
-            let slot_rob = get_val(&format!("ls_slots_{}_rob_id", lsq_num), &vals);
-            let slot_ready = get_val(&format!("ls_slots_{}_data_ready", lsq_num), &vals)
-                .map_or(false, |v| v == "1");
-            let ls_write_valid = get_val("ls_write_valid", &vals).map_or(false, |v| v == "1");
-            let ls_write_lsq = get_val("ls_write_bits_lsq_id", &vals);
-            let write_to_slot = ls_write_valid
-                && ls_write_lsq
-                    .as_ref()
-                    .and_then(|s| u32::from_str_radix(s.trim(), 2).ok())
-                    == Some(lsq_num as u32);
+        for s in tb.range(start, end_t) {
+            let idx = s.idx;
+            let snap = snapshot_at(wf, sigs, idx);
+            let slot_valid = snap.is_high(&format!("lsq.slot[{}].valid", lsq_num));
+            let slot_rob = snap.get(&format!("lsq.slot[{}].rob_id", lsq_num));
+            let slot_ready = snap.is_high(&format!("lsq.slot[{}].data_ready", lsq_num));
+            let write_to_slot =
+                snap.is_high("lsq.write.valid") && snap.u64("lsq.write.lsq_id") == Some(lsq_num);
 
-            if slot_valid && matches_rid(slot_rob.as_ref()) {
-                println!(
-                    "t={} slot{}: valid={} data_ready={} ls_write_to_slot={}",
-                    t, lsq_num, slot_valid, slot_ready, write_to_slot
-                );
+            if slot_valid && matches_rid(slot_rob) {
+                out.event(
+                    &Event::on(s, "LSQ_SLOT")
+                        .with("slot", lsq_num)
+                        .with("valid", slot_valid)
+                        .with("data_ready", slot_ready)
+                        .with("ls_write_to_slot", write_to_slot),
+                )?;
             }
         }
     }
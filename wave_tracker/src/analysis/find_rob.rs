@@ -1,72 +1,86 @@
-use wellen::simple::Waveform;
-use wellen::{SignalRef, Time};
+use clap::{ArgMatches, Args, Command, FromArgMatches};
 
-/// Find instruction with rob_id in IQ, EXU pipeline, MemUnit.
-pub fn find_rob_id_in_pipeline(
-    wf: &mut Waveform,
-    time_table: &[Time],
-    rid_bin: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let hierarchy = wf.hierarchy();
-    let mut sigs: Vec<(String, SignalRef)> = Vec::new();
-    for var in hierarchy.iter_vars() {
-        let name = var.full_name(hierarchy);
-        if (name.contains("rob_id")
-            && (name.contains("iq.entries_")
-                || name.contains("exu.")
-                || name.contains("memUnit.ls_slots_")))
-            || (name.contains("io_issuePorts_")
-                && (name.contains("valid") || name.contains("rob_id")))
-            || (name.contains("iq.valids_"))
-        {
-            sigs.push((name, var.signal_ref()));
-        }
+use super::pass::{AnalysisPass, PassContext};
+use crate::core::{Event, NumArg, snapshot_at};
+
+#[derive(Args)]
+struct FindRobArgs {
+    /// rob_id (e.g. 7, 0x7, 0b0111)
+    rob_id: NumArg,
+}
+
+/// `find-rob`: where a rob_id sits in IQ/EXU/MemUnit over the last 200 time units.
+pub struct FindRob;
+
+impl AnalysisPass for FindRob {
+    fn name(&self) -> &'static str {
+        "find-rob"
+    }
+
+    fn command(&self) -> Command {
+        FindRobArgs::augment_args(
+            Command::new(self.name()).about("Find instruction with rob_id=X in IQ/EXU/MemUnit"),
+        )
+    }
+
+    fn output(&self) -> &'static str {
+        "one event per sample and pipeline location holding the rob_id: IQ_ENTRY, ISSUE, EXU_PIPE, LSQ_SLOT"
     }
-    let to_load: Vec<SignalRef> = sigs.iter().map(|(_, sr)| *sr).collect();
-    wf.load_signals(&to_load);
 
-    let get_val = |name_contains: &str, vals: &[(String, String)]| -> Option<String> {
-        vals.iter()
-            .find(|(n, _)| n.contains(name_contains))
-            .map(|(_, v)| v.clone())
-    };
+    fn signals(&self) -> &'static [&'static str] {
+        &[
+            "iq.valid[i]",
+            "iq.entry[i].rob_id",
+            "issue[port].valid",
+            "issue[port].rob_id",
+            "exu.pipe[i].rob_id",
+            "exu.pipe[i].p_rd",
+            "lsq.slot[i].valid",
+            "lsq.slot[i].rob_id",
+            "lsq.slot[i].p_rd",
+        ]
+    }
+
+    fn run(
+        &self,
+        ctx: &mut PassContext<'_>,
+        args: &ArgMatches,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let a = FindRobArgs::from_arg_matches(args)?;
+        find_rob_id_in_pipeline(ctx, a.rob_id.get())
+    }
+}
 
-    let rid_trim = rid_bin.trim();
-    let last_t = time_table.last().copied().unwrap_or(0);
+/// Find instruction with rob_id in IQ, EXU pipeline, MemUnit.
+fn find_rob_id_in_pipeline(
+    ctx: &mut PassContext<'_>,
+    rid: u64,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (wf, tb, sigs, out) = (&*ctx.wf, ctx.tb, ctx.sigs, &mut *ctx.out);
+    let layout = ctx.layout;
+
+    let last_t = tb.last_time();
     let start = last_t.saturating_sub(200);
 
-    println!(
-        "Finding rob_id={} (slot {}) in pipeline, t={}..{}\n",
-        rid_trim,
-        u32::from_str_radix(rid_trim, 2).unwrap_or(99),
-        start,
-        last_t
-    );
-
-    for (i, &t) in time_table.iter().enumerate() {
-        if t < start {
-            continue;
-        }
-        let idx = i as u32;
-        let mut vals: Vec<(String, String)> = Vec::new();
-        for (name, sig_ref) in &sigs {
-            if let Some(sig) = wf.get_signal(*sig_ref) {
-                if let Some(offset) = sig.get_offset(idx) {
-                    let val = sig.get_value_at(&offset, 0).to_string();
-                    vals.push((name.clone(), val));
-                }
-            }
-        }
+    out.note(&format!(
+        "Finding rob_id={} in pipeline, t={}..{}\n",
+        rid, start, last_t
+    ))?;
+
+    for s in tb.range(start, last_t) {
+        let idx = s.idx;
+        let snap = snapshot_at(wf, sigs, idx);
 
-        for entry in 0..8 {
-            let v = get_val(&format!("iq.entries_{}_rob_id", entry), &vals);
-            if v.as_deref().map(|s| s.trim()) == Some(rid_trim) {
-                let valid =
-                    get_val(&format!("iq.valids_{}", entry), &vals).map_or(false, |v| v == "1");
-                println!(
-                    "t={} IQ entry{}: rob_id={} valid={}",
-                    t, entry, rid_trim, valid
-                );
+        for entry in 0..layout.iq_entries {
+            let v = snap.get(&format!("iq.entry[{}].rob_id", entry));
+            if v.is_some_and(|v| *v == rid) {
+                let valid = snap.is_high(&format!("iq.valid[{}]", entry));
+                out.event(
+                    &Event::on(s, "IQ_ENTRY")
+                        .with("entry", entry)
+                        .with("rob_id", rid)
+                        .with("valid", valid),
+                )?;
             }
         }
 
@@ -78,35 +92,37 @@ pub fn find_rob_id_in_pipeline(
             ("div", "DIV"),
             ("sysu", "SYSU"),
         ] {
-            let v = get_val(&format!("io_issuePorts_{}_valid", port), &vals)
-                .map_or(false, |x| x == "1");
-            let r = get_val(&format!("io_issuePorts_{}_bits_rob_id", port), &vals);
-            if v && r.as_deref().map(|s| s.trim()) == Some(rid_trim) {
-                println!("t={} ISSUE {}: rob_id={} (being issued)", t, name, rid_trim);
+            let v = snap.is_high(&format!("issue[{}].valid", port));
+            let r = snap.get(&format!("issue[{}].rob_id", port));
+            if v && r.is_some_and(|v| *v == rid) {
+                out.event(&Event::on(s, "ISSUE").with("port", name).with("rob_id", rid))?;
             }
         }
 
-        for pipe in ["", "_1", "_2", "_3", "_4", "_5"] {
-            let r = get_val(&format!("exu.pipeOut_bits_r{}_rob_id", pipe), &vals);
-            if r.as_deref().map(|s| s.trim()) == Some(rid_trim) {
-                let p_rd = get_val(&format!("exu.pipeOut_bits_r{}_p_rd", pipe), &vals);
-                println!(
-                    "t={} EXU pipeOut{}: rob_id={} p_rd={:?} (in EX pipeline)",
-                    t, pipe, rid_trim, p_rd
-                );
+        for pipe in 0..layout.prf_write_ports {
+            let r = snap.get(&format!("exu.pipe[{}].rob_id", pipe));
+            if r.is_some_and(|v| *v == rid) {
+                let p_rd = snap.get(&format!("exu.pipe[{}].p_rd", pipe));
+                out.event(
+                    &Event::on(s, "EXU_PIPE")
+                        .with("pipe", pipe)
+                        .with("rob_id", rid)
+                        .with("p_rd", p_rd),
+                )?;
             }
         }
 
-        for ls in 0..8 {
-            let v = get_val(&format!("memUnit.ls_slots_{}_valid", ls), &vals)
-                .map_or(false, |x| x == "1");
-            let r = get_val(&format!("memUnit.ls_slots_{}_rob_id", ls), &vals);
-            if v && r.as_deref().map(|s| s.trim()) == Some(rid_trim) {
-                let p_rd = get_val(&format!("memUnit.ls_slots_{}_p_rd", ls), &vals);
-                println!(
-                    "t={} MemUnit ls_slot{}: rob_id={} p_rd={:?}",
-                    t, ls, rid_trim, p_rd
-                );
+        for ls in 0..layout.ls_slots {
+            let v = snap.is_high(&format!("lsq.slot[{}].valid", ls));
+            let r = snap.get(&format!("lsq.slot[{}].rob_id", ls));
+            if v && r.is_some_and(|v| *v == rid) {
+                let p_rd = snap.get(&format!("lsq.slot[{}].p_rd", ls));
+                out.event(
+                    &Event::on(s, "LSQ_SLOT")
+                        .with("slot", ls)
+                        .with("rob_id", rid)
+                        .with("p_rd", p_rd),
+                )?;
             }
         }
     }
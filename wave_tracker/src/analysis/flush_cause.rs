@@ -0,0 +1,263 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::LazyLock;
+
+use clap::{ArgMatches, Args, Command, FromArgMatches};
+
+use super::pass::{AnalysisPass, PassContext, RangeArgs};
+use super::stats::{count_high, rob_count};
+use super::tracker::{Instruction, InstructionTracker, Stage};
+use super::words;
+use crate::core::{Event, Field, snapshot_at};
+
+#[derive(Args)]
+struct FlushCauseArgs {
+    #[command(flatten)]
+    range: RangeArgs,
+}
+
+const FLUSH_SIGNALS: &[&str] = &[
+    "ifu.out.pred_next_pc",
+    "commit.redirect_pc",
+    "tile.ext_flush_req",
+    "rob.tail_ptr",
+    "iq.count",
+    "iq.valid[i]",
+    "lsq.slot[i].valid",
+];
+
+static SIGNALS: LazyLock<Vec<&'static str>> =
+    LazyLock::new(|| [InstructionTracker::SIGNALS, FLUSH_SIGNALS].concat());
+
+/// Flush cause from the opcode of the instruction that committed with its flush bit set.
+/// The BRU sets the bit on a mispredict, so a branch or jump the fetch predicted right
+/// (`mispredict` false) did not cause the flush.
+fn cause_of(inst: Option<u64>, mispredict: Option<bool>) -> &'static str {
+    match (inst.map(|w| w & 0x7f), mispredict) {
+        (Some(0x63 | 0x6f | 0x67), Some(false)) => "unknown",
+        (Some(0x63), _) => "branch",
+        (Some(0x6f), _) => "jal",
+        (Some(0x67), _) => "jalr",
+        (Some(0x73), _) => "csr",
+        (Some(0x0f), _) => "fence",
+        (Some(_), _) => "other",
+        (None, _) => "unknown",
+    }
+}
+
+/// Totals of one cause.
+#[derive(Default)]
+struct CauseTotals {
+    flushes: usize,
+    squashed: usize,
+    refill: usize,
+    /// Flushes with a commit after them (the ones `refill` sums over).
+    refilled: usize,
+}
+
+/// `flush-cause`: why each `rob.do_flush` happened and what it cost.
+pub struct FlushCause;
+
+impl AnalysisPass for FlushCause {
+    fn name(&self) -> &'static str {
+        "flush-cause"
+    }
+
+    fn command(&self) -> Command {
+        FlushCauseArgs::augment_args(Command::new(self.name()).about(
+            "Explain each rob.do_flush: the instruction that triggered it (branch / jal / jalr mispredict, CSR, external request), its PC, the redirect target, the speculative ROB / IQ / LSQ entries discarded and the cycles until the next commit",
+        ))
+    }
+
+    fn output(&self) -> &'static str {
+        "FLUSH per flush (n, cause, pc, disasm, next_pc, pred_next_pc, mispredict, redirect_pc, rob, iq, lsq, squashed, refill), then CAUSE per cause (cause, flushes, share, squashed, refill_cycles, mean_refill)"
+    }
+
+    fn signals(&self) -> &'static [&'static str] {
+        &SIGNALS
+    }
+
+    fn run(
+        &self,
+        ctx: &mut PassContext<'_>,
+        args: &ArgMatches,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let a = FlushCauseArgs::from_arg_matches(args)?;
+        let (start, end) = a.range.or_last(ctx)?;
+        if !ctx.has("rob.do_flush") {
+            return Err("Could not find rob.do_flush".into());
+        }
+        for (key, what) in [
+            (
+                "ifu.out.pred_next_pc",
+                "mispredict check: branches and jumps are named by opcode",
+            ),
+            ("commit.redirect_pc", "redirect_pc"),
+            ("rob.tail_ptr", "ROB entries discarded"),
+            ("lsq.slot[", "LSQ entries discarded"),
+        ] {
+            if !ctx.has(key) {
+                ctx.out
+                    .note(&format!("{} not in the trace: no {}", key, what))?;
+            }
+        }
+        let iq_count = ctx.has("iq.count");
+        let iq_valid = ctx.has("iq.valid[");
+        let lsq = ctx.has("lsq.slot[");
+        let tracker = InstructionTracker::build(ctx.wf, ctx.tb, ctx.sigs, ctx.layout);
+        let (wf, tb, sigs, isa, layout, out) = (
+            &*ctx.wf,
+            ctx.tb,
+            ctx.sigs,
+            ctx.isa,
+            &ctx.layout,
+            &mut *ctx.out,
+        );
+
+        // Commit and flush of every tracked instruction, by sample.
+        let committed: HashMap<u32, &Instruction> = tracker
+            .records()
+            .iter()
+            .filter_map(|i| i.at(Stage::Commit).map(|s| (s.idx, i)))
+            .collect();
+        let mut squashed: HashMap<u32, usize> = HashMap::new();
+        for i in tracker.records() {
+            if let Some(s) = &i.flushed {
+                *squashed.entry(s.idx).or_default() += 1;
+            }
+        }
+
+        let samples = tb.samples();
+        // Positions in `samples` of the commits, for the refill distance.
+        let commit_pos: Vec<usize> = samples
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| committed.contains_key(&s.idx))
+            .map(|(k, _)| k)
+            .collect();
+        let mut totals: BTreeMap<&str, CauseTotals> = BTreeMap::new();
+        let mut n = 0usize;
+        out.note(&format!("Flushes, t={}..{}:", start, end))?;
+        for (k, s) in samples.iter().enumerate() {
+            if s.time < start || s.time > end {
+                continue;
+            }
+            let snap = snapshot_at(wf, sigs, s.idx);
+            if !snap.is_high("rob.do_flush") {
+                continue;
+            }
+            // do_flush is registered: the flushing instruction committed on the edge before.
+            let prev = k.checked_sub(1).map(|p| &samples[p]);
+            let prev_snap = prev.map(|p| snapshot_at(wf, sigs, p.idx));
+            let flagged = prev_snap
+                .as_ref()
+                .is_some_and(|p| p.is_high("rob.commit.valid"));
+            let inst = prev.and_then(|p| committed.get(&p.idx).copied());
+            let external = snap.is_high("tile.ext_flush_req")
+                || prev_snap
+                    .as_ref()
+                    .is_some_and(|p| p.is_high("tile.ext_flush_req"));
+            let next_pc = prev_snap
+                .as_ref()
+                .filter(|_| flagged)
+                .and_then(|p| p.u64("rob.commit.next_pc"));
+            // What fetch predicted after the instruction, against where it went.
+            let pred_next_pc = inst
+                .and_then(|i| i.at(Stage::Fetch))
+                .and_then(|f| snapshot_at(wf, sigs, f.idx).u64("ifu.out.pred_next_pc"));
+            let mispredict = pred_next_pc.zip(next_pc).map(|(p, n)| p != n);
+            let cause = match (flagged, external) {
+                (true, _) => cause_of(inst.and_then(|i| i.inst), mispredict),
+                (false, true) => "external",
+                (false, false) => "unknown",
+            };
+
+            let iq = if iq_count {
+                snap.u64("iq.count").map(|n| n as usize)
+            } else {
+                iq_valid
+                    .then(|| count_high(&snap, layout.iq_entries, |i| format!("iq.valid[{}]", i)))
+            };
+            let lsq = lsq
+                .then(|| count_high(&snap, layout.ls_slots, |i| format!("lsq.slot[{}].valid", i)));
+            let lost = squashed.get(&s.idx).copied().unwrap_or(0);
+            // Cycles from the flush to the first commit after it.
+            let refill = commit_pos
+                .get(commit_pos.partition_point(|&c| c <= k))
+                .map(|c| c - k);
+
+            n += 1;
+            out.event(
+                &Event::on(s, "FLUSH")
+                    .with("n", n)
+                    .with("cause", cause)
+                    .with(
+                        "pc",
+                        inst.and_then(|i| i.pc).map_or(Field::Null, Field::Hex),
+                    )
+                    .with(
+                        "disasm",
+                        words::disasm(&isa, inst.and_then(|i| i.pc), inst.and_then(|i| i.inst)),
+                    )
+                    .with("next_pc", next_pc.map_or(Field::Null, Field::Hex))
+                    .with("pred_next_pc", pred_next_pc.map_or(Field::Null, Field::Hex))
+                    .with("mispredict", mispredict)
+                    .with("redirect_pc", Field::hex(snap.get("commit.redirect_pc")))
+                    .with("rob", rob_count(&snap, layout.rob_slots))
+                    .with("iq", iq)
+                    .with("lsq", lsq)
+                    .with("squashed", lost)
+                    .with("refill", refill),
+            )?;
+
+            let t = totals.entry(cause).or_default();
+            t.flushes += 1;
+            t.squashed += lost;
+            if let Some(r) = refill {
+                t.refill += r;
+                t.refilled += 1;
+            }
+        }
+
+        if n == 0 {
+            out.note("  (no flush in range)")?;
+            return Ok(());
+        }
+        out.note("\nPer cause:")?;
+        for (cause, t) in &totals {
+            out.event(
+                &Event::new("CAUSE")
+                    .with("cause", *cause)
+                    .with("flushes", t.flushes)
+                    .with("share", t.flushes as f64 / n as f64)
+                    .with("squashed", t.squashed)
+                    .with("refill_cycles", t.refill)
+                    .with(
+                        "mean_refill",
+                        (t.refilled > 0).then(|| t.refill as f64 / t.refilled as f64),
+                    ),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn causes() {
+        // bnez s1, +8; j -52; csrw mscratch, s0; fence; addi
+        let (bnez, j, csrw, fence, addi) =
+            (0x00049463, 0xfcdff06f, 0x34041073, 0x0ff0000f, 0x00108093);
+        assert_eq!(cause_of(Some(bnez), Some(true)), "branch");
+        assert_eq!(cause_of(Some(bnez), None), "branch");
+        assert_eq!(cause_of(Some(bnez), Some(false)), "unknown");
+        assert_eq!(cause_of(Some(j), Some(true)), "jal");
+        assert_eq!(cause_of(Some(j), Some(false)), "unknown");
+        assert_eq!(cause_of(Some(csrw), Some(false)), "csr");
+        assert_eq!(cause_of(Some(fence), None), "fence");
+        assert_eq!(cause_of(Some(addi), None), "other");
+        assert_eq!(cause_of(None, Some(true)), "unknown");
+    }
+}
@@ -0,0 +1,257 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+use clap::{ArgMatches, Args, Command, FromArgMatches};
+
+use super::pass::{AnalysisPass, PassContext, RangeArgs};
+use super::tracker::{Instruction, InstructionTracker, Outcome, Stage};
+use crate::core::{Isa, Sample};
+
+/// Kanata stage names, for the stages shown in the viewer (the issue queue enqueue fires
+/// with dispatch, so it has no lane of its own).
+const STAGES: [(Stage, &str); 9] = [
+    (Stage::Fetch, "F"),
+    (Stage::Decode, "D"),
+    (Stage::Rename, "Rn"),
+    (Stage::Dispatch, "Ds"),
+    (Stage::Issue, "Is"),
+    (Stage::Exec, "Ex"),
+    (Stage::Mem, "Mem"),
+    (Stage::Writeback, "Wb"),
+    (Stage::Commit, "Cm"),
+];
+
+#[derive(Args)]
+struct KanataArgs {
+    /// Kanata log to write (open it in Konata)
+    path: PathBuf,
+
+    #[command(flatten)]
+    range: RangeArgs,
+}
+
+/// `export-kanata`: write the reconstructed lifecycles as a Kanata log for Konata.
+pub struct ExportKanata;
+
+impl AnalysisPass for ExportKanata {
+    fn name(&self) -> &'static str {
+        "export-kanata"
+    }
+
+    fn command(&self) -> Command {
+        KanataArgs::augment_args(Command::new(self.name()).about(
+            "Write instruction lifecycles as a Kanata log (F/D/Rn/Ds/Is/Ex/Mem/Wb/Cm) for the Konata pipeline viewer",
+        ))
+    }
+
+    fn output(&self) -> &'static str {
+        "the Kanata file at PATH (instructions first seen in the window, labelled with PC and disassembly, retired or flushed), then a summary"
+    }
+
+    fn signals(&self) -> &'static [&'static str] {
+        InstructionTracker::SIGNALS
+    }
+
+    fn run(
+        &self,
+        ctx: &mut PassContext<'_>,
+        args: &ArgMatches,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let a = KanataArgs::from_arg_matches(args)?;
+        let (start, end) = a.range.or_last(ctx)?;
+        let tracker = InstructionTracker::build(ctx.wf, ctx.tb, ctx.sigs, ctx.layout);
+        let selected: Vec<&Instruction> = tracker
+            .records()
+            .iter()
+            .filter(|i| i.first().is_some_and(|s| (start..=end).contains(&s.time)))
+            .collect();
+
+        let file = File::create(&a.path)
+            .map_err(|e| format!("Cannot create {}: {}", a.path.display(), e))?;
+        let mut w = BufWriter::new(file);
+        write_kanata(&mut w, &ctx.isa, &selected, ctx.tb.has_cycles())?;
+        w.flush()?;
+
+        let committed = selected
+            .iter()
+            .filter(|i| i.outcome() == Outcome::Committed)
+            .count();
+        let flushed = selected
+            .iter()
+            .filter(|i| i.outcome() == Outcome::Flushed)
+            .count();
+        ctx.out.note(&format!(
+            "Wrote {} instructions ({} retired, {} flushed, t={}..{}) to {}",
+            selected.len(),
+            committed,
+            flushed,
+            start,
+            end,
+            a.path.display()
+        ))?;
+        Ok(())
+    }
+}
+
+/// Left-pane label: PC and disassembly.
+fn label(isa: &Isa, inst: &Instruction) -> String {
+    let pc = inst.pc.map_or("?".into(), |pc| format!("{:08x}", pc));
+    match inst.inst {
+        Some(word) => format!("{}: {}", pc, isa.disasm(inst.pc.unwrap_or(0), word as u32)),
+        None => pc,
+    }
+}
+
+/// Hover detail: the identifiers the other passes take.
+fn detail(inst: &Instruction) -> String {
+    let show = |v: Option<u64>| v.map_or("?".into(), |v| v.to_string());
+    format!(
+        "seq={} epoch={} rob_id={} rd={} p_rd={} old_p_rd={} port={}",
+        inst.seq,
+        inst.epoch,
+        show(inst.rob_id),
+        show(inst.rd_index),
+        show(inst.p_rd),
+        show(inst.old_p_rd),
+        inst.port.unwrap_or("?")
+    )
+}
+
+/// Kanata clock of a sample: its cycle, with the edges during reset at cycle 0 so the
+/// clock never runs backwards; the time when the trace has no cycle numbers.
+fn tick(s: &Sample, clocked: bool) -> u64 {
+    if clocked {
+        s.cycle.unwrap_or(0)
+    } else {
+        s.time
+    }
+}
+
+/// Kanata 0004: per instruction `I` (start), `L` (labels), `S` (stage start, ending the
+/// previous one) and `R` (retire, or flush with type 1), ordered by cycle with `C`
+/// advancing the clock. Instructions get file ids 0.. in fetch order.
+fn write_kanata(
+    w: &mut impl Write,
+    isa: &Isa,
+    insts: &[&Instruction],
+    clocked: bool,
+) -> io::Result<()> {
+    let stamp = |s: &Sample| tick(s, clocked);
+    let mut lines: Vec<(u64, String)> = Vec::new();
+    let mut retired = 0;
+    for (id, inst) in insts.iter().enumerate() {
+        let Some(first) = inst.first() else {
+            continue;
+        };
+        lines.push((stamp(first), format!("I\t{}\t{}\t0", id, inst.seq)));
+        lines.push((stamp(first), format!("L\t{}\t0\t{}", id, label(isa, inst))));
+        lines.push((stamp(first), format!("L\t{}\t1\t{}", id, detail(inst))));
+        // Commit shows for its cycle; a flush ends the instruction on the flush edge.
+        let retire = match (inst.at(Stage::Commit), inst.flushed.as_ref()) {
+            (Some(c), _) => {
+                retired += 1;
+                Some((stamp(c) + 1, format!("R\t{}\t{}\t0", id, retired - 1)))
+            }
+            (None, Some(f)) => Some((stamp(f), format!("R\t{}\t{}\t1", id, id))),
+            (None, None) => None,
+        };
+        let end = retire.as_ref().map_or(u64::MAX, |(c, _)| *c);
+        for (stage, name) in STAGES {
+            // Stages after the end (a store's memory write after commit) are not drawn.
+            if let Some(s) = inst.at(stage).filter(|s| stamp(s) < end) {
+                lines.push((stamp(s), format!("S\t{}\t0\t{}", id, name)));
+            }
+        }
+        lines.extend(retire);
+    }
+    // Stable: an instruction's own lines keep their order within a cycle.
+    lines.sort_by_key(|(cycle, _)| *cycle);
+
+    writeln!(w, "Kanata\t0004")?;
+    let mut now = lines.first().map_or(0, |(c, _)| *c);
+    writeln!(w, "C=\t{}", now)?;
+    for (cycle, line) in lines {
+        if cycle > now {
+            writeln!(w, "C\t{}", cycle - now)?;
+            now = cycle;
+        }
+        writeln!(w, "{}", line)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(cycle: Option<u64>, time: u64) -> Sample {
+        Sample {
+            idx: time as u32,
+            time,
+            cycle,
+        }
+    }
+
+    fn write(insts: &[&Instruction], clocked: bool) -> String {
+        let mut out = Vec::new();
+        write_kanata(&mut out, &Isa::default(), insts, clocked).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    /// A nop fetched during reset and committed at c2, and an instruction flushed at c3.
+    fn pair() -> (Instruction, Instruction) {
+        let mut a = Instruction::default();
+        (a.pc, a.inst, a.rob_id) = (Some(0x8000_0000), Some(0x13), Some(0));
+        a.reach(Stage::Fetch, &at(None, 5));
+        a.reach(Stage::Decode, &at(Some(0), 12));
+        a.reach(Stage::Dispatch, &at(Some(1), 20));
+        a.reach(Stage::Commit, &at(Some(2), 30));
+        let mut b = Instruction::default();
+        (b.seq, b.pc, b.flushed) = (1, Some(0x8000_0004), Some(at(Some(3), 40)));
+        b.reach(Stage::Fetch, &at(Some(1), 20));
+        (a, b)
+    }
+
+    #[test]
+    fn reset_edges_draw_at_cycle_zero() {
+        let (a, b) = pair();
+        let detail = |seq, rob_id| {
+            format!(
+                "seq={} epoch=0 rob_id={} rd=? p_rd=? old_p_rd=? port=?",
+                seq, rob_id
+            )
+        };
+        let expected = [
+            "Kanata\t0004",
+            "C=\t0",
+            "I\t0\t0\t0",
+            "L\t0\t0\t80000000: nop",
+            &format!("L\t0\t1\t{}", detail(0, "0")),
+            "S\t0\t0\tF",
+            "S\t0\t0\tD",
+            "C\t1",
+            "S\t0\t0\tDs",
+            "I\t1\t1\t0",
+            "L\t1\t0\t80000004",
+            &format!("L\t1\t1\t{}", detail(1, "?")),
+            "S\t1\t0\tF",
+            "C\t1",
+            "S\t0\t0\tCm",
+            // Retired the cycle after commit; the flushed one ends on the flush edge.
+            "C\t1",
+            "R\t0\t0\t0",
+            "R\t1\t1\t1",
+        ];
+        assert_eq!(write(&[&a, &b], true), expected.join("\n") + "\n");
+    }
+
+    #[test]
+    fn unclocked_uses_time() {
+        let (a, _) = pair();
+        let out = write(&[&a], false);
+        let clock: Vec<&str> = out.lines().filter(|l| l.starts_with('C')).collect();
+        // Fetch at t=5, decode 12, dispatch 20, commit 30, retire 31.
+        assert_eq!(clock, ["C=\t5", "C\t7", "C\t8", "C\t10", "C\t1"]);
+    }
+}
@@ -0,0 +1,115 @@
+use clap::{ArgMatches, Args, Command, FromArgMatches};
+
+use super::pass::{AnalysisPass, PassContext, RangeArgs};
+use super::tracker::{Instruction, InstructionTracker, Outcome, Stage, stamp};
+use super::words;
+use crate::core::{Event, Field, Isa, NumArg, Sample};
+
+#[derive(Args)]
+struct LifecycleArgs {
+    /// Only instructions that held this rob_id (e.g. 7, 0x7)
+    #[arg(long)]
+    rob_id: Option<NumArg>,
+
+    /// Only instructions at this PC; bare digits are hex (e.g. 80005c9c)
+    #[arg(long, value_parser = NumArg::parse_hex)]
+    pc: Option<NumArg>,
+
+    /// Only instructions that allocated this physical register (e.g. 37, PR37)
+    #[arg(long)]
+    p_rd: Option<NumArg>,
+
+    /// Only instructions squashed by a flush
+    #[arg(long)]
+    flushed: bool,
+
+    #[command(flatten)]
+    range: RangeArgs,
+}
+
+/// `lifecycle`: every dynamic instruction with the cycle of each pipeline stage.
+pub struct Lifecycle;
+
+impl AnalysisPass for Lifecycle {
+    fn name(&self) -> &'static str {
+        "lifecycle"
+    }
+
+    fn command(&self) -> Command {
+        LifecycleArgs::augment_args(Command::new(self.name()).about(
+            "Reconstruct per-instruction lifecycles (fetch .. commit or flush) keyed by rob_id and epoch",
+        ))
+    }
+
+    fn output(&self) -> &'static str {
+        "INSN events (seq, epoch, rob_id, pc, inst, disasm, rd_index, p_rd, old_p_rd, p_rs1, p_rs2, lsq_id, port, then the cycle of each stage reached: fetch, decode, rename, dispatch, iq_enq, issue, exec, mem, writeback, commit or flush, and outcome) for instructions first seen in the window, then a summary"
+    }
+
+    fn signals(&self) -> &'static [&'static str] {
+        InstructionTracker::SIGNALS
+    }
+
+    fn run(
+        &self,
+        ctx: &mut PassContext<'_>,
+        args: &ArgMatches,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let a = LifecycleArgs::from_arg_matches(args)?;
+        let (start, end) = a.range.or_last(ctx)?;
+        let tracker = InstructionTracker::build(ctx.wf, ctx.tb, ctx.sigs, ctx.layout);
+
+        let selected: Vec<&Instruction> = tracker
+            .records()
+            .iter()
+            .filter(|i| i.first().is_some_and(|s| (start..=end).contains(&s.time)))
+            .filter(|i| a.rob_id.is_none_or(|r| i.rob_id == Some(r.get())))
+            .filter(|i| a.pc.is_none_or(|pc| i.pc == Some(pc.get())))
+            .filter(|i| a.p_rd.is_none_or(|p| i.p_rd == Some(p.get())))
+            .filter(|i| !a.flushed || i.outcome() == Outcome::Flushed)
+            .collect();
+
+        let isa = ctx.isa;
+        let out = &mut *ctx.out;
+        out.note(&format!("Instruction lifecycles, t={}..{}\n", start, end))?;
+        let mut counts = [0usize; 3];
+        for inst in &selected {
+            counts[inst.outcome() as usize] += 1;
+            out.event(&insn_event(&isa, inst))?;
+        }
+        out.note(&format!(
+            "\n{} instructions: {} committed, {} flushed, {} in flight",
+            selected.len(),
+            counts[Outcome::Committed as usize],
+            counts[Outcome::Flushed as usize],
+            counts[Outcome::InFlight as usize]
+        ))?;
+        Ok(())
+    }
+}
+
+pub(super) fn insn_event(isa: &Isa, inst: &Instruction) -> Event {
+    let first = inst.first().copied().unwrap_or(Sample {
+        idx: 0,
+        time: 0,
+        cycle: None,
+    });
+    let mut ev = Event::on(&first, "INSN")
+        .with("seq", inst.seq)
+        .with("epoch", inst.epoch)
+        .with("rob_id", inst.rob_id)
+        .with("pc", inst.pc.map_or(Field::Null, Field::Hex))
+        .with("inst", inst.inst.map_or(Field::Null, Field::Hex))
+        .with("disasm", words::disasm(isa, inst.pc, inst.inst))
+        .with("rd_index", inst.rd_index)
+        .with("p_rd", inst.p_rd)
+        .with("old_p_rd", inst.old_p_rd)
+        .with("p_rs1", inst.p_rs1)
+        .with("p_rs2", inst.p_rs2)
+        .with("lsq_id", inst.lsq_id)
+        .with("port", inst.port);
+    for stage in Stage::ALL {
+        ev = ev.with(stage.name(), inst.at(stage).map(stamp));
+    }
+    ev.with("flush", inst.flushed.as_ref().map(stamp))
+        .with("outcome", inst.outcome().name())
+}
@@ -0,0 +1,294 @@
+use std::sync::LazyLock;
+
+use clap::{ArgMatches, Args, Command, FromArgMatches};
+
+use super::dbus::{self, Access, Dir, Owners};
+use super::pass::{AnalysisPass, PassContext, RangeArgs};
+use super::shadow::{ImageArgs, ShadowMem, Writer};
+use super::tracker::{InstructionTracker, Outcome};
+use super::words;
+use crate::core::{Event, Field, Isa, NumArg, TimeArg};
+
+static SIGNALS: LazyLock<Vec<&'static str>> =
+    LazyLock::new(|| [dbus::SIGNALS, InstructionTracker::SIGNALS].concat());
+
+#[derive(Args)]
+struct MemArgs {
+    /// Byte address; bare digits are hex (e.g. 87ffff68)
+    #[arg(value_parser = NumArg::parse_hex)]
+    addr: NumArg,
+
+    /// Time to show: cycle (c1234), time with unit (1234ns) or raw timescale units
+    #[arg(long)]
+    at: TimeArg,
+
+    /// Bytes to show, 1 to 8
+    #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u64).range(1..=8))]
+    size: u64,
+
+    #[command(flatten)]
+    image: ImageArgs,
+}
+
+/// `mem`: shadow memory contents at a time, and the stores that wrote them.
+pub struct Mem;
+
+impl AnalysisPass for Mem {
+    fn name(&self) -> &'static str {
+        "mem"
+    }
+
+    fn command(&self) -> Command {
+        MemArgs::augment_args(Command::new(self.name()).about(
+            "Value at a memory address at a time, from the dbus writes before it (optionally over an ELF / raw image), with the store that last wrote each byte",
+        ))
+    }
+
+    fn output(&self) -> &'static str {
+        "MEM (addr, size, value, data) at the last sample <= time, then STORE per store that wrote one of the bytes (addr, size, value, rob_id, pc, disasm) and IMAGE (bytes) for bytes still from the image"
+    }
+
+    fn signals(&self) -> &'static [&'static str] {
+        &SIGNALS
+    }
+
+    fn run(
+        &self,
+        ctx: &mut PassContext<'_>,
+        args: &ArgMatches,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let a = MemArgs::from_arg_matches(args)?;
+        let time = a.at.resolve(ctx.tb)?;
+        if !ctx.has("dbus.req.") {
+            return Err("Could not find dbus.req signals".into());
+        }
+        let mut mem = a.image.shadow()?;
+        let Some(s) = ctx.tb.at_or_before(time) else {
+            ctx.out
+                .note(&format!("No sample <= {} in waveform", a.at))?;
+            return Ok(());
+        };
+        let tracker = InstructionTracker::build(ctx.wf, ctx.tb, ctx.sigs, ctx.layout);
+        let owners = Owners::new(&tracker);
+        let (isa, out) = (ctx.isa, &mut *ctx.out);
+
+        let log = dbus::accesses(ctx.wf, ctx.tb, ctx.sigs, 0, s.time);
+        mem.replay(&log, s.time);
+
+        let addr = a.addr.get();
+        out.event(
+            &Event::on(s, "MEM")
+                .with("addr", Field::Hex(addr))
+                .with("size", a.size)
+                .with(
+                    "value",
+                    mem.value(addr, a.size).map_or(Field::Null, Field::Hex),
+                )
+                .with("data", mem.text(addr, a.size)),
+        )?;
+
+        let mut stores: Vec<usize> = Vec::new();
+        let mut from_image = 0usize;
+        for k in 0..a.size {
+            match mem.get(addr + k) {
+                Some((_, Writer::Store(i))) if !stores.contains(&i) => stores.push(i),
+                Some((_, Writer::Image)) => from_image += 1,
+                _ => {}
+            }
+        }
+        stores.sort_unstable();
+        if stores.is_empty() && from_image == 0 {
+            out.note("  no store to these bytes before this time, and not in the image")?;
+        }
+        for &i in &stores {
+            let store = &log[i];
+            out.event(&with_access(
+                Event::on(&store.req, "STORE"),
+                &isa,
+                &owners,
+                store,
+            ))?;
+        }
+        if from_image > 0 {
+            out.event(&Event::new("IMAGE").with("bytes", from_image))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+struct MemCheckArgs {
+    /// Report every mismatching load, not only the first
+    #[arg(long)]
+    all: bool,
+
+    #[command(flatten)]
+    image: ImageArgs,
+
+    #[command(flatten)]
+    range: RangeArgs,
+}
+
+/// `mem-check`: every load response against the shadow memory.
+pub struct MemCheck;
+
+impl AnalysisPass for MemCheck {
+    fn name(&self) -> &'static str {
+        "mem-check"
+    }
+
+    fn command(&self) -> Command {
+        MemCheckArgs::augment_args(Command::new(self.name()).about(
+            "Check each load response against shadow memory: wrong data (differs from what the dbus writes left in memory) or stale (misses an older store, in program order); first mismatch with the store that should have been seen",
+        ))
+    }
+
+    fn output(&self) -> &'static str {
+        "MISMATCH (kind, addr, size, value, rob_id, pc, disasm, expected) for the first bad load (all with --all), each followed by the STORE that wrote the expected bytes; then SUMMARY (loads, checked, wrong_data, stale)"
+    }
+
+    fn signals(&self) -> &'static [&'static str] {
+        &SIGNALS
+    }
+
+    fn run(
+        &self,
+        ctx: &mut PassContext<'_>,
+        args: &ArgMatches,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let a = MemCheckArgs::from_arg_matches(args)?;
+        let (start, end) = a.range.or_last(ctx)?;
+        if !ctx.has("dbus.req.") || !ctx.has("dbus.resp.data") {
+            return Err("Could not find dbus.req / dbus.resp.data signals".into());
+        }
+        let program_order = ctx.has("lsu.mem_req.rob_id");
+        let image = a.image.shadow()?;
+        let tracker = InstructionTracker::build(ctx.wf, ctx.tb, ctx.sigs, ctx.layout);
+        let owners = Owners::new(&tracker);
+        let (isa, out) = (ctx.isa, &mut *ctx.out);
+        // Stores before the window still shape memory: replay from the start, check in the window.
+        let log = dbus::accesses(ctx.wf, ctx.tb, ctx.sigs, 0, end);
+        let checks = |acc: &Access| acc.dir == Dir::Read && acc.req.time >= start;
+        if !program_order {
+            out.note("lsu.mem_req.rob_id not in the trace: no stale-load check")?;
+        }
+        out.note(&format!(
+            "Checking loads against shadow memory, t={}..{}",
+            start, end
+        ))?;
+
+        // (load, kind, writer of the differing bytes, expected bytes)
+        let mut bad: Vec<(usize, &str, Option<Writer>, String)> = Vec::new();
+
+        // Memory as the bus left it: writes in request order.
+        let mut mem = image.clone();
+        let mut checked = 0usize;
+        for (k, acc) in log.iter().enumerate() {
+            if checks(acc) && acc.word().is_some() {
+                checked += 1;
+                if let Some((writer, expected)) = compare(&mem, acc) {
+                    bad.push((k, "wrong_data", writer, expected));
+                }
+            }
+            mem.apply(k, acc);
+        }
+
+        // Memory as the program sees it: committed stores and loads in program order.
+        if program_order {
+            let mut committed: Vec<(usize, usize)> = log
+                .iter()
+                .enumerate()
+                .filter_map(|(k, acc)| {
+                    let inst = owners.of(acc)?;
+                    (inst.outcome() == Outcome::Committed).then_some((inst.seq, k))
+                })
+                .collect();
+            committed.sort_unstable();
+            let mut mem = image.clone();
+            for &(_, k) in &committed {
+                let acc = &log[k];
+                if checks(acc)
+                    && acc.word().is_some()
+                    && !bad.iter().any(|b| b.0 == k)
+                    && let Some((writer, expected)) = compare(&mem, acc)
+                {
+                    bad.push((k, "stale", writer, expected));
+                }
+                mem.apply(k, acc);
+            }
+        }
+        bad.sort_by_key(|b| b.0);
+
+        let loads = log.iter().filter(|acc| checks(acc)).count();
+        let count = |kind: &str| bad.iter().filter(|b| b.1 == kind).count();
+        if bad.is_empty() {
+            out.note("  every load matches the shadow memory")?;
+        }
+        for (k, kind, writer, expected) in bad.iter().take(if a.all { bad.len() } else { 1 }) {
+            let acc = &log[*k];
+            out.event(
+                &with_access(
+                    Event::on(&acc.req, "MISMATCH").with("kind", *kind),
+                    &isa,
+                    &owners,
+                    acc,
+                )
+                .with("expected", expected.clone()),
+            )?;
+            match writer {
+                Some(Writer::Store(i)) => {
+                    let store = &log[*i];
+                    out.event(&with_access(
+                        Event::on(&store.req, "STORE"),
+                        &isa,
+                        &owners,
+                        store,
+                    ))?
+                }
+                Some(Writer::Image) => out.note("  expected bytes come from the image")?,
+                None => {}
+            }
+        }
+        out.event(
+            &Event::new("SUMMARY")
+                .with("loads", loads)
+                .with("checked", checked)
+                .with("wrong_data", count("wrong_data"))
+                .with("stale", count("stale")),
+        )?;
+        Ok(())
+    }
+}
+
+/// Compare the bytes a read returned with `mem`; on a mismatch, the writer of the last
+/// differing byte and the expected bytes (`??` where the shadow does not know them).
+fn compare(mem: &ShadowMem, acc: &Access) -> Option<(Option<Writer>, String)> {
+    let mut writer = None;
+    let mut differs = false;
+    for (addr, b) in acc.bytes() {
+        if let Some((expected, w)) = mem.get(addr)
+            && expected != b
+        {
+            differs = true;
+            writer = Some(w);
+        }
+    }
+    differs.then(|| (writer, mem.text(acc.byte_addr(), u64::from(acc.size()))))
+}
+
+/// `ev` with the access and the load / store it belongs to.
+fn with_access(ev: Event, isa: &Isa, owners: &Owners, acc: &Access) -> Event {
+    let inst = owners.of(acc);
+    ev.with("addr", Field::Hex(acc.byte_addr()))
+        .with("size", acc.size())
+        .with("value", acc.value().map_or(Field::Null, Field::Hex))
+        .with("rob_id", acc.rob_id)
+        .with(
+            "pc",
+            inst.and_then(|i| i.pc).map_or(Field::Null, Field::Hex),
+        )
+        .with(
+            "disasm",
+            words::disasm(isa, inst.and_then(|i| i.pc), inst.and_then(|i| i.inst)),
+        )
+}
@@ -1,32 +1,97 @@
 //! RTL-specific analysis passes over loaded waveforms (nzea pipeline debugging).
-#![allow(clippy::all)]
+//!
+//! Each pass is one CLI subcommand; add new ones to [`registry`]. The passes that predate
+//! the registry still carry `#[allow(clippy::all)]` until they are cleaned up.
 
+mod bp_stats;
+mod browse;
+#[allow(clippy::all)]
 mod bug;
+mod commit_log;
+mod commits;
+mod dbus;
+mod dbus_log;
+#[allow(clippy::all)]
 mod deadlock;
+#[allow(clippy::all)]
 mod deadlock_tail;
+mod difftest;
+#[allow(clippy::all)]
 mod dispatch_lsq;
+#[allow(clippy::all)]
 mod enq_match;
+#[allow(clippy::all)]
 mod find_rob;
+mod flush_cause;
+mod kanata;
+mod lifecycle;
+mod mem;
+mod pass;
+#[allow(clippy::all)]
 mod prf_iq;
+mod regs;
+mod rename;
+mod rename_check;
+mod rename_model;
+#[allow(clippy::all)]
 mod rob_flush;
+#[allow(clippy::all)]
 mod scan;
+mod shadow;
+mod stats;
+mod store_check;
+#[allow(clippy::all)]
 mod timeline;
+mod top_down;
+#[allow(clippy::all)]
 mod trace_p_rd;
+#[allow(clippy::all)]
 mod trace_pc;
+#[allow(clippy::all)]
 mod trace_rob;
+mod tracker;
+#[allow(clippy::all)]
 mod who_find;
+mod words;
 
-pub use bug::bug_scan_pr_in_both;
-pub use deadlock::deadlock_analysis;
-pub use deadlock_tail::deadlock_tail;
-pub use dispatch_lsq::dispatch_lsq_check;
-pub use enq_match::find_enq_rob_id_p_rd;
-pub use find_rob::find_rob_id_in_pipeline;
-pub use prf_iq::prf_iq_mismatch_scan;
-pub use rob_flush::{flush_sync_scan, rob_iq_desync_scan};
-pub use scan::scan_time_range;
-pub use timeline::timeline_trace;
-pub use trace_p_rd::trace_p_rd_timeline;
-pub use trace_pc::trace_pc_timeline;
-pub use trace_rob::trace_rob_id_timeline;
-pub use who_find::{find_p_rd_in_pipeline, who_produces_pr};
+pub use pass::{AnalysisPass, PassContext, RangeArgs};
+pub use tracker::{Instruction, InstructionTracker, Outcome, PORTS, Stage};
+
+/// All passes, in `--help` order.
+pub fn registry() -> Vec<Box<dyn AnalysisPass>> {
+    vec![
+        Box::new(browse::List),
+        Box::new(browse::At),
+        Box::new(regs::Regs),
+        Box::new(scan::Scan),
+        Box::new(bug::BugScan),
+        Box::new(timeline::Timeline),
+        Box::new(prf_iq::PrfIqMismatch),
+        Box::new(deadlock::Deadlock),
+        Box::new(deadlock_tail::DeadlockTail),
+        Box::new(who_find::WhoProduces),
+        Box::new(who_find::FindPrd),
+        Box::new(find_rob::FindRob),
+        Box::new(trace_rob::TraceRob),
+        Box::new(trace_p_rd::TracePrd),
+        Box::new(enq_match::EnqMatch),
+        Box::new(dispatch_lsq::DispatchLsq),
+        Box::new(trace_pc::TracePc),
+        Box::new(lifecycle::Lifecycle),
+        Box::new(kanata::ExportKanata),
+        Box::new(commit_log::CommitLog),
+        Box::new(difftest::Difftest),
+        Box::new(rename_check::RenameCheck),
+        Box::new(rename_model::RenameModel),
+        Box::new(rob_flush::RobIqDesync),
+        Box::new(rob_flush::FlushSync),
+        Box::new(stats::Stats),
+        Box::new(top_down::TopDown),
+        Box::new(bp_stats::BpStats),
+        Box::new(flush_cause::FlushCause),
+        Box::new(dbus_log::DbusLog),
+        Box::new(mem::Mem),
+        Box::new(mem::MemCheck),
+        Box::new(store_check::StoreCheck),
+    ]
+}
@@ -0,0 +1,124 @@
+//! Pass interface: every subcommand is an [`AnalysisPass`] registered in [`super::registry`].
+
+use clap::{ArgMatches, Command};
+use wellen::simple::Waveform;
+use wellen::{SignalRef, Time};
+
+use crate::core::{CoreLayout, Isa, Schema, SignalMap, Sink, TimeArg, Timebase};
+
+/// Waveform state handed to a pass after its signals have been loaded.
+pub struct PassContext<'a> {
+    pub wf: &'a mut Waveform,
+    pub time_table: &'a [Time],
+    /// `(name, signal)` for every signal the pass asked for, in hierarchy order: concrete
+    /// logical names (`iq.entry[3].p_rd`) for [`signals`](AnalysisPass::signals), full
+    /// hierarchical names for [`wants_signal`](AnalysisPass::wants_signal).
+    pub sigs: &'a [(String, SignalRef)],
+    /// Samples to analyse: one per clock cycle (see [`Timebase`]).
+    pub tb: &'a Timebase,
+    /// Signal schema and `--scope`, for names only known at run time (see [`lookup`](Self::lookup)).
+    pub schema: &'a Schema,
+    pub scope: Option<&'a str>,
+    /// Structure sizes detected from the hierarchy (with CLI overrides applied).
+    pub layout: CoreLayout,
+    /// Extensions to disassemble (`--isa`).
+    pub isa: Isa,
+    /// Where results go: passes emit [`Event`](crate::core::Event)s and text notes here
+    /// instead of printing.
+    pub out: &'a mut dyn Sink,
+}
+
+impl PassContext<'_> {
+    /// Time of the last sample (last cycle when a clock is known).
+    pub fn last_time(&self) -> Time {
+        self.tb.last_time()
+    }
+
+    /// Whether a signal whose name starts with `prefix` was loaded (`rob.slot[` for any slot).
+    pub fn has(&self, prefix: &str) -> bool {
+        has_signal(self.sigs, prefix)
+    }
+
+    /// Resolve a signal named at run time (see [`SignalMap::lookup`]) and load it.
+    pub fn lookup(&mut self, name: &str) -> Result<Option<SignalRef>, Box<dyn std::error::Error>> {
+        let sig = SignalMap::lookup(self.schema, self.wf.hierarchy(), name, self.scope)?;
+        if let Some(sig) = sig {
+            self.wf.load_signals(&[sig]);
+        }
+        Ok(sig)
+    }
+}
+
+/// [`PassContext::has`] over the `sigs` handed to shared builders.
+pub fn has_signal(sigs: &[(String, SignalRef)], prefix: &str) -> bool {
+    sigs.iter().any(|(n, _)| n.starts_with(prefix))
+}
+
+/// One analysis subcommand.
+///
+/// A pass declares its arguments ([`command`](Self::command)), the signals it reads
+/// ([`signals`](Self::signals) and/or [`wants_signal`](Self::wants_signal)) and what it
+/// prints ([`output`](Self::output)). The CLI loads exactly those signals before calling
+/// [`run`](Self::run).
+pub trait AnalysisPass {
+    /// Subcommand name, e.g. `trace-rob`.
+    fn name(&self) -> &'static str;
+
+    /// Subcommand definition: about text plus the pass-specific arguments.
+    fn command(&self) -> Command;
+
+    /// One-line description of the pass output, shown in `--help`.
+    fn output(&self) -> &'static str;
+
+    /// Schema keys of the logical signals the pass reads (e.g. `iq.entry[i].p_rd`).
+    fn signals(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Whether the pass needs the signal with this full hierarchical name, for passes
+    /// that select raw signals (e.g. by `--grep`) instead of schema keys.
+    fn wants_signal(&self, _name: &str, _args: &ArgMatches) -> bool {
+        false
+    }
+
+    fn run(
+        &self,
+        ctx: &mut PassContext<'_>,
+        args: &ArgMatches,
+    ) -> Result<(), Box<dyn std::error::Error>>;
+}
+
+// `--from/--to` window shared by range-based passes. Plain comment: a doc comment
+// here would become the `about` of every subcommand that flattens it.
+#[derive(clap::Args, Debug, Clone)]
+pub struct RangeArgs {
+    /// Start: cycle (c1234), time with unit (1234ns) or raw timescale units
+    #[arg(long, default_value = "0")]
+    pub from: TimeArg,
+
+    /// End, same forms as --from (default depends on the pass)
+    #[arg(long)]
+    pub to: Option<TimeArg>,
+}
+
+impl RangeArgs {
+    /// `(from, to)` in timescale units, with `to` defaulting to `from + span`.
+    pub fn span(&self, tb: &Timebase, span: Time) -> Result<(Time, Time), String> {
+        let from = self.from.resolve(tb)?;
+        let to = match self.to {
+            Some(to) => to.resolve(tb)?,
+            None => from + span,
+        };
+        Ok((from, to))
+    }
+
+    /// `(from, to)` in timescale units, with `to` defaulting to the last sample.
+    pub fn or_last(&self, ctx: &PassContext<'_>) -> Result<(Time, Time), String> {
+        let from = self.from.resolve(ctx.tb)?;
+        let to = match self.to {
+            Some(to) => to.resolve(ctx.tb)?,
+            None => ctx.last_time(),
+        };
+        Ok((from, to))
+    }
+}
@@ -1,92 +1,108 @@
-use wellen::simple::Waveform;
-use wellen::{SignalRef, Time};
+use clap::{ArgMatches, Args, Command, FromArgMatches};
+use wellen::Time;
+
+use super::pass::{AnalysisPass, PassContext, RangeArgs};
+use crate::core::{Event, snapshot_at};
+
+#[derive(Args)]
+struct PrfIqMismatchArgs {
+    #[command(flatten)]
+    range: RangeArgs,
+}
+
+/// `prf-iq-mismatch`: PRF says ready, IQ entry says not ready.
+pub struct PrfIqMismatch;
+
+impl AnalysisPass for PrfIqMismatch {
+    fn name(&self) -> &'static str {
+        "prf-iq-mismatch"
+    }
+
+    fn command(&self) -> Command {
+        PrfIqMismatchArgs::augment_args(Command::new(self.name()).about(
+            "Find cycles where PRF has bank_ready=1 but IQ entry has rs1/rs2_ready=0 (default --to: from+5000)",
+        ))
+    }
+
+    fn output(&self) -> &'static str {
+        "PRF_IQ_MISMATCH events (entry, src, p_rs) per entry operand, P_RS_UNKNOWN events when p_rs is X/Z, then the total count"
+    }
+
+    fn signals(&self) -> &'static [&'static str] {
+        &[
+            "iq.valid[i]",
+            "iq.entry[i].p_rs1",
+            "iq.entry[i].p_rs2",
+            "iq.entry[i].rs1_ready",
+            "iq.entry[i].rs2_ready",
+            "prf.bank[b].ready[i]",
+        ]
+    }
+
+    fn run(
+        &self,
+        ctx: &mut PassContext<'_>,
+        args: &ArgMatches,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let a = PrfIqMismatchArgs::from_arg_matches(args)?;
+        let (start, end) = a.range.span(ctx.tb, 5000)?;
+        prf_iq_mismatch_scan(ctx, start, end)
+    }
+}
 
 /// PRF-IQ mismatch scan: find cycles where PRF has bank_ready=1 but IQ entry has rs1/rs2_ready=0.
 /// Anomaly = PRF says ready, IQ says not ready (bypass persist should have updated IQ).
-pub fn prf_iq_mismatch_scan(
-    wf: &mut Waveform,
-    time_table: &[Time],
+fn prf_iq_mismatch_scan(
+    ctx: &mut PassContext<'_>,
     start: Time,
     end: Time,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let hierarchy = wf.hierarchy();
-    let mut sigs: Vec<(String, SignalRef)> = Vec::new();
-    for var in hierarchy.iter_vars() {
-        let name = var.full_name(hierarchy);
-        if name.contains("core.iq.valids_")
-            || name.contains("core.iq.entries_")
-                && (name.contains("p_rs1")
-                    || name.contains("p_rs2")
-                    || name.contains("rs1_ready")
-                    || name.contains("rs2_ready"))
-            || name.contains("core.isu.bank_ready_")
-        {
-            sigs.push((name, var.signal_ref()));
-        }
-    }
-    let to_load: Vec<SignalRef> = sigs.iter().map(|(_, sr)| *sr).collect();
-    wf.load_signals(&to_load);
-
-    let get_val = |name_contains: &str, vals: &[(String, String)]| -> Option<String> {
-        vals.iter()
-            .find(|(n, _)| n.contains(name_contains))
-            .map(|(_, v)| v.clone())
-    };
+    let (wf, tb, sigs, out) = (&*ctx.wf, ctx.tb, ctx.sigs, &mut *ctx.out);
+    let layout = ctx.layout;
 
     let mut found = 0u32;
-    for (i, &t) in time_table.iter().enumerate() {
-        if t < start {
-            continue;
-        }
-        if t > end {
-            break;
-        }
-        let idx = i as u32;
-        let mut vals: Vec<(String, String)> = Vec::new();
-        for (name, sig_ref) in &sigs {
-            if let Some(sig) = wf.get_signal(*sig_ref) {
-                if let Some(offset) = sig.get_offset(idx) {
-                    let val = sig.get_value_at(&offset, 0).to_string();
-                    vals.push((name.clone(), val));
-                }
-            }
-        }
+    for s in tb.range(start, end) {
+        let snap = snapshot_at(wf, sigs, s.idx);
 
-        for entry in 0..8 {
-            let valid = get_val(&format!("iq.valids_{}", entry), &vals).map_or(false, |v| v == "1");
-            if !valid {
+        for entry in 0..layout.iq_entries {
+            if !snap.is_high(&format!("iq.valid[{}]", entry)) {
                 continue;
             }
-            let p_rs1 = get_val(&format!("iq.entries_{}_p_rs1", entry), &vals);
-            let p_rs2 = get_val(&format!("iq.entries_{}_p_rs2", entry), &vals);
-            let rs1_ready = get_val(&format!("iq.entries_{}_rs1_ready", entry), &vals)
-                .map_or(false, |v| v == "1");
-            let rs2_ready = get_val(&format!("iq.entries_{}_rs2_ready", entry), &vals)
-                .map_or(false, |v| v == "1");
+            let p_rs1 = snap.get(&format!("iq.entry[{}].p_rs1", entry));
+            let p_rs2 = snap.get(&format!("iq.entry[{}].p_rs2", entry));
+            let rs1_ready = snap.is_high(&format!("iq.entry[{}].rs1_ready", entry));
+            let rs2_ready = snap.is_high(&format!("iq.entry[{}].rs2_ready", entry));
 
             for (src, p_rs, iq_ready) in [("rs1", p_rs1, rs1_ready), ("rs2", p_rs2, rs2_ready)] {
-                let Some(pr_bin) = p_rs else { continue };
-                let pr = u32::from_str_radix(pr_bin.trim(), 2).unwrap_or(99);
+                let Some(p_rs) = p_rs else { continue };
+                let Some(pr) = p_rs.as_u64() else {
+                    out.event(
+                        &Event::on(s, "P_RS_UNKNOWN")
+                            .with("entry", entry)
+                            .with("src", src)
+                            .with("p_rs", p_rs),
+                    )?;
+                    continue;
+                };
                 if pr == 0 {
                     continue;
                 }
                 if iq_ready {
                     continue;
                 }
-                let bank = (pr >> 4) as usize;
-                let idx_bank = (pr & 0xF) as usize;
-                let bank_suffix = format!("bank_ready_{}_{}", bank, idx_bank);
-                let prf_ready = get_val(&bank_suffix, &vals).map_or(false, |v| v == "1");
-                if prf_ready {
+                let (bank, idx_bank) = layout.prf_bank(pr);
+                if snap.is_high(&format!("prf.bank[{}].ready[{}]", bank, idx_bank)) {
                     found += 1;
-                    println!(
-                        "t={} *** PRF-IQ MISMATCH: entry={} {} p_rs={} (PR{}) PRF ready=1 IQ ready=0",
-                        t, entry, src, pr_bin, pr
-                    );
+                    out.event(
+                        &Event::on(s, "PRF_IQ_MISMATCH")
+                            .with("entry", entry)
+                            .with("src", src)
+                            .with("p_rs", pr),
+                    )?;
                 }
             }
         }
     }
-    println!("\nTotal PRF-IQ mismatches found: {}", found);
+    out.note(&format!("\nTotal PRF-IQ mismatches found: {}", found))?;
     Ok(())
 }
@@ -0,0 +1,114 @@
+use clap::{ArgMatches, Args, Command, FromArgMatches};
+
+use super::pass::{AnalysisPass, PassContext};
+use super::rename::arch_name;
+use crate::core::{CoreLayout, Event, Field, Snapshot, TimeArg, snapshot_at};
+
+#[derive(Args)]
+struct RegsArgs {
+    /// Time to show: cycle (c1234), time with unit (1234ns) or raw timescale units
+    #[arg(long)]
+    at: TimeArg,
+}
+
+const SIGNALS: &[&str] = &[
+    "rmt.map[i]",
+    "commit.restore_rmt[i]",
+    "prf.bank[b].data[i]",
+    "prf.bank[b].ready[i]",
+];
+
+/// `regs`: architectural register file view, speculative (RMT) and committed (AMT).
+pub struct Regs;
+
+impl AnalysisPass for Regs {
+    fn name(&self) -> &'static str {
+        "regs"
+    }
+
+    fn command(&self) -> Command {
+        RegsArgs::augment_args(Command::new(self.name()).about(
+            "Print x0..x31 at a time: speculative value through the RMT, committed value through the AMT, the mapped PR and its PRF ready bit",
+        ))
+    }
+
+    fn output(&self) -> &'static str {
+        "REG events (reg, pr, value, ready, commit_pr, commit_value), one per x-register at the last sample <= time"
+    }
+
+    fn signals(&self) -> &'static [&'static str] {
+        SIGNALS
+    }
+
+    fn run(
+        &self,
+        ctx: &mut PassContext<'_>,
+        args: &ArgMatches,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let a = RegsArgs::from_arg_matches(args)?;
+        let time = a.at.resolve(ctx.tb)?;
+        if !ctx.has("rmt.map[") {
+            return Err("Could not find rmt.map[i] signals".into());
+        }
+        let (prf, amt) = (ctx.has("prf.bank["), ctx.has("commit.restore_rmt["));
+        let (sigs, layout, out) = (ctx.sigs, &ctx.layout, &mut *ctx.out);
+        let Some(s) = ctx.tb.at_or_before(time) else {
+            out.note(&format!("No sample <= {} in waveform", a.at))?;
+            return Ok(());
+        };
+        if !prf {
+            out.note("prf.bank[b].data[i] not in the trace: no register values")?;
+        }
+        if !amt {
+            out.note("commit.restore_rmt[i] not in the trace: no committed values")?;
+        }
+        out.note(&format!("\nArchitectural registers at t={}:", s.time))?;
+
+        let snap = snapshot_at(ctx.wf, sigs, s.idx);
+        for arch in 0..32 {
+            // x0 is PR0 in both tables; neither holds an entry for it.
+            let (pr, commit_pr) = match arch {
+                0 => (Some(0), Some(0)),
+                _ => (
+                    snap.u64(&format!("rmt.map[{}]", arch - 1)),
+                    snap.u64(&format!("commit.restore_rmt[{}]", arch - 1)),
+                ),
+            };
+            out.event(
+                &Event::on(s, "REG")
+                    .with("reg", arch_name(arch))
+                    .with("pr", pr)
+                    .with("value", prf_value(&snap, layout, pr))
+                    .with("ready", prf_ready(&snap, layout, pr))
+                    .with("commit_pr", commit_pr)
+                    .with("commit_value", prf_value(&snap, layout, commit_pr)),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// PRF contents of `pr`; PR0 reads as 0 like the RTL's read ports.
+fn prf_value(snap: &Snapshot, layout: &CoreLayout, pr: Option<u64>) -> Field {
+    match pr {
+        None => Field::Null,
+        Some(0) => Field::Hex(0),
+        Some(pr) => {
+            let (bank, idx) = layout.prf_bank(pr);
+            Field::hex(snap.get(&format!("prf.bank[{}].data[{}]", bank, idx)))
+        }
+    }
+}
+
+fn prf_ready(snap: &Snapshot, layout: &CoreLayout, pr: Option<u64>) -> Field {
+    match pr {
+        None => Field::Null,
+        Some(0) => true.into(),
+        Some(pr) => {
+            let (bank, idx) = layout.prf_bank(pr);
+            snap.get(&format!("prf.bank[{}].ready[{}]", bank, idx))
+                .and_then(|v| v.as_bool())
+                .into()
+        }
+    }
+}
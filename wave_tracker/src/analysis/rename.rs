@@ -0,0 +1,105 @@
+//! IDU rename state at one sample: the RMT and the FreeList, from either FreeList
+//! implementation (the `free` bitmap of the current IDU or the older `freeList` ring buffer).
+
+use crate::core::Snapshot;
+
+/// Schema keys of the RMT and both FreeList implementations.
+pub const SIGNALS: &[&str] = &[
+    "rmt.map[i]",
+    "freelist.free[i]",
+    "freelist.head",
+    "freelist.tail",
+    "freelist.buf[i]",
+];
+
+/// Index `N` of a concrete name `<prefix>N]`.
+pub fn index_of(name: &str, prefix: &str) -> Option<usize> {
+    name.strip_prefix(prefix)?.strip_suffix(']')?.parse().ok()
+}
+
+/// `x2(sp)`.
+pub fn arch_name(arch: usize) -> String {
+    format!("x{}({})", arch, crate::core::num::ABI_NAMES[arch])
+}
+
+/// Free physical registers.
+pub enum FreeList {
+    /// `free[pr]` bits: the free PRs, in order, out of `size`.
+    Bitmap { free: Vec<u64>, size: usize },
+    /// Ring buffer: `(slot, pr)` from head up to (not including) tail.
+    Ring {
+        head: u64,
+        tail: u64,
+        free: Vec<(u64, Option<u64>)>,
+    },
+    /// Neither implementation is in the trace.
+    Missing,
+}
+
+pub struct RenameState {
+    /// `rmt[i]` is the PR mapped to x(i+1); empty without RMT signals.
+    pub rmt: Vec<Option<u64>>,
+    pub free_list: FreeList,
+}
+
+impl RenameState {
+    /// Read the RMT and FreeList; `slots` is the ring buffer size (`CoreLayout::freelist_slots`).
+    pub fn read(snap: &Snapshot, slots: usize) -> Self {
+        let mut rmt: Vec<(usize, Option<u64>)> = snap
+            .iter()
+            .filter_map(|(name, v)| index_of(name, "rmt.map[").map(|i| (i, v.as_u64())))
+            .collect();
+        rmt.sort_by_key(|(i, _)| *i);
+        let mut map = vec![None; rmt.last().map_or(0, |(i, _)| i + 1)];
+        for (i, pr) in rmt {
+            map[i] = pr;
+        }
+
+        let bits: Vec<(usize, bool)> = snap
+            .iter()
+            .filter_map(|(name, v)| {
+                index_of(name, "freelist.free[").map(|i| (i, v.as_bool() == Some(true)))
+            })
+            .collect();
+        let free_list = if !bits.is_empty() {
+            let mut free: Vec<u64> = bits
+                .iter()
+                .filter(|(_, f)| *f)
+                .map(|(i, _)| *i as u64)
+                .collect();
+            free.sort_unstable();
+            FreeList::Bitmap {
+                free,
+                size: bits.len(),
+            }
+        } else if let (Some(head), Some(tail)) =
+            (snap.u64("freelist.head"), snap.u64("freelist.tail"))
+        {
+            let slots = slots.max(1) as u64;
+            let free = (0..slots)
+                .map(|k| (head + k) % slots)
+                .take_while(|&slot| slot != tail % slots)
+                .map(|slot| (slot, snap.u64(&format!("freelist.buf[{}]", slot))))
+                .collect();
+            FreeList::Ring { head, tail, free }
+        } else {
+            FreeList::Missing
+        };
+        RenameState {
+            rmt: map,
+            free_list,
+        }
+    }
+
+    /// `(ring slot, pr)` of every free PR; the slot is `None` for the bitmap and the PR is
+    /// `None` for an X/Z ring slot.
+    pub fn free(&self) -> Vec<(Option<u64>, Option<u64>)> {
+        match &self.free_list {
+            FreeList::Bitmap { free, .. } => free.iter().map(|&pr| (None, Some(pr))).collect(),
+            FreeList::Ring { free, .. } => {
+                free.iter().map(|&(slot, pr)| (Some(slot), pr)).collect()
+            }
+            FreeList::Missing => Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,395 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::LazyLock;
+
+use clap::{ArgMatches, Args, Command, FromArgMatches};
+
+use super::pass::{AnalysisPass, PassContext, RangeArgs};
+use super::rename::{self, FreeList, RenameState, arch_name};
+use super::stats::rob_count;
+use crate::core::{CoreLayout, Event, Field, Sample, Snapshot, snapshot_at};
+
+#[derive(Args)]
+struct RenameCheckArgs {
+    /// State changes of each offending PR to print before the violation
+    #[arg(long, default_value_t = 8)]
+    history: usize,
+
+    #[command(flatten)]
+    range: RangeArgs,
+}
+
+/// Holders of an in-flight `old_p_rd`, released when its instruction commits.
+const PENDING_SIGNALS: &[&str] = &[
+    "isu.in.valid",
+    "isu.in.old_p_rd",
+    "rob.head_ptr",
+    "rob.tail_ptr",
+    "rob.slot[i].old_p_rd",
+    "freelist.pending.valid",
+    "freelist.pending.addr",
+];
+
+/// Offending PRs whose history is printed.
+const MAX_HISTORIES: usize = 4;
+
+static SIGNALS: LazyLock<Vec<&'static str>> =
+    LazyLock::new(|| [rename::SIGNALS, PENDING_SIGNALS].concat());
+
+/// `rename-check`: every PR free, mapped or pending release, exactly once, every cycle.
+pub struct RenameCheck;
+
+impl AnalysisPass for RenameCheck {
+    fn name(&self) -> &'static str {
+        "rename-check"
+    }
+
+    fn command(&self) -> Command {
+        RenameCheckArgs::augment_args(Command::new(self.name()).about(
+            "Check every cycle that each PR is in exactly one state: free, mapped by one RMT entry, or an in-flight old_p_rd pending release",
+        ))
+    }
+
+    fn output(&self) -> &'static str {
+        "at the first violating cycle, VIOLATION per offending PR (pr, problem, arch, state) and its last state changes as HISTORY (pr, change, from, to); or a note that every cycle passed"
+    }
+
+    fn signals(&self) -> &'static [&'static str] {
+        &SIGNALS
+    }
+
+    fn run(
+        &self,
+        ctx: &mut PassContext<'_>,
+        args: &ArgMatches,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let a = RenameCheckArgs::from_arg_matches(args)?;
+        let (start, end) = a.range.or_last(ctx)?;
+        if !ctx.has("rmt.map[") {
+            return Err("Could not find rmt.map[i] signals".into());
+        }
+        if !ctx.has("freelist.free[") && !ctx.has("freelist.head") {
+            return Err(
+                "Could not find FreeList signals (freelist.free[i] or freelist.head)".into(),
+            );
+        }
+        // Without the ROB slots every in-flight old_p_rd would look leaked.
+        let check_leaks =
+            ctx.has("rob.head_ptr") && ctx.has("rob.tail_ptr") && ctx.has("rob.slot[");
+        let missing: Vec<(&str, &str)> = [
+            ("isu.in.valid", "the IDU->ISU pipe register"),
+            ("freelist.pending.valid", "the delayed commit free"),
+        ]
+        .into_iter()
+        .filter(|(key, _)| check_leaks && !ctx.has(key))
+        .collect();
+        let (wf, tb, sigs, layout, out) = (&*ctx.wf, ctx.tb, ctx.sigs, &ctx.layout, &mut *ctx.out);
+
+        out.note(&format!("Rename invariant check, t={}..{}", start, end))?;
+        if !check_leaks {
+            out.note("  rob.slot[i].old_p_rd not in the trace: only PRs held twice are reported, not leaked ones")?;
+        }
+        for (key, what) in missing {
+            out.note(&format!(
+                "  {} not in the trace: PRs held by {} will show as leaked",
+                key, what
+            ))?;
+        }
+        out.note("")?;
+
+        let mut prs = 0;
+        let mut history: Vec<VecDeque<(Sample, String, String)>> = Vec::new();
+        let mut last_state: Vec<String> = Vec::new();
+        let mut last_arch: Vec<Option<usize>> = Vec::new();
+        let cycles = tb.cycles_in(start, end);
+        let (mut checked, mut skipped) = (0, tb.range(start, end).len() - cycles.len());
+        for s in cycles {
+            let snap = snapshot_at(wf, sigs, s.idx);
+            let state = RenameState::read(&snap, layout.freelist_slots);
+            let Some(Held { holders, stray }) = holders(&snap, &state, layout, check_leaks) else {
+                skipped += 1;
+                continue;
+            };
+            checked += 1;
+            if holders.len() > prs {
+                prs = holders.len();
+                history.resize_with(prs, VecDeque::new);
+                last_state.resize(prs, String::new());
+                last_arch.resize(prs, None);
+            }
+
+            let mut bad = Vec::new();
+            for (pr, held) in holders.iter().enumerate().skip(1) {
+                let label = state_label(held);
+                if !last_state[pr].is_empty() && label != last_state[pr] {
+                    let h = &mut history[pr];
+                    if h.len() == a.history {
+                        h.pop_front();
+                    }
+                    if a.history > 0 {
+                        h.push_back((*s, last_state[pr].clone(), label.clone()));
+                    }
+                }
+                if let Some(arch) = held.iter().find_map(Holder::arch) {
+                    last_arch[pr] = Some(arch);
+                }
+                last_state[pr] = label;
+
+                let problem = match held.len() {
+                    0 if check_leaks => "leaked",
+                    0 | 1 => continue,
+                    _ if held.iter().filter(|h| h.arch().is_some()).count() > 1 => {
+                        "multiply mapped"
+                    }
+                    _ => "multiple states",
+                };
+                bad.push((pr, problem));
+            }
+            if bad.is_empty() && stray.is_empty() {
+                continue;
+            }
+
+            for (pr, h) in &stray {
+                out.event(
+                    &Event::on(s, "VIOLATION")
+                        .with("pr", *pr)
+                        .with("problem", "out of range")
+                        .with(
+                            "arch",
+                            h.arch().map_or(Field::Null, |a| arch_name(a).into()),
+                        )
+                        .with("state", h.to_string()),
+                )?;
+            }
+            for &(pr, problem) in &bad {
+                out.event(
+                    &Event::on(s, "VIOLATION")
+                        .with("pr", pr)
+                        .with("problem", problem)
+                        .with(
+                            "arch",
+                            last_arch[pr].map_or(Field::Null, |a| arch_name(a).into()),
+                        )
+                        .with("state", last_state[pr].as_str()),
+                )?;
+            }
+            if bad.len() > MAX_HISTORIES {
+                out.note(&format!(
+                    "\nState changes of the first {} PRs only",
+                    MAX_HISTORIES
+                ))?;
+            }
+            for &(pr, _) in bad.iter().take(MAX_HISTORIES) {
+                out.note(&format!("\nRecent state changes of PR{}:", pr))?;
+                if history[pr].is_empty() {
+                    out.note("  (none in the window)")?;
+                }
+                for (at, from, to) in &history[pr] {
+                    out.event(
+                        &Event::on(at, "HISTORY")
+                            .with("pr", pr)
+                            .with("change", change(from, to))
+                            .with("from", from.as_str())
+                            .with("to", to.as_str()),
+                    )?;
+                }
+            }
+            return Ok(());
+        }
+        out.note(&format!(
+            "No violation: {} cycles checked, {} PRs",
+            checked,
+            prs.saturating_sub(1)
+        ))?;
+        if skipped > 0 {
+            out.note(&format!(
+                "{} samples skipped: in reset, or RMT / FreeList X/Z",
+                skipped
+            ))?;
+        }
+        Ok(())
+    }
+}
+
+/// One place holding a physical register.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Holder {
+    /// In the FreeList (the ring buffer slot, if any).
+    Free(Option<u64>),
+    /// RMT mapping of x(arch).
+    Rmt(usize),
+    /// `old_p_rd` in the IDU->ISU pipe register.
+    Isu,
+    /// `old_p_rd` of a live ROB slot.
+    Rob(usize),
+    /// Committed `old_p_rd` in IDU's one-cycle pending free.
+    PendingFree,
+}
+
+impl Holder {
+    fn arch(&self) -> Option<usize> {
+        match self {
+            Holder::Rmt(arch) => Some(*arch),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Holder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Holder::Free(None) => f.write_str("free"),
+            Holder::Free(Some(slot)) => write!(f, "free[{}]", slot),
+            Holder::Rmt(arch) => f.write_str(&arch_name(*arch)),
+            Holder::Isu => f.write_str("isu"),
+            Holder::Rob(slot) => write!(f, "rob[{}]", slot),
+            Holder::PendingFree => f.write_str("pending_free"),
+        }
+    }
+}
+
+fn state_label(held: &[Holder]) -> String {
+    if held.is_empty() {
+        return "none".into();
+    }
+    let names: Vec<String> = held.iter().map(Holder::to_string).collect();
+    names.join("+")
+}
+
+/// `alloc` when a PR leaves the FreeList, `free` when it enters it.
+fn change(from: &str, to: &str) -> &'static str {
+    let in_free_list = |label: &str| label.split('+').any(|h| h.starts_with("free"));
+    match (in_free_list(from), in_free_list(to)) {
+        (true, false) => "alloc",
+        (false, true) => "free",
+        _ => "move",
+    }
+}
+
+/// Where the PRs are held in one cycle.
+struct Held {
+    /// Holders of each PR (index 0, x0's PR, is never checked).
+    holders: Vec<Vec<Holder>>,
+    /// `(pr, holder)` of PRs past the FreeList's size.
+    stray: Vec<(u64, Holder)>,
+}
+
+/// Holders of every PR, or `None` when the RMT or FreeList is X/Z.
+fn holders(
+    snap: &Snapshot,
+    state: &RenameState,
+    layout: &CoreLayout,
+    check_leaks: bool,
+) -> Option<Held> {
+    let size = match &state.free_list {
+        FreeList::Bitmap { size, .. } => *size,
+        FreeList::Ring { .. } => layout.ring_prs(),
+        FreeList::Missing => return None,
+    };
+    let mut held = vec![Vec::new(); size];
+    let mut stray = Vec::new();
+    let mut hold = |pr: u64, h: Holder| match held.get_mut(pr as usize) {
+        _ if pr == 0 => {}
+        Some(v) => v.push(h),
+        None => stray.push((pr, h)),
+    };
+
+    for (i, pr) in state.rmt.iter().enumerate() {
+        hold((*pr)?, Holder::Rmt(i + 1));
+    }
+    for (slot, pr) in state.free() {
+        hold(pr?, Holder::Free(slot));
+    }
+    if snap.is_high("isu.in.valid")
+        && let Some(pr) = snap.u64("isu.in.old_p_rd")
+    {
+        hold(pr, Holder::Isu);
+    }
+    if snap.is_high("freelist.pending.valid")
+        && let Some(pr) = snap.u64("freelist.pending.addr")
+    {
+        hold(pr, Holder::PendingFree);
+    }
+    if check_leaks {
+        let depth = layout.rob_slots.max(1) as u64;
+        let (head, count) = (
+            snap.u64("rob.head_ptr")?,
+            rob_count(snap, layout.rob_slots)?,
+        );
+        for k in 0..count as u64 {
+            let slot = ((head + k) % depth) as usize;
+            if let Some(pr) = snap.u64(&format!("rob.slot[{}].old_p_rd", slot)) {
+                hold(pr, Holder::Rob(slot));
+            }
+        }
+    }
+    Some(Held {
+        holders: held,
+        stray,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Value;
+
+    fn snap(vals: &[(&str, u64)]) -> Snapshot {
+        vals.iter()
+            .map(|&(name, v)| (name, Value::from_u64(v, 8)))
+            .collect()
+    }
+
+    /// `(pr, holder)` of the stray PRs and the holders of the in-range ones, as text.
+    fn held(snap: &Snapshot, layout: &CoreLayout) -> (Vec<String>, Vec<String>) {
+        let state = RenameState::read(snap, layout.freelist_slots);
+        let Held { holders, stray } = holders(snap, &state, layout, false).unwrap();
+        (
+            stray
+                .iter()
+                .map(|(pr, h)| format!("{}:{}", pr, h))
+                .collect(),
+            holders.iter().map(|h| state_label(h)).collect(),
+        )
+    }
+
+    #[test]
+    fn bitmap_pr_out_of_range() {
+        // Four PRs: x1 on PR1, PR2 and PR3 free; x2 on PR6 is past the bitmap.
+        let s = snap(&[
+            ("rmt.map[0]", 1),
+            ("rmt.map[1]", 6),
+            ("freelist.free[0]", 0),
+            ("freelist.free[1]", 0),
+            ("freelist.free[2]", 1),
+            ("freelist.free[3]", 1),
+            ("freelist.pending.valid", 1),
+            ("freelist.pending.addr", 9),
+        ]);
+        let (stray, holders) = held(&s, &CoreLayout::default());
+        assert_eq!(stray, ["6:x2(sp)", "9:pending_free"]);
+        assert_eq!(holders, ["none", "x1(ra)", "free", "free"]);
+    }
+
+    #[test]
+    fn ring_size_from_layout() {
+        let layout = CoreLayout {
+            freelist_slots: 2,
+            ..CoreLayout::default()
+        };
+        // 32 + 2 PRs: PR33 is the last one, PR34 is out of range.
+        let s = snap(&[
+            ("rmt.map[0]", 33),
+            ("rmt.map[1]", 34),
+            ("freelist.head", 0),
+            ("freelist.tail", 1),
+            ("freelist.buf[0]", 32),
+            ("freelist.buf[1]", 1),
+        ]);
+        let (stray, holders) = held(&s, &layout);
+        assert_eq!(stray, ["34:x2(sp)"]);
+        assert_eq!(holders.len(), 34);
+        assert_eq!(holders[32], "free[0]");
+        assert_eq!(holders[33], "x1(ra)");
+    }
+}
@@ -0,0 +1,717 @@
+use std::collections::VecDeque;
+use std::sync::LazyLock;
+
+use clap::{ArgMatches, Args, Command, FromArgMatches};
+
+use super::pass::{AnalysisPass, PassContext, RangeArgs};
+use super::rename::{self, FreeList, RenameState, arch_name, index_of};
+use crate::core::{CoreLayout, Event, Field, Sample, Snapshot, snapshot_at};
+
+#[derive(Args)]
+struct RenameModelArgs {
+    /// Model steps (flush, free, commit, alloc) to print before the divergence
+    #[arg(long, default_value_t = 16)]
+    history: usize,
+
+    #[command(flatten)]
+    range: RangeArgs,
+}
+
+/// Rename, commit and flush inputs of the model, and the AMT and pending free it tracks.
+const INPUT_SIGNALS: &[&str] = &[
+    "idu.out.valid",
+    "idu.out.ready",
+    "idu.out.flush",
+    "idu.out.rd_index",
+    "idu.out.p_rd",
+    "rob.do_flush",
+    "rob.commit.valid",
+    "rob.commit.rd_index",
+    "rob.commit.p_rd",
+    "rob.commit.old_p_rd",
+    "commit.restore_rmt[i]",
+    "freelist.pending.valid",
+    "freelist.pending.addr",
+];
+
+static SIGNALS: LazyLock<Vec<&'static str>> =
+    LazyLock::new(|| [rename::SIGNALS, INPUT_SIGNALS].concat());
+
+/// `rename-model`: replay rename, commit and flush through a model of IDU's RMT / FreeList
+/// and Commit's AMT, and report the first cycle the RTL state differs from it.
+pub struct RenameModel;
+
+impl AnalysisPass for RenameModel {
+    fn name(&self) -> &'static str {
+        "rename-model"
+    }
+
+    fn command(&self) -> Command {
+        RenameModelArgs::augment_args(Command::new(self.name()).about(
+            "Replay the trace's renames, commits and flushes through a software model of the RMT, FreeList and AMT; report the first cycle the RTL's rmt, FreeList or restore_rmt differs from it",
+        ))
+    }
+
+    fn output(&self) -> &'static str {
+        "at the first divergence, DIVERGENCE per differing entry (state, entry, wave, model) and the model's last steps (FLUSH, FREE (pr), COMMIT and ALLOC (rd, p_rd, old_p_rd)); or a note that the model matched"
+    }
+
+    fn signals(&self) -> &'static [&'static str] {
+        &SIGNALS
+    }
+
+    fn run(
+        &self,
+        ctx: &mut PassContext<'_>,
+        args: &ArgMatches,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let a = RenameModelArgs::from_arg_matches(args)?;
+        let (start, end) = a.range.or_last(ctx)?;
+        if !ctx.has("rmt.map[") {
+            return Err("Could not find rmt.map[i] signals".into());
+        }
+        if !ctx.has("freelist.free[") && !ctx.has("freelist.head") {
+            return Err(
+                "Could not find FreeList signals (freelist.free[i] or freelist.head)".into(),
+            );
+        }
+        for key in ["idu.out.valid", "idu.out.rd_index", "rob.commit.valid"] {
+            if !ctx.has(key) {
+                return Err(format!("Could not find {}", key).into());
+            }
+        }
+        // IDU sees the ROB's registered flush through the pipe flush chain.
+        let flush = if ctx.has("idu.out.flush") {
+            "idu.out.flush"
+        } else if ctx.has("rob.do_flush") {
+            "rob.do_flush"
+        } else {
+            return Err("Could not find idu.out.flush or rob.do_flush".into());
+        };
+        let check_amt = ctx.has("commit.restore_rmt[");
+        let pending = ctx.has("freelist.pending.valid");
+        let (wf, tb, sigs, layout, out) = (&*ctx.wf, ctx.tb, ctx.sigs, &ctx.layout, &mut *ctx.out);
+        let slots = layout.freelist_slots;
+
+        out.note(&format!("Rename model replay, t={}..{}", start, end))?;
+        if !check_amt {
+            out.note("  commit.restore_rmt[i] not in the trace: the AMT starts from its reset value and is not checked")?;
+        }
+        if !pending {
+            out.note("  freelist.pending.valid not in the trace: assuming no commit free is pending at the first cycle")?;
+        }
+        out.note("")?;
+
+        let mut model: Option<Model> = None;
+        let mut history: VecDeque<Event> = VecDeque::new();
+        let mut log = Vec::new();
+        let window = tb.cycles_in(start, end);
+        let (mut cycles, mut skipped) = (0, tb.range(start, end).len() - window.len());
+        let (mut allocs, mut commits, mut flushes) = (0, 0, 0);
+        for s in window {
+            let snap = snapshot_at(wf, sigs, s.idx);
+            let state = RenameState::read(&snap, slots);
+            let input = Inputs::read(&snap, flush);
+            let m = match model.take() {
+                Some(m) => {
+                    let diverged = m.diff(&snap, &state, &input, check_amt);
+                    if !diverged.is_empty() {
+                        for d in diverged {
+                            out.event(
+                                &Event::on(s, "DIVERGENCE")
+                                    .with("state", d.state)
+                                    .with("entry", d.entry)
+                                    .with("wave", d.wave)
+                                    .with("model", d.model),
+                            )?;
+                        }
+                        out.note(&format!("\nLast {} model steps:", history.len()))?;
+                        for e in &history {
+                            out.event(e)?;
+                        }
+                        return Ok(());
+                    }
+                    m
+                }
+                // The model starts from the RTL state at the first cycle with a known one.
+                None => match Model::read(&snap, &state, layout, check_amt) {
+                    Some(m) => m,
+                    None => {
+                        skipped += 1;
+                        continue;
+                    }
+                },
+            };
+            let m = model.insert(m);
+            cycles += 1;
+
+            allocs += usize::from(input.alloc.is_some() && !input.flush);
+            commits += usize::from(input.commit.is_some());
+            flushes += usize::from(input.flush);
+            m.step(s, &input, &mut log);
+            for e in log.drain(..) {
+                if history.len() == a.history {
+                    history.pop_front();
+                }
+                if a.history > 0 {
+                    history.push_back(e);
+                }
+            }
+        }
+
+        if model.is_none() {
+            out.note("No cycle with a known RMT and FreeList in the window")?;
+            return Ok(());
+        }
+        out.note(&format!(
+            "Model matches: {} cycles, {} allocs, {} commits, {} flushes",
+            cycles, allocs, commits, flushes
+        ))?;
+        if skipped > 0 {
+            out.note(&format!(
+                "{} samples skipped: in reset, RMT / FreeList X/Z, or ring FreeList head = tail",
+                skipped
+            ))?;
+        }
+        Ok(())
+    }
+}
+
+/// IDU's RMT and FreeList and Commit's AMT, updated as the RTL does each cycle.
+struct Model {
+    /// `rmt[i]` is the PR of x(i+1).
+    rmt: Vec<u64>,
+    /// `amt[i]` is the committed PR of x(i+1), IDU's `restore_rmt`.
+    amt: Vec<u64>,
+    free: Free,
+    /// Committed `old_p_rd`, set free at the end of the next cycle.
+    pending: Option<u64>,
+}
+
+/// The model's FreeList, in the trace's implementation.
+enum Free {
+    /// `free[pr]` bits; allocation takes the lowest free PR above PR0, which IDU never
+    /// allocates.
+    Bitmap(Vec<bool>),
+    /// Ring buffer: allocation pops `buf[head]` and a free pushes at the tail, so the
+    /// `count` slots from `head` hold the free PRs. Popped slots keep their PR until a
+    /// free overwrites them.
+    Ring {
+        buf: Vec<Option<u64>>,
+        head: usize,
+        count: usize,
+        /// PRs behind the buffer, PR0 included.
+        size: usize,
+    },
+}
+
+impl Free {
+    /// The PR the next allocation takes.
+    fn pick(&self) -> Option<u64> {
+        match self {
+            Free::Bitmap(free) => (1..free.len()).find(|&pr| free[pr]).map(|pr| pr as u64),
+            Free::Ring {
+                buf, head, count, ..
+            } => (*count > 0).then(|| buf[*head]).flatten(),
+        }
+    }
+
+    /// Take the PR [`pick`](Self::pick) chose.
+    fn alloc(&mut self, pr: u64) {
+        match self {
+            Free::Bitmap(free) => free[pr as usize] = false,
+            Free::Ring {
+                buf, head, count, ..
+            } => {
+                *head = (*head + 1) % buf.len();
+                *count -= 1;
+            }
+        }
+    }
+
+    fn release(&mut self, pr: u64) {
+        match self {
+            Free::Bitmap(free) => {
+                if let Some(free) = free.get_mut(pr as usize) {
+                    *free = true;
+                }
+            }
+            Free::Ring {
+                buf, head, count, ..
+            } => {
+                if *count < buf.len() {
+                    let tail = (*head + *count) % buf.len();
+                    buf[tail] = Some(pr);
+                    *count += 1;
+                }
+            }
+        }
+    }
+
+    /// After a flush every PR outside the AMT is free: the bitmap sets their bits, and the
+    /// ring walks `head` back over the PRs allocated since, still in the slots behind it.
+    fn restore(&mut self, amt: &[u64]) {
+        match self {
+            Free::Bitmap(free) => {
+                for (pr, free) in free.iter_mut().enumerate() {
+                    *free = !amt.contains(&(pr as u64));
+                }
+            }
+            Free::Ring {
+                buf,
+                head,
+                count,
+                size,
+            } => {
+                let slots = buf.len();
+                let outside = (1..*size as u64).filter(|pr| !amt.contains(pr)).count();
+                let back = outside.saturating_sub(*count).min(slots - *count);
+                *head = (*head + slots - back) % slots;
+                *count += back;
+            }
+        }
+    }
+}
+
+/// What the model reads from the trace in one cycle.
+struct Inputs {
+    flush: bool,
+    /// `(rd_index, p_rd the RTL allocated)` of an instruction leaving IDU with rd != x0.
+    alloc: Option<(usize, Option<u64>)>,
+    /// `(rd_index, p_rd, old_p_rd)` of the committing instruction.
+    commit: Option<(usize, u64, u64)>,
+}
+
+impl Inputs {
+    fn read(snap: &Snapshot, flush: &str) -> Self {
+        let fire = snap.is_high("idu.out.valid")
+            && snap
+                .get("idu.out.ready")
+                .is_none_or(|v| v.as_bool() == Some(true));
+        let alloc = snap
+            .u64("idu.out.rd_index")
+            .filter(|&rd| fire && rd != 0)
+            .map(|rd| (rd as usize, snap.u64("idu.out.p_rd")));
+        let commit = snap.is_high("rob.commit.valid").then(|| {
+            (
+                snap.u64("rob.commit.rd_index").unwrap_or(0) as usize,
+                snap.u64("rob.commit.p_rd").unwrap_or(0),
+                snap.u64("rob.commit.old_p_rd").unwrap_or(0),
+            )
+        });
+        Inputs {
+            flush: snap.is_high(flush),
+            alloc,
+            commit,
+        }
+    }
+}
+
+/// One RTL entry that differs from the model.
+struct Divergence {
+    state: &'static str,
+    entry: Field,
+    wave: Field,
+    model: Field,
+}
+
+impl Model {
+    /// The RTL state at one sample, or `None` while any of it is X/Z or the ring FreeList
+    /// has head = tail, which is both empty and full.
+    fn read(
+        snap: &Snapshot,
+        state: &RenameState,
+        layout: &CoreLayout,
+        check_amt: bool,
+    ) -> Option<Self> {
+        let free = match &state.free_list {
+            FreeList::Bitmap { free, size } => {
+                let mut bits = vec![false; *size];
+                for &pr in free {
+                    *bits.get_mut(pr as usize)? = true;
+                }
+                Free::Bitmap(bits)
+            }
+            FreeList::Ring { head, free, .. } => {
+                let slots = layout.freelist_slots.max(1);
+                if free.is_empty() || free.iter().any(|(_, pr)| pr.is_none()) {
+                    return None;
+                }
+                Free::Ring {
+                    buf: (0..slots)
+                        .map(|slot| snap.u64(&format!("freelist.buf[{}]", slot)))
+                        .collect(),
+                    head: *head as usize % slots,
+                    count: free.len(),
+                    size: layout.ring_prs(),
+                }
+            }
+            FreeList::Missing => return None,
+        };
+        let rmt: Vec<u64> = state.rmt.iter().copied().collect::<Option<_>>()?;
+        let amt = if check_amt {
+            restore_rmt(snap)
+                .into_iter()
+                .map(|(_, pr)| pr)
+                .collect::<Option<_>>()?
+        } else {
+            (1..=rmt.len() as u64).collect()
+        };
+        let pending = snap
+            .is_high("freelist.pending.valid")
+            .then(|| snap.u64("freelist.pending.addr"))
+            .flatten();
+        Some(Model {
+            rmt,
+            amt,
+            free,
+            pending,
+        })
+    }
+
+    /// Advance one cycle, pushing what changed onto `log`.
+    fn step(&mut self, s: &Sample, input: &Inputs, log: &mut Vec<Event>) {
+        // Allocation picks from the FreeList at the start of the cycle.
+        let pick = self.free.pick();
+        if input.flush {
+            // restore_rmt is the AMT register: this cycle's commit is not in it yet. The
+            // pending free is outside it, so it is free after the flush too.
+            self.rmt = self.amt.clone();
+            if let Some(pr) = self.pending.take() {
+                self.free.release(pr);
+            }
+            self.free.restore(&self.amt);
+            log.push(Event::on(s, "FLUSH"));
+        } else {
+            if let Some(pr) = self.pending.take() {
+                self.free.release(pr);
+                log.push(Event::on(s, "FREE").with("pr", pr));
+            }
+            if let Some((rd, p_rd, old_p_rd)) = input.commit
+                && rd != 0
+                && old_p_rd != p_rd
+                && old_p_rd != 0
+            {
+                self.pending = Some(old_p_rd);
+            }
+        }
+        if let Some((rd, p_rd, old_p_rd)) = input.commit {
+            if let Some(entry) = rd.checked_sub(1).and_then(|i| self.amt.get_mut(i)) {
+                *entry = p_rd;
+            }
+            log.push(
+                Event::on(s, "COMMIT")
+                    .with("rd", rd_name(rd))
+                    .with("p_rd", p_rd)
+                    .with("old_p_rd", old_p_rd),
+            );
+        }
+        if let (Some((rd, _)), false) = (input.alloc, input.flush) {
+            let Some(pr) = pick else {
+                return;
+            };
+            let Some(entry) = self.rmt.get_mut(rd - 1) else {
+                return;
+            };
+            let old_p_rd = *entry;
+            *entry = pr;
+            self.free.alloc(pr);
+            log.push(
+                Event::on(s, "ALLOC")
+                    .with("rd", rd_name(rd))
+                    .with("p_rd", pr)
+                    .with("old_p_rd", old_p_rd),
+            );
+        }
+    }
+
+    /// RMT, FreeList and (with `check_amt`) `restore_rmt` entries of the RTL that differ,
+    /// and the PR IDU allocates this cycle if it is not the model's pick. The bitmap is
+    /// compared per PR, the ring by head, tail and the PR in each slot between them.
+    fn diff(
+        &self,
+        snap: &Snapshot,
+        state: &RenameState,
+        input: &Inputs,
+        check_amt: bool,
+    ) -> Vec<Divergence> {
+        let mut diverged = Vec::new();
+        let wave = |name: String| snap.get(&name).map_or(Field::Null, Field::from);
+        for (i, &pr) in self.rmt.iter().enumerate() {
+            if state.rmt.get(i).copied().flatten() != Some(pr) {
+                diverged.push(Divergence {
+                    state: "rmt",
+                    entry: arch_name(i + 1).into(),
+                    wave: wave(format!("rmt.map[{}]", i)),
+                    model: pr.into(),
+                });
+            }
+        }
+        match &self.free {
+            Free::Bitmap(bits) => {
+                let mut free = vec![false; bits.len()];
+                for pr in state.free().into_iter().filter_map(|(_, pr)| pr) {
+                    if let Some(f) = free.get_mut(pr as usize) {
+                        *f = true;
+                    }
+                }
+                for (pr, (&wave, &model)) in free.iter().zip(bits).enumerate() {
+                    if wave != model {
+                        diverged.push(Divergence {
+                            state: "free",
+                            entry: pr.into(),
+                            wave: wave.into(),
+                            model: model.into(),
+                        });
+                    }
+                }
+            }
+            Free::Ring {
+                buf, head, count, ..
+            } => {
+                let slots = buf.len();
+                let tail = (head + count) % slots;
+                for (name, model) in [("head", *head), ("tail", tail)] {
+                    let key = format!("freelist.{}", name);
+                    if snap.u64(&key).map(|p| p as usize % slots) != Some(model) {
+                        diverged.push(Divergence {
+                            state: "ring",
+                            entry: name.into(),
+                            wave: wave(key),
+                            model: model.into(),
+                        });
+                    }
+                }
+                // Slots from the model's head: a wrong head is reported once, above.
+                for slot in (0..*count).map(|k| (head + k) % slots) {
+                    let key = format!("freelist.buf[{}]", slot);
+                    if snap.u64(&key) != buf[slot] {
+                        diverged.push(Divergence {
+                            state: "free",
+                            entry: slot.into(),
+                            wave: wave(key),
+                            model: buf[slot].into(),
+                        });
+                    }
+                }
+            }
+        }
+        if check_amt {
+            for (i, pr) in restore_rmt(snap) {
+                let model = self.amt.get(i).copied();
+                if pr != model {
+                    diverged.push(Divergence {
+                        state: "restore_rmt",
+                        entry: arch_name(i + 1).into(),
+                        wave: wave(format!("commit.restore_rmt[{}]", i)),
+                        model: model.into(),
+                    });
+                }
+            }
+        }
+        if let Some((rd, Some(p_rd))) = input.alloc.filter(|_| !input.flush) {
+            let pick = self.free.pick();
+            if pick != Some(p_rd) {
+                diverged.push(Divergence {
+                    state: "alloc",
+                    entry: rd_name(rd).into(),
+                    wave: p_rd.into(),
+                    model: pick.into(),
+                });
+            }
+        }
+        diverged
+    }
+}
+
+/// `(i, pr)` of every `commit.restore_rmt[i]`, in order.
+fn restore_rmt(snap: &Snapshot) -> Vec<(usize, Option<u64>)> {
+    let mut amt: Vec<(usize, Option<u64>)> = snap
+        .iter()
+        .filter_map(|(name, v)| index_of(name, "commit.restore_rmt[").map(|i| (i, v.as_u64())))
+        .collect();
+    amt.sort_by_key(|(i, _)| *i);
+    amt
+}
+
+fn rd_name(rd: usize) -> String {
+    if rd < 32 {
+        arch_name(rd)
+    } else {
+        format!("x{}", rd)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Value;
+
+    fn at(cycle: u64) -> Sample {
+        Sample {
+            idx: cycle as u32,
+            time: 10 * cycle,
+            cycle: Some(cycle),
+        }
+    }
+
+    fn alloc(rd: usize) -> Inputs {
+        Inputs {
+            flush: false,
+            alloc: Some((rd, None)),
+            commit: None,
+        }
+    }
+
+    fn commit(rd: usize, p_rd: u64, old_p_rd: u64) -> Inputs {
+        Inputs {
+            flush: false,
+            alloc: None,
+            commit: Some((rd, p_rd, old_p_rd)),
+        }
+    }
+
+    const IDLE: Inputs = Inputs {
+        flush: false,
+        alloc: None,
+        commit: None,
+    };
+
+    const FLUSH: Inputs = Inputs {
+        flush: true,
+        alloc: None,
+        commit: None,
+    };
+
+    /// x1, x2 on PR1, PR2 and PRs up to 8, with `free` as the FreeList.
+    fn model(free: Free) -> Model {
+        Model {
+            rmt: vec![1, 2],
+            amt: vec![1, 2],
+            free,
+            pending: None,
+        }
+    }
+
+    /// Drive `inputs` one cycle each; the kinds of the events logged.
+    fn run(m: &mut Model, inputs: &[Inputs]) -> Vec<&'static str> {
+        let mut log = Vec::new();
+        for (c, input) in inputs.iter().enumerate() {
+            m.step(&at(c as u64), input, &mut log);
+        }
+        log.iter().map(|e| e.kind).collect()
+    }
+
+    #[test]
+    fn bitmap_takes_lowest_free() {
+        let mut bits = vec![true; 9];
+        bits[..3].fill(false);
+        let mut m = model(Free::Bitmap(bits));
+        let log = run(&mut m, &[alloc(1), alloc(2), commit(1, 3, 1), IDLE]);
+        assert_eq!(log, ["ALLOC", "ALLOC", "COMMIT", "FREE"]);
+        assert_eq!(m.rmt, [3, 4]);
+        assert_eq!(m.amt, [3, 2]);
+        // PR1 came back and is the lowest again.
+        assert_eq!(m.free.pick(), Some(1));
+
+        // x2 -> PR4 was not committed: the flush frees it.
+        run(&mut m, &[FLUSH]);
+        assert_eq!(m.rmt, [3, 2]);
+        let Free::Bitmap(bits) = &m.free else {
+            unreachable!()
+        };
+        let free: Vec<usize> = (1..9).filter(|&pr| bits[pr]).collect();
+        assert_eq!(free, [1, 4, 5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn ring_pops_head_and_pushes_tail() {
+        let buf = (3..9).map(Some).collect();
+        let mut m = model(Free::Ring {
+            buf,
+            head: 0,
+            count: 6,
+            size: 9,
+        });
+        let log = run(&mut m, &[alloc(1), alloc(2), commit(1, 3, 1), IDLE]);
+        assert_eq!(log, ["ALLOC", "ALLOC", "COMMIT", "FREE"]);
+        assert_eq!(m.rmt, [3, 4]);
+        // PR1 went to the tail, behind 5..8, not to the front as with the bitmap.
+        let Free::Ring {
+            buf, head, count, ..
+        } = &m.free
+        else {
+            unreachable!()
+        };
+        assert_eq!((*head, *count), (2, 5));
+        assert_eq!(buf[0], Some(1));
+        assert_eq!(m.free.pick(), Some(5));
+
+        // The flush walks head back over PR4, the one uncommitted allocation.
+        run(&mut m, &[FLUSH]);
+        let Free::Ring {
+            buf, head, count, ..
+        } = &m.free
+        else {
+            unreachable!()
+        };
+        assert_eq!((*head, *count), (1, 6));
+        let free: Vec<Option<u64>> = (0..6).map(|k| buf[(head + k) % 6]).collect();
+        assert_eq!(free, [4, 5, 6, 7, 8, 1].map(Some));
+    }
+
+    #[test]
+    fn ring_flush_keeps_pending_free() {
+        let buf = (3..9).map(Some).collect();
+        let mut m = model(Free::Ring {
+            buf,
+            head: 0,
+            count: 6,
+            size: 9,
+        });
+        run(&mut m, &[alloc(1), commit(1, 3, 1), FLUSH]);
+        assert_eq!(m.pending, None);
+        let Free::Ring {
+            buf, head, count, ..
+        } = &m.free
+        else {
+            unreachable!()
+        };
+        let free: Vec<Option<u64>> = (0..*count).map(|k| buf[(head + k) % 6]).collect();
+        assert_eq!(free, [4, 5, 6, 7, 8, 1].map(Some));
+    }
+
+    #[test]
+    fn ring_diff() {
+        let buf = (3..9).map(Some).collect();
+        let mut m = model(Free::Ring {
+            buf,
+            head: 0,
+            count: 6,
+            size: 9,
+        });
+        run(&mut m, &[alloc(1), alloc(2)]);
+        let snap = |head: u64, slot2: u64| -> Snapshot {
+            let mut vals = vec![
+                ("rmt.map[0]".to_string(), Value::from_u64(3, 4)),
+                ("rmt.map[1]".to_string(), Value::from_u64(4, 4)),
+                ("freelist.head".to_string(), Value::from_u64(head, 3)),
+                ("freelist.tail".to_string(), Value::from_u64(0, 3)),
+            ];
+            for (slot, pr) in [3, 4, slot2, 6, 7, 8].into_iter().enumerate() {
+                vals.push((format!("freelist.buf[{}]", slot), Value::from_u64(pr, 4)));
+            }
+            vals.into_iter().collect()
+        };
+        let diff = |snap: &Snapshot| -> Vec<(&'static str, String)> {
+            let state = RenameState::read(snap, 6);
+            m.diff(snap, &state, &IDLE, false)
+                .into_iter()
+                .map(|d| (d.state, d.entry.to_string()))
+                .collect()
+        };
+        assert!(diff(&snap(2, 5)).is_empty());
+        assert_eq!(diff(&snap(2, 1)), [("free", "2".to_string())]);
+        assert_eq!(diff(&snap(3, 5)), [("ring", "head".to_string())]);
+    }
+}
@@ -1,127 +1,158 @@
-use wellen::simple::Waveform;
-use wellen::{SignalRef, Time};
+use clap::{ArgMatches, Args, Command, FromArgMatches};
+use wellen::Time;
+
+use super::pass::{AnalysisPass, PassContext, RangeArgs};
+use crate::core::{Event, Value, snapshot_at};
+
+#[derive(Args)]
+struct RangeOnlyArgs {
+    #[command(flatten)]
+    range: RangeArgs,
+}
+
+/// `rob-iq-desync`: rob_enq fired while the IQ could not accept.
+pub struct RobIqDesync;
+
+impl AnalysisPass for RobIqDesync {
+    fn name(&self) -> &'static str {
+        "rob-iq-desync"
+    }
+
+    fn command(&self) -> Command {
+        RangeOnlyArgs::augment_args(
+            Command::new(self.name()).about(
+                "Scan for ROB-IQ desync: IQ full but rob_enq fired (default --to: from+50000)",
+            ),
+        )
+    }
+
+    fn output(&self) -> &'static str {
+        "ROB_IQ_DESYNC events (count: IQ count) when rob_enq fired with IQ in_ready=0, then the total count"
+    }
+
+    fn signals(&self) -> &'static [&'static str] {
+        &["iq.count", "iq.in.ready", "rob.enq.valid", "rob.enq.ready"]
+    }
+
+    fn run(
+        &self,
+        ctx: &mut PassContext<'_>,
+        args: &ArgMatches,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let a = RangeOnlyArgs::from_arg_matches(args)?;
+        let (start, end) = a.range.span(ctx.tb, 50000)?;
+        rob_iq_desync_scan(ctx, start, end)
+    }
+}
+
+/// `flush-sync`: ROB do_flush vs IQ flush edges.
+pub struct FlushSync;
+
+impl AnalysisPass for FlushSync {
+    fn name(&self) -> &'static str {
+        "flush-sync"
+    }
+
+    fn command(&self) -> Command {
+        RangeOnlyArgs::augment_args(Command::new(self.name()).about(
+            "Scan for flush timing: when do_flush/iq.flush change, check ROB vs IQ sync (default --to: from+50000)",
+        ))
+    }
+
+    fn output(&self) -> &'static str {
+        "FLUSH_EDGE events (rob_do_flush, iq_flush, mismatch) per flush edge; FLUSH_UNKNOWN when either is X/Z"
+    }
+
+    fn signals(&self) -> &'static [&'static str] {
+        &["rob.do_flush", "iq.flush"]
+    }
+
+    fn run(
+        &self,
+        ctx: &mut PassContext<'_>,
+        args: &ArgMatches,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let a = RangeOnlyArgs::from_arg_matches(args)?;
+        let (start, end) = a.range.span(ctx.tb, 50000)?;
+        flush_sync_scan(ctx, start, end)
+    }
+}
 
 /// Scan for ROB-IQ desync: IQ full but rob_enq fired (instruction entered ROB but not IQ).
-pub fn rob_iq_desync_scan(
-    wf: &mut Waveform,
-    time_table: &[Time],
+fn rob_iq_desync_scan(
+    ctx: &mut PassContext<'_>,
     start: Time,
     end: Time,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let hierarchy = wf.hierarchy();
-    let mut sigs: Vec<(String, SignalRef)> = Vec::new();
-    for var in hierarchy.iter_vars() {
-        let name = var.full_name(hierarchy);
-        if (name.contains("iq.count") || name.contains("iq.io_in_ready"))
-            || (name.contains("rob.enq_req") && (name.contains("valid") || name.contains("ready")))
-        {
-            sigs.push((name, var.signal_ref()));
-        }
-    }
-    let to_load: Vec<SignalRef> = sigs.iter().map(|(_, sr)| *sr).collect();
-    wf.load_signals(&to_load);
-
-    let get_val = |name_contains: &str, vals: &[(String, String)]| -> Option<String> {
-        vals.iter()
-            .find(|(n, _)| n.contains(name_contains))
-            .map(|(_, v)| v.clone())
-    };
+    let (wf, tb, sigs, out) = (&*ctx.wf, ctx.tb, ctx.sigs, &mut *ctx.out);
 
-    println!(
+    out.note(&format!(
         "Scanning for ROB-IQ desync: IQ full but rob_enq fired, t={}..{}\n",
         start, end
-    );
+    ))?;
 
     let mut found = 0u32;
-    for (i, &t) in time_table.iter().enumerate() {
-        if t < start || t > end {
-            continue;
-        }
-        let idx = i as u32;
-        let mut vals: Vec<(String, String)> = Vec::new();
-        for (name, sig_ref) in &sigs {
-            if let Some(sig) = wf.get_signal(*sig_ref) {
-                if let Some(offset) = sig.get_offset(idx) {
-                    let val = sig.get_value_at(&offset, 0).to_string();
-                    vals.push((name.clone(), val));
-                }
-            }
-        }
+    for s in tb.range(start, end) {
+        let idx = s.idx;
+        let snap = snapshot_at(wf, sigs, idx);
 
-        let iq_in_ready = get_val("iq.io_in_ready", &vals).map_or(false, |v| v == "1");
-        let rob_enq_valid = get_val("rob.enq_req_valid", &vals).map_or(false, |v| v == "1");
-        let rob_enq_ready = get_val("rob.enq_req_ready", &vals).map_or(false, |v| v == "1");
+        let iq_in_ready = snap.is_high("iq.in.ready");
+        let rob_enq_valid = snap.is_high("rob.enq.valid");
+        let rob_enq_ready = snap.is_high("rob.enq.ready");
         let rob_enq_fire = rob_enq_valid && rob_enq_ready;
-        let iq_count = get_val("iq.count", &vals);
+        let iq_count = snap.get("iq.count");
 
         if !iq_in_ready && rob_enq_fire {
             found += 1;
-            println!(
-                "t={} *** ROB-IQ DESYNC: IQ in_ready=0 but rob_enq fired (instruction entered ROB, not IQ) count={:?}",
-                t, iq_count
-            );
+            out.event(&Event::on(s, "ROB_IQ_DESYNC").with("count", iq_count))?;
         }
     }
-    println!("\nTotal ROB-IQ desync events found: {}", found);
+    out.note(&format!("\nTotal ROB-IQ desync events found: {}", found))?;
     Ok(())
 }
 
 /// Scan for flush timing: when do_flush/iq.flush change, check ROB vs IQ sync.
-pub fn flush_sync_scan(
-    wf: &mut Waveform,
-    time_table: &[Time],
+fn flush_sync_scan(
+    ctx: &mut PassContext<'_>,
     start: Time,
     end: Time,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let hierarchy = wf.hierarchy();
-    let mut sigs: Vec<(String, SignalRef)> = Vec::new();
-    for var in hierarchy.iter_vars() {
-        let name = var.full_name(hierarchy);
-        if (name.contains("rob") && name.contains("do_flush"))
-            || (name.contains("iq") && name.contains("flush"))
-        {
-            sigs.push((name, var.signal_ref()));
-        }
-    }
-    let to_load: Vec<SignalRef> = sigs.iter().map(|(_, sr)| *sr).collect();
-    wf.load_signals(&to_load);
-
-    let get_val = |name_contains: &str, vals: &[(String, String)]| -> Option<String> {
-        vals.iter()
-            .find(|(n, _)| n.contains(name_contains))
-            .map(|(_, v)| v.clone())
-    };
+    let (wf, tb, sigs, out) = (&*ctx.wf, ctx.tb, ctx.sigs, &mut *ctx.out);
 
-    println!("Scanning for flush timing, t={}..{}\n", start, end);
+    out.note(&format!(
+        "Scanning for flush timing, t={}..{}\n",
+        start, end
+    ))?;
 
     let mut prev_rob_flush: Option<bool> = None;
     let mut prev_iq_flush: Option<bool> = None;
 
-    for (i, &t) in time_table.iter().enumerate() {
-        if t < start || t > end {
-            continue;
-        }
-        let idx = i as u32;
-        let mut vals: Vec<(String, String)> = Vec::new();
-        for (name, sig_ref) in &sigs {
-            if let Some(sig) = wf.get_signal(*sig_ref) {
-                if let Some(offset) = sig.get_offset(idx) {
-                    let val = sig.get_value_at(&offset, 0).to_string();
-                    vals.push((name.clone(), val));
-                }
-            }
-        }
+    for s in tb.range(start, end) {
+        let idx = s.idx;
+        let snap = snapshot_at(wf, sigs, idx);
 
-        let rob_flush = get_val("rob.io_do_flush", &vals)
-            .or_else(|| get_val("do_flush", &vals))
-            .map(|v| v == "1");
-        let iq_flush = get_val("iq.io_flush", &vals).map(|v| v == "1");
+        let rob_flush_val = snap.get("rob.do_flush");
+        let iq_flush_val = snap.get("iq.flush");
+        if rob_flush_val.is_some_and(Value::is_unknown)
+            || iq_flush_val.is_some_and(Value::is_unknown)
+        {
+            out.event(
+                &Event::on(s, "FLUSH_UNKNOWN")
+                    .with("rob_do_flush", rob_flush_val)
+                    .with("iq_flush", iq_flush_val),
+            )?;
+        }
+        let rob_flush = rob_flush_val.and_then(Value::as_bool);
+        let iq_flush = iq_flush_val.and_then(Value::as_bool);
 
         if let (Some(rf), Some(if_)) = (rob_flush, iq_flush) {
             if prev_rob_flush != Some(rf) || prev_iq_flush != Some(if_) {
-                let sync = rf == if_;
-                let mark = if !sync { " *** MISMATCH" } else { "" };
-                println!("t={} rob_do_flush={} iq_flush={}{}", t, rf, if_, mark);
+                out.event(
+                    &Event::on(s, "FLUSH_EDGE")
+                        .with("rob_do_flush", rf)
+                        .with("iq_flush", if_)
+                        .with("mismatch", rf != if_),
+                )?;
             }
             prev_rob_flush = Some(rf);
             prev_iq_flush = Some(if_);
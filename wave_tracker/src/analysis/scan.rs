@@ -1,77 +1,159 @@
-use crate::core::binary_to_hex_contains;
-use wellen::simple::Waveform;
+use clap::{ArgMatches, Args, Command, FromArgMatches};
 use wellen::{SignalRef, Time};
 
+use super::pass::{AnalysisPass, PassContext, RangeArgs};
+use crate::core::{Event, Expr, Snapshot, Value, snapshot_at, value_at};
+
+#[derive(Args)]
+struct ScanArgs {
+    /// Signal name substring to watch (e.g. do_flush); with --where, extra signals to print
+    #[arg(short, long, required_unless_present = "cond")]
+    grep: Option<String>,
+
+    /// Print the cycles where this expression holds instead of value changes
+    /// (e.g. 'rose(rob.do_flush) && iq.count >= 3')
+    #[arg(short = 'w', long = "where", value_name = "EXPR")]
+    cond: Option<Expr>,
+
+    /// Extra signals to print with --where matches (schema or hierarchical names, comma separated)
+    #[arg(long, value_delimiter = ',', requires = "cond")]
+    show: Vec<String>,
+
+    #[command(flatten)]
+    range: RangeArgs,
+
+    /// Only print when any printed signal's value contains this string (e.g. "8000611" for next_pc ~0x80006118)
+    #[arg(long)]
+    filter_value: Option<String>,
+}
+
+/// `scan`: value changes of grep-matched signals over a window, or the cycles where a
+/// `--where` expression holds.
+pub struct Scan;
+
+impl AnalysisPass for Scan {
+    fn name(&self) -> &'static str {
+        "scan"
+    }
+
+    fn command(&self) -> Command {
+        ScanArgs::augment_args(Command::new(self.name()).about(
+            "Scan time range, print when signal matching grep changes or --where holds (default --to: from+500)",
+        ))
+    }
+
+    fn output(&self) -> &'static str {
+        "CHANGE events (idx, then one field per matched signal), for each sample where one changed; with --where, MATCH events (expression signals, --show and --grep signals) for each cycle where it holds"
+    }
+
+    fn wants_signal(&self, name: &str, args: &ArgMatches) -> bool {
+        args.get_one::<String>("grep")
+            .is_some_and(|g| name.contains(g.as_str()))
+    }
+
+    fn run(
+        &self,
+        ctx: &mut PassContext<'_>,
+        args: &ArgMatches,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let a = ScanArgs::from_arg_matches(args)?;
+        let (start, end) = a.range.span(ctx.tb, 500)?;
+        match a.cond {
+            Some(cond) => scan_where(ctx, cond, &a.show, start, end, a.filter_value.as_deref()),
+            None => scan_time_range(ctx, start, end, a.filter_value.as_deref()),
+        }
+    }
+}
+
+/// Whether any value's bit string or hex form contains `filter`.
+fn value_contains<'v>(mut vals: impl Iterator<Item = &'v Value>, filter: &str) -> bool {
+    vals.any(|v| {
+        v.to_string().contains(filter) || format!("{:x}", v).contains(&filter.to_ascii_lowercase())
+    })
+}
+
 /// Scan time range, print (time, value) when any matching signal changes.
-/// If filter_value is Some, only print when any signal's value string contains the filter.
-/// If filter_rd_index is Some, only print when a signal named *rd_index* has value matching the filter.
-pub fn scan_time_range(
-    wf: &mut Waveform,
-    to_show: &[(String, SignalRef)],
-    time_table: &[Time],
+/// If filter_value is Some, only print when any signal's bit string or hex value contains the filter.
+fn scan_time_range(
+    ctx: &mut PassContext<'_>,
     start: Time,
     end: Time,
     filter_value: Option<&str>,
-    filter_rd_index: Option<&str>,
-) {
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (wf, tb, to_show, out) = (&*ctx.wf, ctx.tb, ctx.sigs, &mut *ctx.out);
     if to_show.is_empty() {
-        return;
+        return Ok(());
     }
 
-    let to_load: Vec<SignalRef> = to_show.iter().map(|(_, sr)| *sr).collect();
-    wf.load_signals(&to_load);
-
-    let mut last_vals: Vec<String> = vec!["?".into(); to_show.len()];
-    for (i, &t) in time_table.iter().enumerate() {
-        if t < start {
+    let mut last_vals: Vec<Option<Value>> = vec![None; to_show.len()];
+    for s in tb.range(start, end) {
+        let idx = s.idx;
+        let vals: Vec<Option<Value>> = to_show
+            .iter()
+            .map(|(_, sig_ref)| value_at(wf, *sig_ref, idx))
+            .collect();
+        if vals == last_vals {
             continue;
         }
-        if t > end {
-            break;
-        }
-        let idx = i as u32;
-        let mut changed = false;
-        let mut vals = Vec::with_capacity(to_show.len());
-        for (j, (_name, sig_ref)) in to_show.iter().enumerate() {
-            if let Some(sig) = wf.get_signal(*sig_ref) {
-                if let Some(offset) = sig.get_offset(idx) {
-                    let val = sig.get_value_at(&offset, 0).to_string();
-                    if last_vals.get(j) != Some(&val) {
-                        changed = true;
-                    }
-                    vals.push(val);
+        if filter_value.is_none_or(|fv| value_contains(vals.iter().flatten(), fv)) {
+            let mut ev = Event::on(s, "CHANGE").with("idx", idx);
+            for ((name, _), v) in to_show.iter().zip(vals.iter()) {
+                if let Some(v) = v {
+                    ev = ev.with(name.as_str(), v);
                 }
             }
+            out.event(&ev)?;
         }
-        if changed {
-            let matches_value = filter_value.map_or(true, |fv| {
-                vals.iter().any(|v| {
-                    v.contains(fv)
-                        || (v
-                            .chars()
-                            .all(|c| c == '0' || c == '1' || c == 'x' || c == 'z')
-                            && binary_to_hex_contains(v, fv))
-                })
-            });
-            let matches_rd_index = filter_rd_index.map_or(true, |fri| {
-                to_show
-                    .iter()
-                    .zip(vals.iter())
-                    .any(|((name, _), v)| name.contains("commit_bits_rd_index") && v == fri)
-            });
-            if matches_value && matches_rd_index {
-                println!("t={} idx={}", t, idx);
-                for (j, (name, _)) in to_show.iter().enumerate() {
-                    if let Some(v) = vals.get(j) {
-                        println!("  {}: {}", name, v);
-                    }
-                }
-            }
-            for (j, v) in vals.iter().enumerate() {
-                if let Some(lv) = last_vals.get_mut(j) {
-                    *lv = v.clone();
-                }
+        last_vals = vals;
+    }
+    Ok(())
+}
+
+/// Evaluate `cond` on every cycle in the window and print the cycles where it holds,
+/// with the signals it reads, then `show`, then the grep-matched signals.
+fn scan_where(
+    ctx: &mut PassContext<'_>,
+    mut cond: Expr,
+    show: &[String],
+    start: Time,
+    end: Time,
+    filter_value: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut sigs: Vec<(String, SignalRef)> = Vec::new();
+    cond.bind(|name| -> Result<bool, Box<dyn std::error::Error>> {
+        let sig = ctx.lookup(name)?;
+        sigs.extend(sig.map(|s| (name.to_string(), s)));
+        Ok(sig.is_some())
+    })?;
+    for name in show {
+        let sig = ctx
+            .lookup(name)?
+            .ok_or_else(|| format!("Signal `{}` not found", name))?;
+        sigs.push((name.clone(), sig));
+    }
+    sigs.extend(ctx.sigs.iter().cloned());
+    let mut seen = std::collections::HashSet::new();
+    sigs.retain(|(name, _)| seen.insert(name.clone()));
+
+    let (wf, tb, out) = (&*ctx.wf, ctx.tb, &mut *ctx.out);
+    out.note(&format!("Cycles where {} (t={}..{})", cond, start, end))?;
+    // History functions see the cycle before the window too.
+    let mut prev: Option<Snapshot> = start
+        .checked_sub(1)
+        .and_then(|t| tb.at_or_before(t))
+        .map(|s| snapshot_at(wf, &sigs, s.idx));
+    for s in tb.range(start, end) {
+        let snap = snapshot_at(wf, &sigs, s.idx);
+        if cond.matches(&snap, prev.as_ref())
+            && filter_value.is_none_or(|fv| value_contains(snap.iter().map(|(_, v)| v), fv))
+        {
+            let mut ev = Event::on(s, "MATCH");
+            for (name, v) in snap.iter() {
+                ev = ev.with(name, v);
             }
+            out.event(&ev)?;
         }
+        prev = Some(snap);
     }
+    Ok(())
 }
@@ -0,0 +1,309 @@
+//! Sparse shadow memory: byte contents rebuilt from dbus writes, optionally seeded from the
+//! program image, with the access that last wrote each byte.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use clap::Args;
+use wellen::Time;
+
+use super::dbus::{Access, Dir};
+use crate::core::NumArg;
+
+/// `PT_LOAD` program header type.
+const PT_LOAD: u32 = 1;
+
+/// Image options shared by the passes that build a shadow memory.
+#[derive(Args)]
+pub struct ImageArgs {
+    /// Seed memory from this image: an ELF (loadable segments at their physical
+    /// address) or a raw binary loaded at --base
+    #[arg(long)]
+    image: Option<PathBuf>,
+
+    /// Load address of a raw image (CoreConfig.defaultPc); bare digits are hex
+    #[arg(long, value_parser = NumArg::parse_hex, default_value = "80000000")]
+    base: NumArg,
+}
+
+impl ImageArgs {
+    /// Shadow memory holding the image, or empty without one.
+    pub fn shadow(&self) -> Result<ShadowMem, Box<dyn std::error::Error>> {
+        let mut mem = ShadowMem::default();
+        if let Some(path) = &self.image {
+            let data = std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            mem.image = if data.starts_with(b"\x7fELF") {
+                elf_segments(&data).map_err(|e| format!("{}: {}", path.display(), e))?
+            } else {
+                vec![Segment {
+                    addr: self.base.get(),
+                    len: data.len() as u64,
+                    data,
+                }]
+            };
+        }
+        Ok(mem)
+    }
+}
+
+/// One range of the image: `data`, then zeros up to `len` bytes (`.bss`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Segment {
+    addr: u64,
+    data: Vec<u8>,
+    len: u64,
+}
+
+impl Segment {
+    fn get(&self, addr: u64) -> Option<u8> {
+        let off = addr.checked_sub(self.addr).filter(|&off| off < self.len)?;
+        Some(self.data.get(off as usize).copied().unwrap_or(0))
+    }
+}
+
+/// Loadable segments of a little-endian ELF32 / ELF64 file; `.bss` reads as zeros.
+fn elf_segments(data: &[u8]) -> Result<Vec<Segment>, String> {
+    let read = |off: usize, n: usize| -> Result<u64, String> {
+        let bytes = data
+            .get(off..off + n)
+            .ok_or_else(|| format!("truncated ELF at offset {:#x}", off))?;
+        Ok(bytes.iter().rev().fold(0, |v, &b| v << 8 | u64::from(b)))
+    };
+    if data.get(5) != Some(&1) {
+        return Err("only little-endian ELF is supported".into());
+    }
+    // (e_phoff, e_phentsize, e_phnum) and (p_offset, p_paddr, p_filesz, p_memsz) offsets.
+    let (wide, header, phdr) = match data.get(4) {
+        Some(1) => (4, (0x1c, 0x2a, 0x2c), (0x04, 0x0c, 0x10, 0x14)),
+        Some(2) => (8, (0x20, 0x36, 0x38), (0x08, 0x18, 0x20, 0x28)),
+        _ => return Err("unknown ELF class".into()),
+    };
+    let phoff = read(header.0, wide)? as usize;
+    let phentsize = read(header.1, 2)? as usize;
+    let phnum = read(header.2, 2)? as usize;
+
+    let mut segments = Vec::new();
+    for k in 0..phnum {
+        let ph = phoff + k * phentsize;
+        if read(ph, 4)? as u32 != PT_LOAD {
+            continue;
+        }
+        let offset = read(ph + phdr.0, wide)? as usize;
+        let paddr = read(ph + phdr.1, wide)?;
+        let filesz = read(ph + phdr.2, wide)? as usize;
+        let memsz = read(ph + phdr.3, wide)?;
+        let bytes = data
+            .get(offset..offset + filesz)
+            .ok_or_else(|| format!("segment {} runs past the end of the file", k))?;
+        segments.push(Segment {
+            addr: paddr,
+            data: bytes.to_vec(),
+            len: memsz.max(filesz as u64),
+        });
+    }
+    Ok(segments)
+}
+
+/// Where a shadow byte came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Writer {
+    Image,
+    /// Index of the write in the access log.
+    Store(usize),
+}
+
+#[derive(Clone, Default)]
+pub struct ShadowMem {
+    /// The image, kept as ranges: a segment's `.bss` can be far larger than the file.
+    image: Vec<Segment>,
+    /// Bytes written on the dbus.
+    bytes: HashMap<u64, (u8, Writer)>,
+}
+
+impl ShadowMem {
+    /// Apply the writes of `log` requested before `time`: one fired on that very edge
+    /// lands after the sample, like any register.
+    pub fn replay(&mut self, log: &[Access], time: Time) {
+        for (k, acc) in log.iter().enumerate() {
+            if acc.req.time < time {
+                self.apply(k, acc);
+            }
+        }
+    }
+
+    /// Apply write `k` of the access log; reads leave memory unchanged.
+    pub fn apply(&mut self, k: usize, acc: &Access) {
+        if acc.dir == Dir::Write {
+            for (addr, b) in acc.bytes() {
+                self.bytes.insert(addr, (b, Writer::Store(k)));
+            }
+        }
+    }
+
+    /// The byte at `addr` and its writer; a later segment wins where the image overlaps.
+    pub fn get(&self, addr: u64) -> Option<(u8, Writer)> {
+        self.bytes.get(&addr).copied().or_else(|| {
+            self.image
+                .iter()
+                .rev()
+                .find_map(|seg| seg.get(addr).map(|b| (b, Writer::Image)))
+        })
+    }
+
+    /// Little-endian value of `size` bytes at `addr`, if every byte is known.
+    pub fn value(&self, addr: u64, size: u64) -> Option<u64> {
+        (0..size).rev().try_fold(0, |v, k| {
+            self.get(addr + k).map(|(b, _)| v << 8 | u64::from(b))
+        })
+    }
+
+    /// `0x12ab??00`: bytes from `addr + size - 1` down to `addr`, `??` where unknown.
+    pub fn text(&self, addr: u64, size: u64) -> String {
+        let bytes: String = (0..size)
+            .rev()
+            .map(|k| match self.get(addr + k) {
+                Some((b, _)) => format!("{:02x}", b),
+                None => "??".to_string(),
+            })
+            .collect();
+        format!("0x{}", bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Sample;
+
+    /// ELF32 with a PT_LOAD of `file` at 0x80000000 sized `memsz` and a PT_NOTE after it.
+    fn elf32(file: &[u8], memsz: u32) -> Vec<u8> {
+        let (phoff, phentsize, data_off) = (52u32, 32u16, 52 + 2 * 32);
+        let mut elf = vec![0u8; data_off as usize];
+        elf[..6].copy_from_slice(b"\x7fELF\x01\x01");
+        elf[0x1c..0x20].copy_from_slice(&phoff.to_le_bytes());
+        elf[0x2a..0x2c].copy_from_slice(&phentsize.to_le_bytes());
+        elf[0x2c..0x2e].copy_from_slice(&2u16.to_le_bytes());
+        let ph = |elf: &mut Vec<u8>, k: usize, fields: [u32; 6]| {
+            // p_type, p_offset, p_vaddr, p_paddr, p_filesz, p_memsz
+            for (i, f) in fields.iter().enumerate() {
+                let at = phoff as usize + k * phentsize as usize + 4 * i;
+                elf[at..at + 4].copy_from_slice(&f.to_le_bytes());
+            }
+        };
+        let len = file.len() as u32;
+        ph(&mut elf, 0, [PT_LOAD, data_off, 0, 0x8000_0000, len, memsz]);
+        ph(&mut elf, 1, [4, data_off, 0, 0x9000_0000, len, len]);
+        elf.extend_from_slice(file);
+        elf
+    }
+
+    fn image(elf: &[u8]) -> ShadowMem {
+        ShadowMem {
+            image: elf_segments(elf).unwrap(),
+            ..ShadowMem::default()
+        }
+    }
+
+    fn write(time: u64, addr: u64, mask: u64, wdata: u64) -> Access {
+        Access {
+            req: Sample {
+                idx: time as u32,
+                time,
+                cycle: None,
+            },
+            req_pos: time as usize,
+            dir: Dir::Write,
+            addr,
+            mask,
+            wdata: Some(wdata),
+            user: None,
+            rob_id: None,
+            resp: None,
+            flushed: false,
+        }
+    }
+
+    #[test]
+    fn elf32_segments() {
+        let segments = elf_segments(&elf32(&[0x13, 0, 0, 0, 0xef, 0xbe], 12)).unwrap();
+        assert_eq!(
+            segments,
+            [Segment {
+                addr: 0x8000_0000,
+                data: vec![0x13, 0, 0, 0, 0xef, 0xbe],
+                len: 12,
+            }]
+        );
+
+        // File bytes, then zeros from filesz to memsz, then nothing.
+        let mem = image(&elf32(&[0x13, 0, 0, 0, 0xef, 0xbe], 12));
+        assert_eq!(mem.value(0x8000_0000, 4), Some(0x13));
+        assert_eq!(mem.value(0x8000_0004, 4), Some(0xbeef));
+        assert_eq!(mem.value(0x8000_0008, 4), Some(0));
+        assert_eq!(mem.get(0x8000_000c), None);
+        assert_eq!(mem.get(0x7fff_ffff), None);
+        assert_eq!(mem.get(0x9000_0000), None);
+        assert_eq!(mem.get(0x8000_0005), Some((0xbe, Writer::Image)));
+
+        // A large .bss is a range, not a byte per address.
+        let mem = image(&elf32(&[1], 0x4000_0000));
+        assert_eq!(mem.get(0xbfff_ffff), Some((0, Writer::Image)));
+        assert_eq!(mem.get(0xc000_0000), None);
+    }
+
+    #[test]
+    fn elf_errors() {
+        let elf = elf32(&[0x13, 0, 0, 0], 4);
+        assert_eq!(
+            elf_segments(&elf[..60]),
+            Err("truncated ELF at offset 0x40".to_string())
+        );
+        assert_eq!(
+            elf_segments(&elf[..elf.len() - 1]),
+            Err("segment 0 runs past the end of the file".to_string())
+        );
+        let mut big = elf.clone();
+        big[5] = 2;
+        assert!(elf_segments(&big).is_err());
+    }
+
+    #[test]
+    fn replay_before_the_sample() {
+        let log = [
+            write(10, 0x100, 0xf, 0x1122_3344),
+            write(20, 0x100, 0xf, 0x5566_7788),
+        ];
+        let mut mem = ShadowMem::default();
+        // The write on the sample's own edge lands after it.
+        mem.replay(&log, 20);
+        assert_eq!(mem.value(0x100, 4), Some(0x1122_3344));
+        let mut mem = ShadowMem::default();
+        mem.replay(&log, 21);
+        assert_eq!(mem.value(0x100, 4), Some(0x5566_7788));
+        let mut mem = ShadowMem::default();
+        mem.replay(&log, 10);
+        assert_eq!(mem.get(0x100), None);
+    }
+
+    #[test]
+    fn newest_write_wins() {
+        let mut mem = image(&elf32(&[0; 8], 8));
+        let log = [
+            write(10, 0x8000_0000, 0xf, 0x1122_3344),
+            // sb 0xaa to byte 1
+            write(20, 0x8000_0000, 0b0010, 0x0000_aa00),
+            // sh 0xbeef to bytes 2..3
+            write(30, 0x8000_0000, 0b1100, 0xbeef_0000),
+        ];
+        mem.replay(&log, 25);
+        assert_eq!(mem.value(0x8000_0000, 4), Some(0x1122_aa44));
+        assert_eq!(mem.get(0x8000_0000), Some((0x44, Writer::Store(0))));
+        assert_eq!(mem.get(0x8000_0001), Some((0xaa, Writer::Store(1))));
+        assert_eq!(mem.get(0x8000_0004), Some((0, Writer::Image)));
+
+        mem.apply(2, &log[2]);
+        assert_eq!(mem.text(0x8000_0000, 4), "0xbeefaa44");
+        assert_eq!(mem.get(0x8000_0003), Some((0xbe, Writer::Store(2))));
+        assert_eq!(mem.text(0x8000_0006, 4), "0x????0000");
+    }
+}
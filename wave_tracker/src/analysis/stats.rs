@@ -0,0 +1,292 @@
+use std::sync::LazyLock;
+
+use clap::{ArgMatches, Args, Command, FromArgMatches};
+
+use super::pass::{AnalysisPass, PassContext, RangeArgs};
+use super::rename::{self, FreeList, RenameState};
+use crate::core::{Event, Sample, Snapshot, snapshot_at};
+
+#[derive(Args)]
+struct StatsArgs {
+    /// Cycles per IPC window
+    #[arg(long, default_value_t = 1000)]
+    window: usize,
+
+    /// Cycles between window starts (default: --window, i.e. windows do not overlap)
+    #[arg(long)]
+    step: Option<usize>,
+
+    #[command(flatten)]
+    range: RangeArgs,
+}
+
+const PIPE_SIGNALS: &[&str] = &[
+    "rob.commit.valid",
+    "rob.enq.valid",
+    "rob.enq.ready",
+    "rob.do_flush",
+    "rob.head_ptr",
+    "rob.tail_ptr",
+    "iq.count",
+    "iq.valid[i]",
+    "lsq.slot[i].valid",
+];
+
+static SIGNALS: LazyLock<Vec<&'static str>> =
+    LazyLock::new(|| [PIPE_SIGNALS, rename::SIGNALS].concat());
+
+/// `stats`: IPC, flushes, dispatch rate and queue occupancy over a window.
+pub struct Stats;
+
+impl AnalysisPass for Stats {
+    fn name(&self) -> &'static str {
+        "stats"
+    }
+
+    fn command(&self) -> Command {
+        StatsArgs::augment_args(Command::new(self.name()).about(
+            "Throughput report: cycles, commits, IPC overall and per window, flushes, dispatches per cycle, and ROB / IQ / LSQ / FreeList occupancy histograms",
+        ))
+    }
+
+    fn output(&self) -> &'static str {
+        "SUMMARY (cycles, commits, ipc, ipc_min, ipc_max, flushes, dispatches, dispatch_per_cycle), WINDOW per IPC window (from, to, commits, ipc), OCCUPANCY per structure (structure, mean, max) and HIST per occupied level (structure, entries, cycles, share)"
+    }
+
+    fn signals(&self) -> &'static [&'static str] {
+        &SIGNALS
+    }
+
+    fn run(
+        &self,
+        ctx: &mut PassContext<'_>,
+        args: &ArgMatches,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let a = StatsArgs::from_arg_matches(args)?;
+        let (start, end) = a.range.or_last(ctx)?;
+        if a.window == 0 || a.step == Some(0) {
+            return Err("--window and --step must be at least 1".into());
+        }
+        if !ctx.has("rob.commit.valid") {
+            return Err("Could not find rob.commit.valid".into());
+        }
+
+        let mut hists = vec![Hist::new("dispatch")];
+        let rob = ctx.has("rob.head_ptr") && ctx.has("rob.tail_ptr");
+        let iq_count = ctx.has("iq.count");
+        let iq = iq_count || ctx.has("iq.valid[");
+        let lsq = ctx.has("lsq.slot[");
+        let free_list = ctx.has("freelist.free[") || ctx.has("freelist.head");
+        let (wf, tb, sigs, layout, out) = (&*ctx.wf, ctx.tb, ctx.sigs, &ctx.layout, &mut *ctx.out);
+        for (present, name, key) in [
+            (rob, "rob", "rob.head_ptr / rob.tail_ptr"),
+            (iq, "iq", "iq.count or iq.valid[i]"),
+            (lsq, "lsq", "lsq.slot[i].valid"),
+            (free_list, "freelist", "freelist.free[i] or freelist.head"),
+        ] {
+            if present {
+                hists.push(Hist::new(name));
+            } else {
+                out.note(&format!("{} not in the trace: no {} occupancy", key, name))?;
+            }
+        }
+
+        let samples = tb.cycles_in(start, end);
+        if !tb.has_cycles() {
+            out.note("No clock: every sample counts as a cycle")?;
+        }
+        out.note(&format!(
+            "Throughput report, t={}..{}, {} cycles",
+            start,
+            end,
+            samples.len()
+        ))?;
+
+        let mut commits = Vec::with_capacity(samples.len());
+        let (mut flushes, mut dispatches) = (0, 0);
+        let mut flushing = false;
+        for s in samples {
+            let snap = snapshot_at(wf, sigs, s.idx);
+            commits.push(snap.is_high("rob.commit.valid"));
+            let dispatch = snap.is_high("rob.enq.valid")
+                && snap
+                    .get("rob.enq.ready")
+                    .is_none_or(|v| v.as_bool() == Some(true));
+            dispatches += usize::from(dispatch);
+            // do_flush is a one-cycle pulse; count rising edges in case it is held.
+            let flush = snap.is_high("rob.do_flush");
+            flushes += usize::from(flush && !flushing);
+            flushing = flush;
+
+            let mut levels = vec![Some(usize::from(dispatch))];
+            if rob {
+                levels.push(rob_count(&snap, layout.rob_slots));
+            }
+            if iq {
+                levels.push(if iq_count {
+                    snap.u64("iq.count").map(|n| n as usize)
+                } else {
+                    Some(count_high(&snap, layout.iq_entries, |i| {
+                        format!("iq.valid[{}]", i)
+                    }))
+                });
+            }
+            if lsq {
+                levels.push(Some(count_high(&snap, layout.ls_slots, |i| {
+                    format!("lsq.slot[{}].valid", i)
+                })));
+            }
+            if free_list {
+                levels.push(free_count(&RenameState::read(&snap, layout.freelist_slots)));
+            }
+            for (h, level) in hists.iter_mut().zip(levels) {
+                if let Some(n) = level {
+                    h.add(n);
+                }
+            }
+        }
+
+        let cycles = samples.len();
+        let committed = commits.iter().filter(|&&c| c).count();
+        let step = a.step.unwrap_or(a.window);
+        let mut windows = Vec::new();
+        let mut w = 0;
+        while w + a.window <= cycles {
+            let n = commits[w..w + a.window].iter().filter(|&&c| c).count();
+            windows.push((w, n));
+            w += step;
+        }
+        let ipc_of = |n: usize| n as f64 / a.window as f64;
+        let ipc_min = windows.iter().map(|&(_, n)| n).min().map(ipc_of);
+        let ipc_max = windows.iter().map(|&(_, n)| n).max().map(ipc_of);
+
+        out.event(
+            &Event::new("SUMMARY")
+                .with("cycles", cycles)
+                .with("commits", committed)
+                .with("ipc", ratio(committed, cycles))
+                .with("ipc_min", ipc_min)
+                .with("ipc_max", ipc_max)
+                .with("flushes", flushes)
+                .with("dispatches", dispatches)
+                .with("dispatch_per_cycle", ratio(dispatches, cycles)),
+        )?;
+
+        out.note(&format!(
+            "\nIPC per {}-cycle window, every {} cycles:",
+            a.window, step
+        ))?;
+        if windows.is_empty() {
+            out.note("  (range shorter than one window)")?;
+        }
+        for &(w, n) in &windows {
+            out.event(
+                &Event::on(&samples[w], "WINDOW")
+                    .with("from", window_label(&samples[w]))
+                    .with("to", window_label(&samples[w + a.window - 1]))
+                    .with("commits", n)
+                    .with("ipc", ipc_of(n)),
+            )?;
+        }
+
+        for h in &hists {
+            out.note(&match h.name {
+                "dispatch" => "\nDispatches per cycle:".to_string(),
+                name => format!("\n{} occupancy:", name),
+            })?;
+            out.event(
+                &Event::new("OCCUPANCY")
+                    .with("structure", h.name)
+                    .with("mean", h.mean())
+                    .with("max", h.max()),
+            )?;
+            let total = h.total();
+            for (entries, &n) in h.cycles.iter().enumerate().filter(|(_, n)| **n > 0) {
+                out.event(
+                    &Event::new("HIST")
+                        .with("structure", h.name)
+                        .with("entries", entries)
+                        .with("cycles", n)
+                        .with("share", n as f64 / total as f64),
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Cycles spent at each occupancy of one structure.
+struct Hist {
+    name: &'static str,
+    /// `cycles[n]` is the number of cycles with `n` entries in use.
+    cycles: Vec<u64>,
+}
+
+impl Hist {
+    fn new(name: &'static str) -> Self {
+        Hist {
+            name,
+            cycles: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, n: usize) {
+        if n >= self.cycles.len() {
+            self.cycles.resize(n + 1, 0);
+        }
+        self.cycles[n] += 1;
+    }
+
+    fn total(&self) -> u64 {
+        self.cycles.iter().sum()
+    }
+
+    fn mean(&self) -> Option<f64> {
+        let weighted: u64 = self
+            .cycles
+            .iter()
+            .enumerate()
+            .map(|(n, c)| n as u64 * c)
+            .sum();
+        (self.total() > 0).then(|| weighted as f64 / self.total() as f64)
+    }
+
+    fn max(&self) -> Option<usize> {
+        self.cycles.iter().rposition(|&c| c > 0)
+    }
+}
+
+pub fn ratio(n: usize, d: usize) -> Option<f64> {
+    (d > 0).then(|| n as f64 / d as f64)
+}
+
+/// `c1234`, or the time without a clock.
+fn window_label(s: &Sample) -> String {
+    s.cycle.map_or(s.time.to_string(), |c| format!("c{}", c))
+}
+
+/// Live ROB entries; head/tail carry a wrap bit above the slot index.
+pub fn rob_count(snap: &Snapshot, rob_slots: usize) -> Option<usize> {
+    let depth = rob_slots.max(1) as u64;
+    let (head, tail) = (snap.u64("rob.head_ptr")?, snap.u64("rob.tail_ptr")?);
+    Some((tail.wrapping_sub(head) % (2 * depth)) as usize)
+}
+
+/// High valid bits `name(i)` for `i` in `0..n`.
+pub fn count_high(snap: &Snapshot, n: usize, name: impl Fn(usize) -> String) -> usize {
+    (0..n).filter(|&i| snap.is_high(&name(i))).count()
+}
+
+/// Free PRs other than PR0, which IDU never allocates.
+pub fn free_count(state: &RenameState) -> Option<usize> {
+    if matches!(state.free_list, FreeList::Missing) {
+        return None;
+    }
+    Some(
+        state
+            .free()
+            .iter()
+            .filter(|(_, pr)| pr.is_some_and(|pr| pr != 0))
+            .count(),
+    )
+}
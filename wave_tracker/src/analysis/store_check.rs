@@ -0,0 +1,322 @@
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use clap::{ArgMatches, Args, Command, FromArgMatches};
+use wellen::Time;
+
+use super::commits::{self, MemKind};
+use super::dbus::{self, Access, Dir, Owners};
+use super::pass::{AnalysisPass, PassContext, RangeArgs};
+use super::rename::arch_name;
+use super::tracker::{Instruction, InstructionTracker, Outcome, Stage};
+use super::words;
+use crate::core::{CoreLayout, Event, Field, Isa, NumArg, Sample, Snapshot, snapshot_at};
+
+#[derive(Args)]
+struct StoreCheckArgs {
+    /// Report every mismatching store, not only the first
+    #[arg(long)]
+    all: bool,
+
+    /// Rename steps to print per physical register involved in a mismatch
+    #[arg(long, default_value_t = 8)]
+    history: usize,
+
+    /// PC of the first commit when the trace does not show it (CoreConfig.defaultPc);
+    /// bare digits are hex
+    #[arg(long, value_parser = NumArg::parse_hex, default_value = "80000000")]
+    reset_pc: NumArg,
+
+    #[command(flatten)]
+    range: RangeArgs,
+}
+
+/// Commit's PR of each commit, and the AMT and PRF the model starts from.
+const MODEL_SIGNALS: &[&str] = &[
+    "rob.commit.p_rd",
+    "commit.restore_rmt[i]",
+    "prf.bank[b].data[i]",
+];
+
+static SIGNALS: LazyLock<Vec<&'static str>> = LazyLock::new(|| {
+    [
+        commits::SIGNALS,
+        InstructionTracker::SIGNALS,
+        dbus::SIGNALS,
+        MODEL_SIGNALS,
+    ]
+    .concat()
+});
+
+/// `store-check`: the data of every committed store against the architectural rs2.
+pub struct StoreCheck;
+
+impl AnalysisPass for StoreCheck {
+    fn name(&self) -> &'static str {
+        "store-check"
+    }
+
+    fn command(&self) -> Command {
+        StoreCheckArgs::augment_args(Command::new(self.name()).about(
+            "Check the dbus write data of every committed store against the value of its rs2 in program order, from a model of the AMT and PRF fed by the commits; on a mismatch show the rename history of the PRs involved",
+        ))
+    }
+
+    fn output(&self) -> &'static str {
+        "MISMATCH per bad store (first only without --all: pc, disasm, addr, size, data, expected, rs2, amt_pr, p_rs2, p_rs2_value, matches), each followed by the rename history of amt_pr and p_rs2 (ALLOC, COMMIT, SQUASH, FREE, READ: pr, seq, reg, pc, disasm, value); then SUMMARY (stores, checked, mismatches, unchecked)"
+    }
+
+    fn signals(&self) -> &'static [&'static str] {
+        &SIGNALS
+    }
+
+    fn run(
+        &self,
+        ctx: &mut PassContext<'_>,
+        args: &ArgMatches,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let a = StoreCheckArgs::from_arg_matches(args)?;
+        let (start, end) = a.range.or_last(ctx)?;
+        if !ctx.has("commit.msg.valid") {
+            return Err("Could not find commit.msg.valid".into());
+        }
+        if !ctx.has("dbus.req.wdata") {
+            return Err("Could not find dbus.req.wdata".into());
+        }
+        if !ctx.has("lsu.mem_req.rob_id") {
+            return Err(
+                "Could not find lsu.mem_req.rob_id: stores cannot be matched to commits".into(),
+            );
+        }
+        let seeded = ctx.has("commit.restore_rmt[") && ctx.has("prf.bank[");
+        let tracker = InstructionTracker::build(ctx.wf, ctx.tb, ctx.sigs, ctx.layout);
+        let wave = commits::commits(ctx.wf, ctx.tb, ctx.sigs, &tracker, a.reset_pc.get());
+        let (wf, tb, sigs, isa, layout, out) = (
+            &*ctx.wf,
+            ctx.tb,
+            ctx.sigs,
+            ctx.isa,
+            &ctx.layout,
+            &mut *ctx.out,
+        );
+        if !seeded {
+            out.note("commit.restore_rmt[i] / prf.bank[b].data[i] not in the trace: registers are unknown until first written")?;
+        }
+
+        // The bus write of each store, by the store's place in fetch order.
+        let log = dbus::accesses(wf, tb, sigs, 0, Time::MAX);
+        let owners = Owners::new(&tracker);
+        let writes: HashMap<usize, &Access> = log
+            .iter()
+            .filter(|acc| acc.dir == Dir::Write)
+            .filter_map(|acc| owners.of(acc).map(|i| (i.seq, acc)))
+            .collect();
+        let values: HashMap<usize, u64> = wave
+            .iter()
+            .filter_map(|c| Some((c.seq?, c.rd?.1)))
+            .collect();
+
+        let Some(first) = tb.cycles().first() else {
+            out.note("No samples in the trace")?;
+            return Ok(());
+        };
+        let mut model = ArchModel::read(&snapshot_at(wf, sigs, first.idx), layout);
+
+        out.note(&format!(
+            "Checking committed store data, t={}..{}",
+            start, end
+        ))?;
+        let (mut stores, mut checked, mut bad, mut unchecked) = (0usize, 0usize, 0usize, 0usize);
+        for c in &wave {
+            let inst = c.seq.map(|seq| &tracker.records()[seq]);
+            if c.mem == Some(MemKind::Store) && c.at.time >= start && c.at.time <= end {
+                stores += 1;
+                let acc = c.seq.and_then(|seq| writes.get(&seq).copied());
+                let Some((word, acc, data)) = c
+                    .inst
+                    .zip(acc)
+                    .and_then(|(w, acc)| Some((w, acc, acc.value()?)))
+                else {
+                    unchecked += 1;
+                    continue;
+                };
+                let rs2 = word >> 20 & 0x1f;
+                let mask = dbus::low_bytes(acc.size());
+                let Some(expected) = model.value(rs2).map(|v| v & mask) else {
+                    unchecked += 1;
+                    continue;
+                };
+                checked += 1;
+                if expected == data {
+                    continue;
+                }
+                bad += 1;
+                if bad > 1 && !a.all {
+                    continue;
+                }
+                let amt_pr = model.pr(rs2);
+                let p_rs2 = inst.and_then(|i| i.p_rs2);
+                let matches: Vec<String> = (1..32)
+                    .filter(|&r| model.value(r).is_some_and(|v| v & mask == data))
+                    .map(|r| arch_name(r as usize))
+                    .collect();
+                out.event(
+                    &Event::on(&c.at, "MISMATCH")
+                        .with("pc", c.pc.map_or(Field::Null, Field::Hex))
+                        .with("disasm", words::disasm(&isa, c.pc, c.inst))
+                        .with("addr", Field::Hex(acc.byte_addr()))
+                        .with("size", acc.size())
+                        .with("data", Field::Hex(data))
+                        .with("expected", Field::Hex(expected))
+                        .with("rs2", arch_name(rs2 as usize))
+                        .with("amt_pr", amt_pr)
+                        .with("p_rs2", p_rs2)
+                        .with(
+                            "p_rs2_value",
+                            p_rs2
+                                .and_then(|pr| model.pr_value(pr))
+                                .map_or(Field::Null, Field::Hex),
+                        )
+                        .with("matches", matches.join(" ")),
+                )?;
+                let mut prs = vec![amt_pr, p_rs2];
+                prs.dedup();
+                for pr in prs.into_iter().flatten().filter(|&pr| pr != 0) {
+                    out.note(&format!("\nRename history of PR{}:", pr))?;
+                    let steps = history(&isa, &tracker, &values, pr, inst, &c.at);
+                    for e in &steps[steps.len().saturating_sub(a.history)..] {
+                        out.event(e)?;
+                    }
+                }
+                out.note("")?;
+            }
+            if let Some((rd, value)) = c.rd {
+                let snap = snapshot_at(wf, sigs, c.at.idx);
+                let p_rd = snap.u64("rob.commit.p_rd").or(inst.and_then(|i| i.p_rd));
+                model.commit(rd, p_rd, value);
+            }
+        }
+
+        if bad == 0 {
+            out.note("  every checked store wrote its rs2")?;
+        }
+        out.event(
+            &Event::new("SUMMARY")
+                .with("stores", stores)
+                .with("checked", checked)
+                .with("mismatches", bad)
+                .with("unchecked", unchecked),
+        )?;
+        Ok(())
+    }
+}
+
+/// Commit's AMT and the PRF contents left by the committed instructions, in program order.
+struct ArchModel {
+    /// `amt[i]` is the committed PR of x(i+1).
+    amt: Vec<Option<u64>>,
+    prf: HashMap<u64, u64>,
+}
+
+impl ArchModel {
+    /// `commit.restore_rmt` and the PRF entries it maps at one sample; X/Z stays unknown.
+    fn read(snap: &Snapshot, layout: &CoreLayout) -> Self {
+        let amt: Vec<Option<u64>> = (0..31)
+            .map(|i| snap.u64(&format!("commit.restore_rmt[{}]", i)))
+            .collect();
+        let prf = amt
+            .iter()
+            .flatten()
+            .filter_map(|&pr| {
+                let (bank, idx) = layout.prf_bank(pr);
+                snap.u64(&format!("prf.bank[{}].data[{}]", bank, idx))
+                    .map(|v| (pr, v))
+            })
+            .collect();
+        ArchModel { amt, prf }
+    }
+
+    fn pr(&self, arch: u64) -> Option<u64> {
+        match arch {
+            0 => Some(0),
+            _ => self.amt.get(arch as usize - 1).copied().flatten(),
+        }
+    }
+
+    /// PR0 reads as 0 like the RTL's read ports.
+    fn pr_value(&self, pr: u64) -> Option<u64> {
+        match pr {
+            0 => Some(0),
+            _ => self.prf.get(&pr).copied(),
+        }
+    }
+
+    fn value(&self, arch: u64) -> Option<u64> {
+        self.pr(arch).and_then(|pr| self.pr_value(pr))
+    }
+
+    /// A commit writing `value` to x`rd` through `p_rd`; an unknown `p_rd` forgets x`rd`.
+    fn commit(&mut self, rd: u64, p_rd: Option<u64>, value: u64) {
+        if let Some(entry) = rd.checked_sub(1).and_then(|i| self.amt.get_mut(i as usize)) {
+            *entry = p_rd;
+        }
+        if let Some(pr) = p_rd {
+            self.prf.insert(pr, value);
+        }
+    }
+}
+
+/// Rename steps of `pr` up to `at`, in time order: allocated by an instruction, written at
+/// its commit, squashed by a flush, freed by a commit with it as old_p_rd, and read as rs2
+/// by `store`.
+fn history(
+    isa: &Isa,
+    tracker: &InstructionTracker,
+    values: &HashMap<usize, u64>,
+    pr: u64,
+    store: Option<&Instruction>,
+    at: &Sample,
+) -> Vec<Event> {
+    let step = |s: &Sample, kind: &'static str, i: &Instruction, reg: Option<u64>| {
+        Event::on(s, kind)
+            .with("pr", pr)
+            .with("seq", i.seq)
+            .with("reg", reg.map(|r| arch_name(r as usize)))
+            .with("pc", i.pc.map_or(Field::Null, Field::Hex))
+            .with("disasm", words::disasm(isa, i.pc, i.inst))
+    };
+    let mut steps: Vec<(Sample, usize, Event)> = Vec::new();
+    for i in tracker.records() {
+        if i.p_rd == Some(pr) {
+            if let Some(s) = i.at(Stage::Rename) {
+                steps.push((*s, i.seq, step(s, "ALLOC", i, i.rd_index)));
+            }
+            match (i.outcome(), i.at(Stage::Commit), &i.flushed) {
+                (Outcome::Committed, Some(s), _) => {
+                    let value = values.get(&i.seq).copied().map_or(Field::Null, Field::Hex);
+                    let e = step(s, "COMMIT", i, i.rd_index).with("value", value);
+                    steps.push((*s, i.seq, e));
+                }
+                (Outcome::Flushed, _, Some(s)) => {
+                    steps.push((*s, i.seq, step(s, "SQUASH", i, i.rd_index)))
+                }
+                _ => {}
+            }
+        }
+        if i.old_p_rd == Some(pr)
+            && i.outcome() == Outcome::Committed
+            && let Some(s) = i.at(Stage::Commit)
+        {
+            steps.push((*s, i.seq, step(s, "FREE", i, i.rd_index)));
+        }
+    }
+    if let Some(store) = store.filter(|i| i.p_rs2 == Some(pr)) {
+        let rs2 = store.inst.map(|w| w >> 20 & 0x1f);
+        if let Some(s) = store.at(Stage::Rename) {
+            steps.push((*s, store.seq, step(s, "READ", store, rs2)));
+        }
+    }
+    steps.retain(|(s, _, _)| s.time <= at.time);
+    steps.sort_by_key(|(s, seq, _)| (s.time, *seq));
+    steps.into_iter().map(|(_, _, e)| e).collect()
+}
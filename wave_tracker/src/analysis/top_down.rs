@@ -0,0 +1,415 @@
+use clap::{ArgMatches, Args, Command, FromArgMatches};
+
+use super::pass::{AnalysisPass, PassContext, RangeArgs};
+use super::rename::RenameState;
+use super::stats::{free_count, ratio, rob_count};
+use crate::core::{CoreLayout, Event, Sample, Snapshot, snapshot_at};
+
+#[derive(Args)]
+struct TopDownArgs {
+    /// Cycles per breakdown window
+    #[arg(long, default_value_t = 1000)]
+    window: usize,
+
+    /// Also print the cause of every cycle without a commit
+    #[arg(long)]
+    each: bool,
+
+    #[command(flatten)]
+    range: RangeArgs,
+}
+
+const SIGNALS: &[&str] = &[
+    "rob.commit.valid",
+    "rob.do_flush",
+    "rob.head_ptr",
+    "rob.tail_ptr",
+    "rob.slot[i].is_done",
+    "rob.slot[i].mem_type",
+    "rob.enq.valid",
+    "rob.enq.ready",
+    "isu.in.valid",
+    "iq.full",
+    "iq.in.ready",
+    "lsq.alloc.ready",
+    "idu.in.valid",
+    "freelist.free[i]",
+    "freelist.head",
+    "freelist.tail",
+    "freelist.buf[i]",
+];
+
+/// What a cycle was spent on; the first matching cause wins, in this order.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Cause {
+    Commit,
+    /// The flush cycle, then the empty ROB until the first instruction after it arrives.
+    Flush,
+    /// The instruction in the IDU->ISU register waits for a ROB slot.
+    RobFull,
+    /// ... for an issue queue entry.
+    IqFull,
+    /// ... for a load/store queue slot.
+    LsqFull,
+    /// IDU holds an instruction but the FreeList has no PR to give it.
+    Rename,
+    /// The ROB head is a load or store not done yet.
+    Memory,
+    /// The ROB head waits for its execution unit (or its operands).
+    Execute,
+    /// IDU's input is empty: nothing arrives from fetch / decode.
+    Frontend,
+    Other,
+}
+
+const CAUSES: [Cause; 10] = [
+    Cause::Commit,
+    Cause::Flush,
+    Cause::RobFull,
+    Cause::IqFull,
+    Cause::LsqFull,
+    Cause::Rename,
+    Cause::Memory,
+    Cause::Execute,
+    Cause::Frontend,
+    Cause::Other,
+];
+
+impl Cause {
+    fn name(self) -> &'static str {
+        match self {
+            Cause::Commit => "commit",
+            Cause::Flush => "flush",
+            Cause::RobFull => "rob_full",
+            Cause::IqFull => "iq_full",
+            Cause::LsqFull => "lsq_full",
+            Cause::Rename => "rename",
+            Cause::Memory => "memory",
+            Cause::Execute => "execute",
+            Cause::Frontend => "frontend",
+            Cause::Other => "other",
+        }
+    }
+
+    /// Top-level category, as in a top-down analysis.
+    fn group(self) -> &'static str {
+        match self {
+            Cause::Commit => "retiring",
+            Cause::Flush => "bad_speculation",
+            Cause::Frontend => "frontend",
+            Cause::Other => "other",
+            _ => "backend",
+        }
+    }
+}
+
+/// `top-down`: attribute every cycle without a commit to one stall cause.
+pub struct TopDown;
+
+impl AnalysisPass for TopDown {
+    fn name(&self) -> &'static str {
+        "top-down"
+    }
+
+    fn command(&self) -> Command {
+        TopDownArgs::augment_args(Command::new(self.name()).about(
+            "Classify each cycle without a commit by cause (flush recovery, ROB / IQ / LSQ full, rename stall, ROB head waiting on memory or execution, empty frontend); totals and per-window breakdown",
+        ))
+    }
+
+    fn output(&self) -> &'static str {
+        "CAUSE per cause (cause, group, cycles, share), WINDOW per window (from, to, then cycles per cause), and with --each a STALL per cycle without a commit (cause, group)"
+    }
+
+    fn signals(&self) -> &'static [&'static str] {
+        SIGNALS
+    }
+
+    fn run(
+        &self,
+        ctx: &mut PassContext<'_>,
+        args: &ArgMatches,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let a = TopDownArgs::from_arg_matches(args)?;
+        let (start, end) = a.range.or_last(ctx)?;
+        if a.window == 0 {
+            return Err("--window must be at least 1".into());
+        }
+        if !ctx.has("rob.commit.valid") {
+            return Err("Could not find rob.commit.valid".into());
+        }
+        let free_list = ctx.has("freelist.free[") || ctx.has("freelist.head");
+        for (found, key, what) in [
+            (ctx.has("rob.head_ptr"), "rob.head_ptr", "memory / execute"),
+            (ctx.has("rob.slot["), "rob.slot[i]", "memory / execute"),
+            (
+                ctx.has("isu.in.valid"),
+                "isu.in.valid",
+                "rob_full / iq_full / lsq_full",
+            ),
+            (ctx.has("idu.in.valid"), "idu.in.valid", "rename / frontend"),
+            (free_list, "freelist.free[i] / freelist.head", "rename"),
+        ] {
+            if !found {
+                ctx.out
+                    .note(&format!("{} not in the trace: no {} cycles", key, what))?;
+            }
+        }
+        let (wf, tb, sigs, layout, out) = (&*ctx.wf, ctx.tb, ctx.sigs, &ctx.layout, &mut *ctx.out);
+
+        let samples = tb.cycles_in(start, end);
+        out.note(&format!(
+            "Top-down cycle attribution, t={}..{}, {} cycles",
+            start,
+            end,
+            samples.len()
+        ))?;
+
+        let mut totals = [0usize; CAUSES.len()];
+        let mut windows: Vec<(&Sample, &Sample, [usize; CAUSES.len()])> = Vec::new();
+        let mut recovering = false;
+        for (i, s) in samples.iter().enumerate() {
+            let snap = snapshot_at(wf, sigs, s.idx);
+            let cause = classify(&snap, layout, &mut recovering);
+            totals[cause as usize] += 1;
+            if i % a.window == 0 {
+                windows.push((s, s, [0; CAUSES.len()]));
+            }
+            if let Some(w) = windows.last_mut() {
+                w.1 = s;
+                w.2[cause as usize] += 1;
+            }
+            if a.each && cause != Cause::Commit {
+                out.event(
+                    &Event::on(s, "STALL")
+                        .with("cause", cause.name())
+                        .with("group", cause.group()),
+                )?;
+            }
+        }
+
+        if a.each {
+            out.note("")?;
+        }
+        for cause in CAUSES {
+            let n = totals[cause as usize];
+            out.event(
+                &Event::new("CAUSE")
+                    .with("cause", cause.name())
+                    .with("group", cause.group())
+                    .with("cycles", n)
+                    .with("share", ratio(n, samples.len())),
+            )?;
+        }
+
+        out.note(&format!(
+            "\nCycles per cause, per {}-cycle window:",
+            a.window
+        ))?;
+        for (from, to, counts) in &windows {
+            let mut ev = Event::on(from, "WINDOW")
+                .with("from", label(from))
+                .with("to", label(to));
+            for cause in CAUSES {
+                ev = ev.with(cause.name(), counts[cause as usize]);
+            }
+            out.event(&ev)?;
+        }
+        Ok(())
+    }
+}
+
+/// `c1234`, or the time without a clock.
+fn label(s: &Sample) -> String {
+    s.cycle.map_or(s.time.to_string(), |c| format!("c{}", c))
+}
+
+/// Cause of one cycle; `recovering` carries the flush recovery state between cycles.
+fn classify(snap: &Snapshot, layout: &CoreLayout, recovering: &mut bool) -> Cause {
+    let rob_slots = layout.rob_slots;
+    let head = snap.u64("rob.head_ptr");
+    let count = rob_count(snap, rob_slots);
+    if snap.is_high("rob.do_flush") {
+        *recovering = true;
+    } else if count.is_some_and(|n| n > 0) {
+        *recovering = false;
+    }
+
+    if snap.is_high("rob.commit.valid") {
+        return Cause::Commit;
+    }
+    if *recovering {
+        return Cause::Flush;
+    }
+    // Dispatch blocked: ISU holds an instruction that does not enter the ROB.
+    if snap.is_high("isu.in.valid") && !snap.is_high("rob.enq.valid") {
+        if snap.is_low("rob.enq.ready") {
+            return Cause::RobFull;
+        }
+        if snap.is_high("iq.full") || snap.is_low("iq.in.ready") {
+            return Cause::IqFull;
+        }
+        if snap.is_low("lsq.alloc.ready") {
+            return Cause::LsqFull;
+        }
+    }
+    // IDU's renameStall: no free PR for the instruction it holds.
+    if snap.is_high("idu.in.valid")
+        && free_count(&RenameState::read(snap, layout.freelist_slots)) == Some(0)
+    {
+        return Cause::Rename;
+    }
+    if let (Some(head), Some(n)) = (head, count)
+        && n > 0
+    {
+        let slot = head % rob_slots.max(1) as u64;
+        if snap.is_low(&format!("rob.slot[{}].is_done", slot)) {
+            let mem = snap
+                .u64(&format!("rob.slot[{}].mem_type", slot))
+                .is_some_and(|t| t != 0);
+            return if mem { Cause::Memory } else { Cause::Execute };
+        }
+    }
+    if snap.is_low("idu.in.valid") {
+        return Cause::Frontend;
+    }
+    Cause::Other
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Value;
+
+    /// Four ROB slots, four PRs; `vals` on top of an idle core with PR3 free.
+    fn snap(vals: &[(&str, u64)]) -> Snapshot {
+        let mut all: Vec<(String, u64)> = [
+            ("rob.head_ptr", 0),
+            ("rob.tail_ptr", 0),
+            ("idu.in.valid", 0),
+            ("freelist.free[0]", 0),
+            ("freelist.free[1]", 0),
+            ("freelist.free[2]", 0),
+            ("freelist.free[3]", 1),
+        ]
+        .iter()
+        .map(|&(n, v)| (n.to_string(), v))
+        .collect();
+        for &(name, v) in vals {
+            match all.iter_mut().find(|(n, _)| n == name) {
+                Some(entry) => entry.1 = v,
+                None => all.push((name.to_string(), v)),
+            }
+        }
+        all.into_iter()
+            .map(|(n, v)| (n, Value::from_u64(v, 8)))
+            .collect()
+    }
+
+    fn layout() -> CoreLayout {
+        CoreLayout {
+            rob_slots: 4,
+            freelist_slots: 2,
+            ..CoreLayout::default()
+        }
+    }
+
+    fn cause(vals: &[(&str, u64)]) -> &'static str {
+        classify(&snap(vals), &layout(), &mut false).name()
+    }
+
+    #[test]
+    fn dispatch_and_rename() {
+        let isu = [("isu.in.valid", 1), ("rob.enq.valid", 0)];
+        assert_eq!(cause(&[isu[0], isu[1], ("rob.enq.ready", 0)]), "rob_full");
+        assert_eq!(cause(&[isu[0], isu[1], ("iq.full", 1)]), "iq_full");
+        assert_eq!(cause(&[isu[0], isu[1], ("iq.in.ready", 0)]), "iq_full");
+        assert_eq!(cause(&[isu[0], isu[1], ("lsq.alloc.ready", 0)]), "lsq_full");
+
+        // Rename is the FreeList running dry, whatever idu.in.ready says.
+        let empty = ("freelist.free[3]", 0);
+        assert_eq!(cause(&[("idu.in.valid", 1), empty]), "rename");
+        assert_eq!(
+            cause(&[("idu.in.valid", 1), empty, ("idu.in.ready", 1)]),
+            "rename"
+        );
+        assert_eq!(cause(&[("idu.in.valid", 1), ("idu.in.ready", 0)]), "other");
+        // Only PR0 free is still empty: IDU never allocates it.
+        assert_eq!(
+            cause(&[("idu.in.valid", 1), empty, ("freelist.free[0]", 1)]),
+            "rename"
+        );
+        assert_eq!(cause(&[empty]), "frontend");
+    }
+
+    #[test]
+    fn ring_free_list() {
+        let ring = |head, tail| {
+            Snapshot::from_iter([
+                ("idu.in.valid", Value::from_u64(1, 1)),
+                ("freelist.head", Value::from_u64(head, 2)),
+                ("freelist.tail", Value::from_u64(tail, 2)),
+                ("freelist.buf[0]", Value::from_u64(5, 8)),
+                ("freelist.buf[1]", Value::from_u64(6, 8)),
+            ])
+        };
+        let classify = |s: &Snapshot| classify(s, &layout(), &mut false).name();
+        assert_eq!(classify(&ring(1, 1)), "rename");
+        assert_eq!(classify(&ring(0, 1)), "other");
+    }
+
+    #[test]
+    fn rob_head_and_frontend() {
+        let busy = [
+            ("rob.head_ptr", 1),
+            ("rob.tail_ptr", 3),
+            ("idu.in.valid", 1),
+        ];
+        let head = |done, mem| {
+            let mut v = busy.to_vec();
+            v.extend([("rob.slot[1].is_done", done), ("rob.slot[1].mem_type", mem)]);
+            cause(&v)
+        };
+        assert_eq!(head(0, 2), "memory");
+        assert_eq!(head(0, 0), "execute");
+        assert_eq!(head(1, 0), "other");
+
+        // Frontend is IDU's input being empty, even with a non-empty ROB.
+        assert_eq!(
+            cause(&[
+                ("rob.head_ptr", 1),
+                ("rob.tail_ptr", 3),
+                ("rob.slot[1].is_done", 1)
+            ]),
+            "frontend"
+        );
+        // An empty ROB with IDU busy is not the frontend.
+        assert_eq!(cause(&[("idu.in.valid", 1)]), "other");
+        // X/Z idu.in.valid tells nothing.
+        let unknown: Snapshot = [("idu.in.valid", Value::from_bit_string("x"))]
+            .into_iter()
+            .collect();
+        assert_eq!(classify(&unknown, &layout(), &mut false).name(), "other");
+    }
+
+    #[test]
+    fn commit_and_flush_recovery() {
+        let l = layout();
+        let mut recovering = false;
+        let mut step = |vals: &[(&str, u64)]| classify(&snap(vals), &l, &mut recovering).name();
+        assert_eq!(step(&[("rob.commit.valid", 1)]), "commit");
+        assert_eq!(step(&[("rob.do_flush", 1)]), "flush");
+        // The ROB stays empty after the flush: still recovering, whatever else holds.
+        assert_eq!(
+            step(&[("idu.in.valid", 1), ("freelist.free[3]", 0)]),
+            "flush"
+        );
+        assert_eq!(step(&[]), "flush");
+        // The first instruction after the flush arrives.
+        assert_eq!(
+            step(&[("rob.tail_ptr", 1), ("rob.slot[0].is_done", 0)]),
+            "execute"
+        );
+        assert_eq!(step(&[]), "frontend");
+    }
+}
@@ -1,94 +1,108 @@
-use wellen::simple::Waveform;
-use wellen::{SignalRef, Time};
+use clap::{ArgMatches, Args, Command, FromArgMatches};
+use wellen::Time;
+
+use super::pass::{AnalysisPass, PassContext, RangeArgs};
+use crate::core::{Event, NumArg, Value, snapshot_at};
+
+#[derive(Args)]
+struct TracePrdArgs {
+    /// Physical register (e.g. 37, PR37, 0b100101)
+    p_rd: NumArg,
+
+    #[command(flatten)]
+    range: RangeArgs,
+}
+
+/// `trace-p-rd`: every pipeline location holding a p_rd over time.
+pub struct TracePrd;
+
+impl AnalysisPass for TracePrd {
+    fn name(&self) -> &'static str {
+        "trace-p-rd"
+    }
+
+    fn command(&self) -> Command {
+        TracePrdArgs::augment_args(
+            Command::new(self.name()).about("Trace p_rd (producer) through timeline"),
+        )
+    }
+
+    fn output(&self) -> &'static str {
+        "one event (with p_rd) per location where the p_rd appears: ROB_ENQ, IQ_IN, IQ_ENTRY (entry, valid), ISSUE (port), EXU_PIPE (pipe), ROB_SLOT (slot, done), LSQ_SLOT (slot)"
+    }
+
+    fn signals(&self) -> &'static [&'static str] {
+        &[
+            "rob.enq.valid",
+            "rob.enq.ready",
+            "rob.enq.p_rd",
+            "iq.in.valid",
+            "iq.in.p_rd",
+            "iq.valid[i]",
+            "iq.entry[i].p_rd",
+            "issue[port].valid",
+            "issue[port].p_rd",
+            "exu.pipe[i].p_rd",
+            "rob.slot[i].p_rd",
+            "rob.slot[i].is_done",
+            "lsq.slot[i].valid",
+            "lsq.slot[i].p_rd",
+        ]
+    }
+
+    fn run(
+        &self,
+        ctx: &mut PassContext<'_>,
+        args: &ArgMatches,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let a = TracePrdArgs::from_arg_matches(args)?;
+        let (start, end) = a.range.or_last(ctx)?;
+        trace_p_rd_timeline(ctx, a.p_rd.get(), start, end)
+    }
+}
 
 /// Trace p_rd (producer) through timeline: when instruction producing this PR appears.
-pub fn trace_p_rd_timeline(
-    wf: &mut Waveform,
-    time_table: &[Time],
-    pr_bin: &str,
+fn trace_p_rd_timeline(
+    ctx: &mut PassContext<'_>,
+    pr: u64,
     start: Time,
-    end: Option<Time>,
+    end_t: Time,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let hierarchy = wf.hierarchy();
-    let mut sigs: Vec<(String, SignalRef)> = Vec::new();
-    for var in hierarchy.iter_vars() {
-        let name = var.full_name(hierarchy);
-        if (name.contains("p_rd")
-            && (name.contains("rob.enq")
-                || name.contains("iq.entries_")
-                || name.contains("iq.io_in")
-                || name.contains("exu.")
-                || name.contains("memUnit.ls_slots_")))
-            || (name.contains("io_issuePorts_")
-                && (name.contains("valid") || name.contains("p_rd")))
-            || (name.contains("rob.slots_p_rd_") || name.contains("rob.slots_is_done_"))
-            || (name.contains("rob.enq_req_valid") || name.contains("rob.enq_req_ready"))
-            || (name.contains("iq.io_in_valid"))
-        {
-            sigs.push((name, var.signal_ref()));
-        }
-    }
-    let to_load: Vec<SignalRef> = sigs.iter().map(|(_, sr)| *sr).collect();
-    wf.load_signals(&to_load);
-
-    let get_val = |name_contains: &str, vals: &[(String, String)]| -> Option<String> {
-        vals.iter()
-            .find(|(n, _)| n.contains(name_contains))
-            .map(|(_, v)| v.clone())
-    };
-
-    let pr_trim = pr_bin.trim();
-    let last_t = time_table.last().copied().unwrap_or(0);
-    let end_t = end.unwrap_or(last_t);
-
-    println!(
-        "Tracing p_rd={} (PR{}) through pipeline, t={}..{}\n",
-        pr_trim,
-        u32::from_str_radix(pr_trim, 2).unwrap_or(99),
-        start,
-        end_t
-    );
-
-    for (i, &t) in time_table.iter().enumerate() {
-        if t < start {
-            continue;
-        }
-        if t > end_t {
-            break;
-        }
-        let idx = i as u32;
-        let mut vals: Vec<(String, String)> = Vec::new();
-        for (name, sig_ref) in &sigs {
-            if let Some(sig) = wf.get_signal(*sig_ref) {
-                if let Some(offset) = sig.get_offset(idx) {
-                    let val = sig.get_value_at(&offset, 0).to_string();
-                    vals.push((name.clone(), val));
-                }
-            }
-        }
+    let (wf, tb, sigs, out) = (&*ctx.wf, ctx.tb, ctx.sigs, &mut *ctx.out);
+    let layout = ctx.layout;
+
+    let matches = |v: Option<&Value>| v.is_some_and(|v| *v == pr);
+
+    out.note(&format!(
+        "Tracing p_rd=PR{} through pipeline, t={}..{}\n",
+        pr, start, end_t
+    ))?;
+
+    for s in tb.range(start, end_t) {
+        let idx = s.idx;
+        let snap = snapshot_at(wf, sigs, idx);
 
-        let mut events: Vec<String> = Vec::new();
-        let matches = |v: Option<&String>| v.as_deref().map(|s| s.trim()) == Some(pr_trim);
+        let mut events: Vec<Event> = Vec::new();
+        let ev = |kind| Event::on(s, kind).with("p_rd", pr);
 
-        let enq_valid = get_val("rob.enq_req_valid", &vals).map_or(false, |v| v == "1");
-        let enq_ready = get_val("rob.enq_req_ready", &vals).map_or(false, |v| v == "1");
-        let enq_p_rd = get_val("rob.enq_req_bits_p_rd", &vals);
-        if enq_valid && enq_ready && matches(enq_p_rd.as_ref()) {
-            events.push("ROB_ENQ".into());
+        let enq_valid = snap.is_high("rob.enq.valid");
+        let enq_ready = snap.is_high("rob.enq.ready");
+        let enq_p_rd = snap.get("rob.enq.p_rd");
+        if enq_valid && enq_ready && matches(enq_p_rd) {
+            events.push(ev("ROB_ENQ"));
         }
 
-        let iq_in_valid = get_val("iq.io_in_valid", &vals).map_or(false, |v| v == "1");
-        let iq_in_p_rd = get_val("iq.io_in_bits_p_rd", &vals);
-        if iq_in_valid && matches(iq_in_p_rd.as_ref()) {
-            events.push("IQ_IN".into());
+        let iq_in_valid = snap.is_high("iq.in.valid");
+        let iq_in_p_rd = snap.get("iq.in.p_rd");
+        if iq_in_valid && matches(iq_in_p_rd) {
+            events.push(ev("IQ_IN"));
         }
 
-        for entry in 0..8 {
-            let v = get_val(&format!("iq.entries_{}_p_rd", entry), &vals);
-            if matches(v.as_ref()) {
-                let valid =
-                    get_val(&format!("iq.valids_{}", entry), &vals).map_or(false, |x| x == "1");
-                events.push(format!("IQ_ent{} valid={}", entry, valid));
+        for entry in 0..layout.iq_entries {
+            let v = snap.get(&format!("iq.entry[{}].p_rd", entry));
+            if matches(v) {
+                let valid = snap.is_high(&format!("iq.valid[{}]", entry));
+                events.push(ev("IQ_ENTRY").with("entry", entry).with("valid", valid));
             }
         }
 
@@ -100,44 +114,38 @@ pub fn trace_p_rd_timeline(
             ("div", "DIV"),
             ("sysu", "SYSU"),
         ] {
-            let v = get_val(&format!("io_issuePorts_{}_valid", port), &vals)
-                .map_or(false, |x| x == "1");
-            let r = get_val(&format!("io_issuePorts_{}_bits_p_rd", port), &vals);
-            if v && matches(r.as_ref()) {
-                events.push(format!("ISSUE_{}", name));
+            let v = snap.is_high(&format!("issue[{}].valid", port));
+            let r = snap.get(&format!("issue[{}].p_rd", port));
+            if v && matches(r) {
+                events.push(ev("ISSUE").with("port", name));
             }
         }
 
-        for pipe in ["", "_1", "_2", "_3", "_4", "_5"] {
-            let r = get_val(&format!("exu.pipeOut_bits_r{}_p_rd", pipe), &vals);
-            if matches(r.as_ref()) {
-                events.push(format!(
-                    "EXU_pipe{}",
-                    if pipe.is_empty() { "0" } else { &pipe[1..] }
-                ));
+        for pipe in 0..layout.prf_write_ports {
+            let r = snap.get(&format!("exu.pipe[{}].p_rd", pipe));
+            if matches(r) {
+                events.push(ev("EXU_PIPE").with("pipe", pipe));
             }
         }
 
-        for slot in 0..16 {
-            let v = get_val(&format!("rob.slots_p_rd_{}", slot), &vals);
-            if matches(v.as_ref()) {
-                let done = get_val(&format!("rob.slots_is_done_{}", slot), &vals)
-                    .map_or(false, |x| x == "1");
-                events.push(format!("ROB_slot{} done={}", slot, done));
+        for slot in 0..layout.rob_slots {
+            let v = snap.get(&format!("rob.slot[{}].p_rd", slot));
+            if matches(v) {
+                let done = snap.is_high(&format!("rob.slot[{}].is_done", slot));
+                events.push(ev("ROB_SLOT").with("slot", slot).with("done", done));
             }
         }
 
-        for ls in 0..8 {
-            let v = get_val(&format!("memUnit.ls_slots_{}_valid", ls), &vals)
-                .map_or(false, |x| x == "1");
-            let r = get_val(&format!("memUnit.ls_slots_{}_p_rd", ls), &vals);
-            if v && matches(r.as_ref()) {
-                events.push(format!("MemUnit_ls{}", ls));
+        for ls in 0..layout.ls_slots {
+            let v = snap.is_high(&format!("lsq.slot[{}].valid", ls));
+            let r = snap.get(&format!("lsq.slot[{}].p_rd", ls));
+            if v && matches(r) {
+                events.push(ev("LSQ_SLOT").with("slot", ls));
             }
         }
 
-        if !events.is_empty() {
-            println!("t={} | {}", t, events.join(" "));
+        for e in &events {
+            out.event(e)?;
         }
     }
     Ok(())
@@ -1,49 +1,105 @@
-use wellen::simple::Waveform;
-use wellen::{SignalRef, Time};
+use std::sync::LazyLock;
+
+use clap::{ArgMatches, Args, Command, FromArgMatches};
+use wellen::Time;
+
+use super::pass::{AnalysisPass, PassContext};
+use super::words::{self, InstWords};
+use crate::core::{Event, Field, NumArg, TimeArg, Value, snapshot_at};
+
+#[derive(Args)]
+struct TracePcArgs {
+    /// PC; bare digits are hex (e.g. 80005c9c, 0x80005c9c)
+    #[arg(value_parser = NumArg::parse_hex)]
+    pc: NumArg,
+
+    /// Start: cycle (c1234), time with unit (1234ns) or raw timescale units (default: last-500)
+    #[arg(long)]
+    from: Option<TimeArg>,
+
+    /// End, same forms as --from (default: last)
+    #[arg(long)]
+    to: Option<TimeArg>,
+}
+
+static SIGNALS: LazyLock<Vec<&'static str>> = LazyLock::new(|| {
+    [
+        &[
+            "iq.in.valid",
+            "iq.in.pc",
+            "iq.valid[i]",
+            "iq.entry[i].pc",
+            "iq.entry[i].rs1_ready",
+            "iq.entry[i].rs2_ready",
+            "iq.entry[i].fu_type",
+            "issue[port].valid",
+            "issue[port].ready",
+            "issue[port].pc",
+            "iq.count",
+            "iq.flush",
+        ],
+        words::SIGNALS,
+    ]
+    .concat()
+});
+
+/// `trace-pc`: one instruction by PC through IQ, issue and BRU.
+pub struct TracePc;
+
+impl AnalysisPass for TracePc {
+    fn name(&self) -> &'static str {
+        "trace-pc"
+    }
+
+    fn command(&self) -> Command {
+        TracePcArgs::augment_args(
+            Command::new(self.name()).about("Trace instruction by PC through IQ/issue/BRU"),
+        )
+    }
+
+    fn output(&self) -> &'static str {
+        "PC events (disasm, iq_in, entry, valid, rs1, rs2, fu, issue, bru_valid, bru_ready, count, flush) per sample where the PC is in IQ, an issue port or BRU"
+    }
+
+    fn signals(&self) -> &'static [&'static str] {
+        &SIGNALS
+    }
+
+    fn run(
+        &self,
+        ctx: &mut PassContext<'_>,
+        args: &ArgMatches,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let a = TracePcArgs::from_arg_matches(args)?;
+        let last_t = ctx.last_time();
+        let start = match a.from {
+            Some(from) => from.resolve(ctx.tb)?,
+            None => last_t.saturating_sub(500),
+        };
+        let end = match a.to {
+            Some(to) => to.resolve(ctx.tb)?,
+            None => last_t,
+        };
+        trace_pc_timeline(ctx, a.pc.get(), start, end)
+    }
+}
 
 /// Trace instruction by PC through IQ/issue/BRU: find why it silently ends without entering BRU.
-pub fn trace_pc_timeline(
-    wf: &mut Waveform,
-    time_table: &[Time],
-    pc_bin: &str,
-    hex_pc: &str,
+fn trace_pc_timeline(
+    ctx: &mut PassContext<'_>,
+    pc: u64,
     start: Time,
     end: Time,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let hierarchy = wf.hierarchy();
-    let mut sigs: Vec<(String, SignalRef)> = Vec::new();
-    for var in hierarchy.iter_vars() {
-        let name = var.full_name(hierarchy);
-        if (name.contains("iq.entries_")
-            && (name.contains("pc")
-                || name.contains("valids_")
-                || name.contains("rs1_ready")
-                || name.contains("rs2_ready")
-                || name.contains("fu_type")))
-            || (name.contains("iq.io_in") && (name.contains("valid") || name.contains("pc")))
-            || (name.contains("io_issuePorts_")
-                && (name.contains("valid") || name.contains("ready") || name.contains("bits_pc")))
-            || (name.contains("iq.count") || name.contains("iq.full"))
-            || (name.contains("iq.io_flush") || name.contains("flush"))
-            || (name.contains("orderedPorts") && name.contains("ready"))
-        {
-            sigs.push((name, var.signal_ref()));
-        }
-    }
-    let to_load: Vec<SignalRef> = sigs.iter().map(|(_, sr)| *sr).collect();
-    wf.load_signals(&to_load);
-
-    let get_val = |name_contains: &str, vals: &[(String, String)]| -> Option<String> {
-        vals.iter()
-            .find(|(n, _)| n.contains(name_contains))
-            .map(|(_, v)| v.clone())
-    };
+    let (wf, tb, sigs, out) = (&*ctx.wf, ctx.tb, ctx.sigs, &mut *ctx.out);
+    let layout = ctx.layout;
+    let disasm = InstWords::collect(wf, tb, sigs).disasm(&ctx.isa, Some(pc));
 
-    let matches_pc = |v: Option<&String>| v.as_deref().map(|s| s.trim()) == Some(pc_bin);
+    let matches_pc = |v: Option<&Value>| v.is_some_and(|v| *v == pc);
 
     // FuType: 0=ALU, 1=BRU, 2=LSU, 3=MUL, 4=DIV, 5=SYSU
-    let fu_type_name = |ft: Option<&String>| -> String {
-        ft.and_then(|s| u32::from_str_radix(s.trim(), 2).ok())
+    let fu_type_name = |ft: Option<&Value>| -> String {
+        ft.and_then(Value::as_u64)
             .map(|n| match n {
                 0 => "ALU",
                 1 => "BRU",
@@ -57,47 +113,28 @@ pub fn trace_pc_timeline(
             .to_string()
     };
 
-    println!(
-        "Tracing PC=0x{} ({}) through IQ/BRU, t={}..{}\n",
-        hex_pc, pc_bin, start, end
-    );
-    println!(
-        "{:>6} | {:12} | {:8} | {:4} {:4} {:4} | {:4} | {:12} {:12} | {:6} {:6}",
-        "t", "IQ_in", "IQ_ent", "valid", "rs1", "rs2", "fu", "ISSUE", "BRU", "count", "flush"
-    );
-    println!("{}", "-".repeat(100));
-
-    for (i, &t) in time_table.iter().enumerate() {
-        if t < start || t > end {
-            continue;
-        }
-        let idx = i as u32;
-        let mut vals: Vec<(String, String)> = Vec::new();
-        for (name, sig_ref) in &sigs {
-            if let Some(sig) = wf.get_signal(*sig_ref) {
-                if let Some(offset) = sig.get_offset(idx) {
-                    let val = sig.get_value_at(&offset, 0).to_string();
-                    vals.push((name.clone(), val));
-                }
-            }
-        }
+    out.note(&format!(
+        "Tracing PC=0x{:08x} through IQ/BRU, t={}..{}\n",
+        pc, start, end
+    ))?;
 
-        let iq_in_valid = get_val("iq.io_in_valid", &vals).map_or(false, |v| v == "1");
-        let iq_in_pc = get_val("iq.io_in_bits_pc", &vals);
-        let iq_in = iq_in_valid && matches_pc(iq_in_pc.as_ref());
+    for s in tb.range(start, end) {
+        let idx = s.idx;
+        let snap = snapshot_at(wf, sigs, idx);
+
+        let iq_in_valid = snap.is_high("iq.in.valid");
+        let iq_in_pc = snap.get("iq.in.pc");
+        let iq_in = iq_in_valid && matches_pc(iq_in_pc);
 
         let mut iq_ent: Option<(usize, bool, bool, bool, String)> = None;
-        for entry in 0..8 {
-            let epc = get_val(&format!("iq.entries_{}_pc", entry), &vals);
-            if matches_pc(epc.as_ref()) {
-                let valid =
-                    get_val(&format!("iq.valids_{}", entry), &vals).map_or(false, |v| v == "1");
-                let r1 = get_val(&format!("iq.entries_{}_rs1_ready", entry), &vals)
-                    .map_or(false, |v| v == "1");
-                let r2 = get_val(&format!("iq.entries_{}_rs2_ready", entry), &vals)
-                    .map_or(false, |v| v == "1");
-                let ft = get_val(&format!("iq.entries_{}_fu_type", entry), &vals);
-                let ft_str = fu_type_name(ft.as_ref());
+        for entry in 0..layout.iq_entries {
+            let epc = snap.get(&format!("iq.entry[{}].pc", entry));
+            if matches_pc(epc) {
+                let valid = snap.is_high(&format!("iq.valid[{}]", entry));
+                let r1 = snap.is_high(&format!("iq.entry[{}].rs1_ready", entry));
+                let r2 = snap.is_high(&format!("iq.entry[{}].rs2_ready", entry));
+                let ft = snap.get(&format!("iq.entry[{}].fu_type", entry));
+                let ft_str = fu_type_name(ft);
                 if valid {
                     iq_ent = Some((entry, valid, r1, r2, ft_str));
                     break;
@@ -117,67 +154,52 @@ pub fn trace_pc_timeline(
             ("div", "DIV"),
             ("sysu", "SYSU"),
         ] {
-            let v = get_val(&format!("io_issuePorts_{}_valid", port), &vals)
-                .map_or(false, |x| x == "1");
-            let pc = get_val(&format!("io_issuePorts_{}_bits_pc", port), &vals);
-            if v && matches_pc(pc.as_ref()) {
+            let v = snap.is_high(&format!("issue[{}].valid", port));
+            let pc = snap.get(&format!("issue[{}].pc", port));
+            if v && matches_pc(pc) {
                 issue_port = Some(name.to_string());
                 break;
             }
         }
 
-        let bru_valid = get_val("io_issuePorts_bru_valid", &vals).map_or(false, |v| v == "1");
-        let bru_ready = get_val("io_issuePorts_bru_ready", &vals).map_or(false, |v| v == "1");
-        let bru_pc = get_val("io_issuePorts_bru_bits_pc", &vals);
-        let bru_has_pc = bru_valid && matches_pc(bru_pc.as_ref());
-
-        let count = get_val("iq.count", &vals)
-            .as_deref()
-            .map(|s| s.trim().to_string())
-            .unwrap_or_else(|| "?".into());
-        let flush = get_val("iq.io_flush", &vals).map_or(false, |v| v == "1");
-
-        let iq_in_str = if iq_in { "IN" } else { "-" };
-        let (ent_str, valid_str, r1_str, r2_str, ft_str) = match &iq_ent {
-            Some((e, v, r1, r2, ft)) => (
-                format!("[{}]", e),
-                if *v { "1" } else { "0" },
-                if *r1 { "1" } else { "0" },
-                if *r2 { "1" } else { "0" },
-                ft.clone(),
-            ),
-            None => ("-".into(), "-".into(), "-".into(), "-".into(), "-".into()),
-        };
-        let issue_str = issue_port.as_deref().unwrap_or("-");
-        let bru_str = if bru_has_pc {
-            format!(
-                "V={} R={}",
-                if bru_valid { "1" } else { "0" },
-                if bru_ready { "1" } else { "0" }
-            )
-        } else {
-            "-".into()
-        };
+        let bru_valid = snap.is_high("issue[bru].valid");
+        let bru_ready = snap.is_high("issue[bru].ready");
+        let bru_pc = snap.get("issue[bru].pc");
+        let bru_has_pc = bru_valid && matches_pc(bru_pc);
+
+        let count = snap.get("iq.count");
+        let flush = snap.is_high("iq.flush");
 
         if iq_in || iq_ent.is_some() || issue_port.is_some() || bru_has_pc {
-            println!(
-                "{:>6} | {:12} | {:8} | {:4} {:4} {:4} | {:4} | {:12} {:12} | {:6} {:6}",
-                t,
-                iq_in_str,
-                ent_str,
-                valid_str,
-                r1_str,
-                r2_str,
-                ft_str,
-                issue_str,
-                bru_str,
-                count,
-                if flush { "1" } else { "0" }
-            );
+            let mut ev = Event::on(s, "PC")
+                .with("pc", Field::Hex(pc))
+                .with("disasm", disasm.clone())
+                .with("iq_in", iq_in);
+            ev = match &iq_ent {
+                Some((e, v, r1, r2, ft)) => ev
+                    .with("entry", *e)
+                    .with("valid", *v)
+                    .with("rs1", *r1)
+                    .with("rs2", *r2)
+                    .with("fu", ft.as_str()),
+                None => ev
+                    .with("entry", Field::Null)
+                    .with("valid", Field::Null)
+                    .with("rs1", Field::Null)
+                    .with("rs2", Field::Null)
+                    .with("fu", Field::Null),
+            };
+            out.event(
+                &ev.with("issue", issue_port.as_deref())
+                    .with("bru_valid", bru_has_pc.then_some(bru_valid))
+                    .with("bru_ready", bru_has_pc.then_some(bru_ready))
+                    .with("count", count)
+                    .with("flush", flush),
+            )?;
         }
     }
-    println!("\nNote: valid=0 with matching PC may mean entry was dequeued (issued) or flushed.");
-    println!("      fu: FuType (BRU=1 for branch). If fu!=BRU, branch was mis-decoded.");
-    println!("      BRU_ready: only div_ready may exist in waveform; BRU ready often inlined.");
+    out.note("\nNote: valid=0 with matching PC may mean entry was dequeued (issued) or flushed.")?;
+    out.note("      fu: FuType (BRU=1 for branch). If fu!=BRU, branch was mis-decoded.")?;
+    out.note("      BRU_ready: only div_ready may exist in waveform; BRU ready often inlined.")?;
     Ok(())
 }
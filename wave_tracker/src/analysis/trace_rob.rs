@@ -1,97 +1,109 @@
-use wellen::simple::Waveform;
-use wellen::{SignalRef, Time};
+use clap::{ArgMatches, Args, Command, FromArgMatches};
+use wellen::Time;
+
+use super::pass::{AnalysisPass, PassContext, RangeArgs};
+use crate::core::{Event, NumArg, Value, snapshot_at};
+
+#[derive(Args)]
+struct TraceRobArgs {
+    /// rob_id (e.g. 7, 0x7, 0b0111)
+    rob_id: NumArg,
+
+    #[command(flatten)]
+    range: RangeArgs,
+}
+
+/// `trace-rob`: one rob_id through enq, IQ, issue, EXU and MemUnit.
+pub struct TraceRob;
+
+impl AnalysisPass for TraceRob {
+    fn name(&self) -> &'static str {
+        "trace-rob"
+    }
+
+    fn command(&self) -> Command {
+        TraceRobArgs::augment_args(
+            Command::new(self.name())
+                .about("Trace rob_id through full timeline: enq, IQ, issue, EXU, MemUnit"),
+        )
+    }
+
+    fn output(&self) -> &'static str {
+        "one event (with rob_id) per location where the rob_id appears: ROB_ENQ, IQ_IN, IQ_ENTRY (entry, valid), ISSUE (port), EXU_PIPE (pipe), FU_IN (fu), LSQ_SLOT (slot)"
+    }
+
+    fn signals(&self) -> &'static [&'static str] {
+        &[
+            "rob.enq.valid",
+            "rob.enq.ready",
+            "rob.enq.rob_id",
+            "iq.in.valid",
+            "iq.in.rob_id",
+            "iq.valid[i]",
+            "iq.entry[i].rob_id",
+            "issue[port].valid",
+            "issue[port].rob_id",
+            "exu.pipe[i].rob_id",
+            "exu.fu[fu].in_valid",
+            "exu.fu[fu].in_rob_id",
+            "lsq.slot[i].valid",
+            "lsq.slot[i].rob_id",
+        ]
+    }
+
+    fn run(
+        &self,
+        ctx: &mut PassContext<'_>,
+        args: &ArgMatches,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let a = TraceRobArgs::from_arg_matches(args)?;
+        let (start, end) = a.range.or_last(ctx)?;
+        trace_rob_id_timeline(ctx, a.rob_id.get(), start, end)
+    }
+}
 
 /// Trace rob_id through full timeline: when it enters ROB, IQ, gets issued, appears in EXU/MemUnit.
-pub fn trace_rob_id_timeline(
-    wf: &mut Waveform,
-    time_table: &[Time],
-    rid_bin: &str,
+fn trace_rob_id_timeline(
+    ctx: &mut PassContext<'_>,
+    rid: u64,
     start: Time,
-    end: Option<Time>,
+    end_t: Time,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let hierarchy = wf.hierarchy();
-    let mut sigs: Vec<(String, SignalRef)> = Vec::new();
-    for var in hierarchy.iter_vars() {
-        let name = var.full_name(hierarchy);
-        if (name.contains("rob_id")
-            && (name.contains("rob.enq")
-                || name.contains("iq.entries_")
-                || name.contains("iq.io_in")
-                || name.contains("exu.")
-                || name.contains("memUnit.ls_slots_")))
-            || (name.contains("io_issuePorts_")
-                && (name.contains("valid") || name.contains("rob_id")))
-            || (name.contains("iq.valids_"))
-            || (name.contains("rob.enq_req_valid") || name.contains("rob.enq_req_ready"))
-            || (name.contains("iq.io_in_valid") || name.contains("iq.io_in_ready"))
-        {
-            sigs.push((name, var.signal_ref()));
-        }
-    }
-    let to_load: Vec<SignalRef> = sigs.iter().map(|(_, sr)| *sr).collect();
-    wf.load_signals(&to_load);
-
-    let get_val = |name_contains: &str, vals: &[(String, String)]| -> Option<String> {
-        vals.iter()
-            .find(|(n, _)| n.contains(name_contains))
-            .map(|(_, v)| v.clone())
-    };
-
-    let rid_trim = rid_bin.trim();
-    let rid_num = u32::from_str_radix(rid_trim, 2).unwrap_or(99);
-    let matches = |v: Option<&String>| -> bool {
-        v.and_then(|s| u32::from_str_radix(s.trim(), 2).ok())
-            .map(|n| n == rid_num)
-            .unwrap_or(false)
-    };
-
-    let last_t = time_table.last().copied().unwrap_or(0);
-    let end_t = end.unwrap_or(last_t);
-
-    println!(
-        "Tracing rob_id={} (slot {}) through pipeline, t={}..{}\n",
-        rid_trim, rid_num, start, end_t
-    );
-
-    for (i, &t) in time_table.iter().enumerate() {
-        if t < start {
-            continue;
-        }
-        if t > end_t {
-            break;
-        }
-        let idx = i as u32;
-        let mut vals: Vec<(String, String)> = Vec::new();
-        for (name, sig_ref) in &sigs {
-            if let Some(sig) = wf.get_signal(*sig_ref) {
-                if let Some(offset) = sig.get_offset(idx) {
-                    let val = sig.get_value_at(&offset, 0).to_string();
-                    vals.push((name.clone(), val));
-                }
-            }
-        }
+    let (wf, tb, sigs, out) = (&*ctx.wf, ctx.tb, ctx.sigs, &mut *ctx.out);
+    let layout = ctx.layout;
+
+    let matches = |v: Option<&Value>| v.is_some_and(|v| *v == rid);
+
+    out.note(&format!(
+        "Tracing rob_id={} through pipeline, t={}..{}\n",
+        rid, start, end_t
+    ))?;
+
+    for s in tb.range(start, end_t) {
+        let idx = s.idx;
+        let snap = snapshot_at(wf, sigs, idx);
 
-        let mut events: Vec<String> = Vec::new();
+        let mut events: Vec<Event> = Vec::new();
+        let ev = |kind| Event::on(s, kind).with("rob_id", rid);
 
-        let enq_valid = get_val("rob.enq_req_valid", &vals).map_or(false, |v| v == "1");
-        let enq_ready = get_val("rob.enq_req_ready", &vals).map_or(false, |v| v == "1");
-        let enq_rid = get_val("rob.enq_rob_id", &vals);
-        if enq_valid && enq_ready && matches(enq_rid.as_ref()) {
-            events.push("ROB_ENQ".into());
+        let enq_valid = snap.is_high("rob.enq.valid");
+        let enq_ready = snap.is_high("rob.enq.ready");
+        let enq_rid = snap.get("rob.enq.rob_id");
+        if enq_valid && enq_ready && matches(enq_rid) {
+            events.push(ev("ROB_ENQ"));
         }
 
-        let iq_in_valid = get_val("iq.io_in_valid", &vals).map_or(false, |v| v == "1");
-        let iq_in_rid = get_val("iq.io_in_bits_rob_id", &vals);
-        if iq_in_valid && matches(iq_in_rid.as_ref()) {
-            events.push("IQ_IN".into());
+        let iq_in_valid = snap.is_high("iq.in.valid");
+        let iq_in_rid = snap.get("iq.in.rob_id");
+        if iq_in_valid && matches(iq_in_rid) {
+            events.push(ev("IQ_IN"));
         }
 
-        for entry in 0..8 {
-            let v = get_val(&format!("iq.entries_{}_rob_id", entry), &vals);
-            if matches(v.as_ref()) {
-                let valid =
-                    get_val(&format!("iq.valids_{}", entry), &vals).map_or(false, |x| x == "1");
-                events.push(format!("IQ_ent{} valid={}", entry, valid));
+        for entry in 0..layout.iq_entries {
+            let v = snap.get(&format!("iq.entry[{}].rob_id", entry));
+            if matches(v) {
+                let valid = snap.is_high(&format!("iq.valid[{}]", entry));
+                events.push(ev("IQ_ENTRY").with("entry", entry).with("valid", valid));
             }
         }
 
@@ -103,52 +115,46 @@ pub fn trace_rob_id_timeline(
             ("div", "DIV"),
             ("sysu", "SYSU"),
         ] {
-            let v = get_val(&format!("io_issuePorts_{}_valid", port), &vals)
-                .map_or(false, |x| x == "1");
-            let r = get_val(&format!("io_issuePorts_{}_bits_rob_id", port), &vals);
-            if v && matches(r.as_ref()) {
-                events.push(format!("ISSUE_{}", name));
+            let v = snap.is_high(&format!("issue[{}].valid", port));
+            let r = snap.get(&format!("issue[{}].rob_id", port));
+            if v && matches(r) {
+                events.push(ev("ISSUE").with("port", name));
             }
         }
 
-        for pipe in ["", "_1", "_2", "_3", "_4", "_5"] {
-            let r = get_val(&format!("exu.pipeOut_bits_r{}_rob_id", pipe), &vals);
-            if matches(r.as_ref()) {
-                events.push(format!(
-                    "EXU_pipe{}",
-                    if pipe.is_empty() { "0" } else { &pipe[1..] }
-                ));
+        for pipe in 0..layout.prf_write_ports {
+            let r = snap.get(&format!("exu.pipe[{}].rob_id", pipe));
+            if matches(r) {
+                events.push(ev("EXU_PIPE").with("pipe", pipe));
             }
         }
 
         for fu in ["alu", "bru", "agu", "sysu"] {
-            let r = get_val(&format!("exu.{}.io_in_bits_rob_id", fu), &vals);
-            if matches(r.as_ref()) {
-                let v =
-                    get_val(&format!("exu.{}.io_in_valid", fu), &vals).map_or(false, |x| x == "1");
+            let r = snap.get(&format!("exu.fu[{}].in_rob_id", fu));
+            if matches(r) {
+                let v = snap.is_high(&format!("exu.fu[{}].in_valid", fu));
                 if v {
-                    events.push(format!("{}_in", fu.to_uppercase()));
+                    events.push(ev("FU_IN").with("fu", fu));
                 }
             }
         }
         for fu in ["mul", "div"] {
-            let r = get_val(&format!("exu.{}.io_in_bits_rob_id", fu), &vals);
-            if matches(r.as_ref()) {
-                events.push(format!("{}_in", fu.to_uppercase()));
+            let r = snap.get(&format!("exu.fu[{}].in_rob_id", fu));
+            if matches(r) {
+                events.push(ev("FU_IN").with("fu", fu));
             }
         }
 
-        for ls in 0..8 {
-            let v = get_val(&format!("memUnit.ls_slots_{}_valid", ls), &vals)
-                .map_or(false, |x| x == "1");
-            let r = get_val(&format!("memUnit.ls_slots_{}_rob_id", ls), &vals);
-            if v && matches(r.as_ref()) {
-                events.push(format!("MemUnit_ls{}", ls));
+        for ls in 0..layout.ls_slots {
+            let v = snap.is_high(&format!("lsq.slot[{}].valid", ls));
+            let r = snap.get(&format!("lsq.slot[{}].rob_id", ls));
+            if v && matches(r) {
+                events.push(ev("LSQ_SLOT").with("slot", ls));
             }
         }
 
-        if !events.is_empty() {
-            println!("t={} | {}", t, events.join(" "));
+        for e in &events {
+            out.event(e)?;
         }
     }
     Ok(())
@@ -0,0 +1,719 @@
+//! Per-instruction lifecycle reconstruction: one [`Instruction`] per dynamic instruction,
+//! built in a single walk over the cycle samples.
+//!
+//! The frontend is in order, so fetch, decode, rename and ROB enqueue go to the oldest
+//! instruction still waiting for that stage (checked against its PC or p_rd where the
+//! stage carries one). From ROB enqueue on, an instruction is found by its rob_id among
+//! the live ones; commit and `rob.do_flush` end it, so a rob_id reused after a wraparound
+//! or a flush starts a new record.
+
+use std::collections::{HashMap, VecDeque};
+
+use wellen::{SignalRef, Time, simple::Waveform};
+
+use crate::core::{CoreLayout, Sample, Snapshot, Timebase, snapshot_at};
+
+/// Issue ports / functional units, as named in the schema (`issue[alu].valid`). These are
+/// names, not a count: a build without one of them simply never fires it. The write-back
+/// pipes behind them (`exu.pipe[i]`) are indexed and counted by
+/// [`CoreLayout::prf_write_ports`].
+pub const PORTS: [&str; 6] = ["alu", "bru", "agu", "mul", "div", "sysu"];
+
+/// `FuType.LSU`.
+const FU_LSU: u64 = 2;
+
+/// Upper bound on ROB access ports probed in the schema (`rob.access[i]`).
+const MAX_ACCESS_PORTS: usize = 8;
+
+/// Pipeline stages, in program order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Stage {
+    /// IFU output handshake.
+    Fetch,
+    /// IDU input handshake.
+    Decode,
+    /// IDU output handshake (p_rd / old_p_rd allocated).
+    Rename,
+    /// ROB enqueue (rob_id assigned).
+    Dispatch,
+    /// Issue queue enqueue.
+    IqEnq,
+    /// Issue port handshake.
+    Issue,
+    /// Functional unit input.
+    Exec,
+    /// Memory issue by the LSU (loads and stores only).
+    Mem,
+    /// First ROB update with `is_done`.
+    Writeback,
+    /// ROB commit.
+    Commit,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 10] = [
+        Stage::Fetch,
+        Stage::Decode,
+        Stage::Rename,
+        Stage::Dispatch,
+        Stage::IqEnq,
+        Stage::Issue,
+        Stage::Exec,
+        Stage::Mem,
+        Stage::Writeback,
+        Stage::Commit,
+    ];
+
+    /// Field name in events.
+    pub fn name(self) -> &'static str {
+        match self {
+            Stage::Fetch => "fetch",
+            Stage::Decode => "decode",
+            Stage::Rename => "rename",
+            Stage::Dispatch => "dispatch",
+            Stage::IqEnq => "iq_enq",
+            Stage::Issue => "issue",
+            Stage::Exec => "exec",
+            Stage::Mem => "mem",
+            Stage::Writeback => "writeback",
+            Stage::Commit => "commit",
+        }
+    }
+}
+
+/// How an instruction left the pipeline.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Committed,
+    /// Squashed by `rob.do_flush`, or dropped by the frontend before dispatch.
+    Flushed,
+    /// Still in the pipeline at the end of the trace.
+    InFlight,
+}
+
+impl Outcome {
+    pub fn name(self) -> &'static str {
+        match self {
+            Outcome::Committed => "commit",
+            Outcome::Flushed => "flush",
+            Outcome::InFlight => "in_flight",
+        }
+    }
+}
+
+/// One dynamic instruction. Fields are `None` when the trace lacks the signal or the
+/// instruction never reached the stage that carries it.
+#[derive(Clone, Debug, Default)]
+pub struct Instruction {
+    /// Position in fetch order over the whole trace, from 0.
+    pub seq: usize,
+    /// Flushes seen before the instruction entered the pipeline.
+    pub epoch: u64,
+    pub pc: Option<u64>,
+    pub inst: Option<u64>,
+    pub rob_id: Option<u64>,
+    pub rd_index: Option<u64>,
+    pub p_rd: Option<u64>,
+    pub old_p_rd: Option<u64>,
+    /// Source physical registers read at rename (0 for x0 or no source).
+    pub p_rs1: Option<u64>,
+    pub p_rs2: Option<u64>,
+    pub lsq_id: Option<u64>,
+    pub fu_type: Option<u64>,
+    /// Issue port (one of [`PORTS`]).
+    pub port: Option<&'static str>,
+    /// PC of the next instruction, from the commit port.
+    pub next_pc: Option<u64>,
+    /// Sample of the flush that squashed it.
+    pub flushed: Option<Sample>,
+    stages: [Option<Sample>; Stage::ALL.len()],
+}
+
+impl Instruction {
+    /// Sample at which the instruction reached `stage`.
+    pub fn at(&self, stage: Stage) -> Option<&Sample> {
+        self.stages[stage as usize].as_ref()
+    }
+
+    /// Record `stage` unless it was already seen (a stall repeats nothing: stages are
+    /// handshakes, recorded once).
+    pub(super) fn reach(&mut self, stage: Stage, s: &Sample) {
+        self.stages[stage as usize].get_or_insert(*s);
+    }
+
+    pub fn outcome(&self) -> Outcome {
+        if self.at(Stage::Commit).is_some() {
+            Outcome::Committed
+        } else if self.flushed.is_some() {
+            Outcome::Flushed
+        } else {
+            Outcome::InFlight
+        }
+    }
+
+    /// Stages reached, in pipeline order.
+    pub fn stages(&self) -> impl Iterator<Item = (Stage, &Sample)> {
+        Stage::ALL
+            .into_iter()
+            .filter_map(|st| self.at(st).map(|s| (st, s)))
+    }
+
+    /// First sample the instruction was seen at.
+    pub fn first(&self) -> Option<&Sample> {
+        self.stages().map(|(_, s)| s).min_by_key(|s| s.time)
+    }
+
+    /// Commit or flush sample, else the last stage reached.
+    pub fn last(&self) -> Option<&Sample> {
+        self.at(Stage::Commit)
+            .or(self.flushed.as_ref())
+            .or_else(|| self.stages().map(|(_, s)| s).max_by_key(|s| s.time))
+    }
+}
+
+/// Cycle number of a sample, or its time when the trace has no clock.
+pub fn stamp(s: &Sample) -> u64 {
+    s.cycle.unwrap_or(s.time)
+}
+
+fn fired(snap: &Snapshot, valid: &str, ready: &str) -> bool {
+    // Ports without a ready signal fire on valid.
+    snap.is_high(valid) && snap.get(ready).is_none_or(|r| r.as_bool() == Some(true))
+}
+
+#[derive(Default)]
+struct Builder {
+    insts: Vec<Instruction>,
+    /// Not yet dispatched, oldest first.
+    front: VecDeque<usize>,
+    /// rob_id -> dispatched, not yet committed.
+    live: HashMap<u64, usize>,
+    epoch: u64,
+    rob_slots: u64,
+}
+
+impl Builder {
+    fn push(&mut self) -> usize {
+        let i = self.insts.len();
+        self.insts.push(Instruction {
+            seq: i,
+            epoch: self.epoch,
+            ..Default::default()
+        });
+        i
+    }
+
+    /// Oldest front instruction that has not reached `stage` and satisfies `fits`. Older
+    /// ones that never reached it were dropped by the frontend. Without a match a new
+    /// record starts (the trace lacks the earlier stages).
+    fn front_at(&mut self, stage: Stage, s: &Sample, fits: impl Fn(&Instruction) -> bool) -> usize {
+        let pos = self.front.iter().position(|&i| {
+            let inst = &self.insts[i];
+            inst.at(stage).is_none() && fits(inst)
+        });
+        let Some(pos) = pos else {
+            let i = self.push();
+            self.front.push_back(i);
+            return i;
+        };
+        let older: Vec<usize> = self.front.drain(..pos).collect();
+        let found = self.front[0];
+        for &i in older.iter().rev() {
+            if self.insts[i].at(stage).is_some() {
+                self.front.push_front(i);
+            } else {
+                self.insts[i].flushed = Some(*s);
+            }
+        }
+        found
+    }
+
+    fn step(&mut self, s: &Sample, snap: &Snapshot) {
+        // Oldest stage first: in one cycle a later stage belongs to an older instruction.
+        if snap.is_high("rob.commit.valid") {
+            let rob_id = snap
+                .u64("rob.commit.rob_id")
+                .or_else(|| snap.u64("rob.head_ptr").map(|h| h % self.rob_slots.max(1)));
+            if let Some(i) = rob_id.and_then(|r| self.live.remove(&r)) {
+                let inst = &mut self.insts[i];
+                inst.reach(Stage::Commit, s);
+                inst.next_pc = snap.u64("rob.commit.next_pc");
+            }
+        }
+
+        let mut done = Vec::new();
+        for port in 0..MAX_ACCESS_PORTS {
+            let valid = format!("rob.access[{}].valid", port);
+            if snap.get(&valid).is_none() {
+                break;
+            }
+            if snap.is_high(&valid) && snap.is_high(&format!("rob.access[{}].is_done", port)) {
+                done.extend(snap.u64(&format!("rob.access[{}].rob_id", port)));
+            }
+        }
+        if snap.is_high("rob.mem_access.valid") && snap.is_high("rob.mem_access.is_done") {
+            done.extend(snap.u64("rob.mem_access.rob_id"));
+        }
+        for rob_id in done {
+            if let Some(&i) = self.live.get(&rob_id) {
+                self.insts[i].reach(Stage::Writeback, s);
+            }
+        }
+
+        if snap.is_high("rob.mem_issue")
+            && snap.is_high("rob.mem_issue_rob_id.valid")
+            && let Some(&i) = snap
+                .u64("rob.mem_issue_rob_id")
+                .and_then(|r| self.live.get(&r))
+        {
+            self.insts[i].reach(Stage::Mem, s);
+        }
+
+        for fu in PORTS {
+            if !snap.is_high(&format!("exu.fu[{}].in_valid", fu)) {
+                continue;
+            }
+            let rob_id = snap.u64(&format!("exu.fu[{}].in_rob_id", fu));
+            if let Some(&i) = rob_id.and_then(|r| self.live.get(&r)) {
+                self.insts[i].reach(Stage::Exec, s);
+            }
+        }
+
+        for port in PORTS {
+            if !fired(
+                snap,
+                &format!("issue[{}].valid", port),
+                &format!("issue[{}].ready", port),
+            ) {
+                continue;
+            }
+            let rob_id = snap.u64(&format!("issue[{}].rob_id", port));
+            if let Some(&i) = rob_id.and_then(|r| self.live.get(&r)) {
+                let inst = &mut self.insts[i];
+                inst.reach(Stage::Issue, s);
+                inst.port.get_or_insert(port);
+            }
+        }
+
+        // Dispatch: ROB enqueue, issue queue enqueue and LSQ allocation fire together.
+        if fired(snap, "rob.enq.valid", "rob.enq.ready") {
+            let p_rd = snap.u64("rob.enq.p_rd");
+            let i = self.front_at(Stage::Dispatch, s, |inst| {
+                inst.p_rd.is_none() || p_rd.is_none() || inst.p_rd == p_rd
+            });
+            self.front.retain(|&f| f != i);
+            let inst = &mut self.insts[i];
+            inst.reach(Stage::Dispatch, s);
+            inst.rob_id = snap.u64("rob.enq.rob_id").or(inst.rob_id);
+            inst.rd_index = snap.u64("rob.enq.rd_index").or(inst.rd_index);
+            inst.p_rd = p_rd.or(inst.p_rd);
+            inst.old_p_rd = snap.u64("rob.enq.old_p_rd").or(inst.old_p_rd);
+            if let Some(rob_id) = inst.rob_id {
+                self.live.insert(rob_id, i);
+            }
+        }
+        if fired(snap, "iq.in.valid", "iq.in.ready")
+            && let Some(rob_id) = snap.u64("iq.in.rob_id")
+        {
+            let i = match self.live.get(&rob_id) {
+                Some(&i) => i,
+                // No ROB enqueue in the trace: the issue queue is the entry point.
+                None => {
+                    let i = self.front_at(Stage::Dispatch, s, |_| true);
+                    self.front.retain(|&f| f != i);
+                    self.insts[i].rob_id = Some(rob_id);
+                    self.live.insert(rob_id, i);
+                    i
+                }
+            };
+            let inst = &mut self.insts[i];
+            inst.reach(Stage::IqEnq, s);
+            inst.pc = inst.pc.or(snap.u64("iq.in.pc"));
+            inst.fu_type = inst.fu_type.or(snap.u64("iq.in.fu_type"));
+            // lsq_id is only meaningful for LSU ops.
+            if inst.fu_type == Some(FU_LSU) {
+                inst.lsq_id = inst.lsq_id.or(snap.u64("iq.in.lsq_id"));
+            }
+        }
+        if fired(snap, "lsq.alloc.valid", "lsq.alloc.ready")
+            && let Some(&i) = snap.u64("lsq.alloc.rob_id").and_then(|r| self.live.get(&r))
+        {
+            self.insts[i].lsq_id = snap.u64("lsq.alloc.lsq_id");
+        }
+
+        if fired(snap, "idu.out.valid", "idu.out.ready") {
+            let pc = snap.u64("idu.out.pc");
+            let i = self.front_at(Stage::Rename, s, |inst| {
+                inst.pc.is_none() || pc.is_none() || inst.pc == pc
+            });
+            let inst = &mut self.insts[i];
+            inst.reach(Stage::Rename, s);
+            inst.pc = inst.pc.or(pc);
+            inst.rd_index = snap.u64("idu.out.rd_index");
+            inst.p_rd = snap.u64("idu.out.p_rd");
+            inst.old_p_rd = snap.u64("idu.out.old_p_rd");
+            inst.fu_type = snap.u64("idu.out.fu_type");
+            inst.p_rs1 = snap.u64("idu.out.p_rs1");
+            inst.p_rs2 = snap.u64("idu.out.p_rs2");
+        }
+
+        if fired(snap, "idu.in.valid", "idu.in.ready") {
+            let pc = snap.u64("idu.in.pc");
+            let i = self.front_at(Stage::Decode, s, |inst| {
+                inst.pc.is_none() || pc.is_none() || inst.pc == pc
+            });
+            let inst = &mut self.insts[i];
+            inst.reach(Stage::Decode, s);
+            inst.pc = inst.pc.or(pc);
+            inst.inst = inst.inst.or(snap.u64("idu.in.inst"));
+        }
+
+        if fired(snap, "ifu.out.valid", "ifu.out.ready") {
+            let i = self.push();
+            self.front.push_back(i);
+            let inst = &mut self.insts[i];
+            inst.reach(Stage::Fetch, s);
+            inst.pc = snap.u64("ifu.out.pc");
+            inst.inst = snap.u64("ifu.out.inst");
+        }
+
+        // The flush (one cycle after the flushing instruction commits) empties the ROB and
+        // every pipeline register, including what handshook on this edge.
+        if snap.is_high("rob.do_flush") {
+            for i in self
+                .front
+                .drain(..)
+                .chain(self.live.drain().map(|(_, i)| i))
+            {
+                self.insts[i].flushed = Some(*s);
+            }
+            self.epoch += 1;
+        }
+    }
+}
+
+/// Every dynamic instruction in the trace, in fetch order.
+pub struct InstructionTracker {
+    insts: Vec<Instruction>,
+}
+
+impl InstructionTracker {
+    /// Schema keys the tracker reads; passes built on it list these in
+    /// [`signals`](super::AnalysisPass::signals).
+    pub const SIGNALS: &'static [&'static str] = &[
+        "ifu.out.valid",
+        "ifu.out.ready",
+        "ifu.out.pc",
+        "ifu.out.inst",
+        "idu.in.valid",
+        "idu.in.ready",
+        "idu.in.pc",
+        "idu.in.inst",
+        "idu.out.valid",
+        "idu.out.ready",
+        "idu.out.pc",
+        "idu.out.rd_index",
+        "idu.out.p_rd",
+        "idu.out.old_p_rd",
+        "idu.out.fu_type",
+        "idu.out.p_rs1",
+        "idu.out.p_rs2",
+        "rob.enq.valid",
+        "rob.enq.ready",
+        "rob.enq.rob_id",
+        "rob.enq.rd_index",
+        "rob.enq.p_rd",
+        "rob.enq.old_p_rd",
+        "iq.in.valid",
+        "iq.in.ready",
+        "iq.in.rob_id",
+        "iq.in.pc",
+        "iq.in.fu_type",
+        "iq.in.lsq_id",
+        "lsq.alloc.valid",
+        "lsq.alloc.ready",
+        "lsq.alloc.rob_id",
+        "lsq.alloc.lsq_id",
+        "issue[port].valid",
+        "issue[port].ready",
+        "issue[port].rob_id",
+        "exu.fu[fu].in_valid",
+        "exu.fu[fu].in_rob_id",
+        "rob.mem_issue",
+        "rob.mem_issue_rob_id.valid",
+        "rob.mem_issue_rob_id",
+        "rob.access[i].valid",
+        "rob.access[i].rob_id",
+        "rob.access[i].is_done",
+        "rob.mem_access.valid",
+        "rob.mem_access.rob_id",
+        "rob.mem_access.is_done",
+        "rob.commit.valid",
+        "rob.commit.rob_id",
+        "rob.commit.next_pc",
+        "rob.head_ptr",
+        "rob.do_flush",
+    ];
+
+    /// Walk every sample of `tb` once; `sigs` must include [`SIGNALS`](Self::SIGNALS).
+    pub fn build(
+        wf: &Waveform,
+        tb: &Timebase,
+        sigs: &[(String, SignalRef)],
+        layout: CoreLayout,
+    ) -> Self {
+        let mut b = Builder {
+            rob_slots: layout.rob_slots as u64,
+            ..Default::default()
+        };
+        for s in tb.samples() {
+            b.step(s, &snapshot_at(wf, sigs, s.idx));
+        }
+        Self { insts: b.insts }
+    }
+
+    /// All instructions, in fetch order.
+    pub fn records(&self) -> &[Instruction] {
+        &self.insts
+    }
+
+    /// Instructions that held `rob_id`, oldest first.
+    pub fn by_rob_id(&self, rob_id: u64) -> impl Iterator<Item = &Instruction> {
+        self.insts.iter().filter(move |i| i.rob_id == Some(rob_id))
+    }
+
+    /// Instructions in the pipeline at time `t` (seen at or before it, not yet ended).
+    pub fn in_flight_at(&self, t: Time) -> impl Iterator<Item = &Instruction> {
+        self.insts.iter().filter(move |i| {
+            i.first().is_some_and(|s| s.time <= t)
+                && (i.outcome() == Outcome::InFlight || i.last().is_some_and(|s| s.time >= t))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Value;
+
+    /// Feed one snapshot per cycle (cycle `c` at time `10 * c`) to a fresh builder.
+    fn run(rob_slots: u64, cycles: &[&[(&str, u64)]]) -> Vec<Instruction> {
+        let mut b = Builder {
+            rob_slots,
+            ..Default::default()
+        };
+        for (c, sigs) in cycles.iter().enumerate() {
+            let s = Sample {
+                idx: c as u32,
+                time: 10 * c as Time,
+                cycle: Some(c as u64),
+            };
+            let snap: Snapshot = sigs
+                .iter()
+                .map(|&(n, v)| (n, Value::from_u64(v, 32)))
+                .collect();
+            b.step(&s, &snap);
+        }
+        b.insts
+    }
+
+    fn cycle(i: &Instruction, stage: Stage) -> Option<u64> {
+        i.at(stage).and_then(|s| s.cycle)
+    }
+
+    /// ROB enqueue of `rob_id` with no frontend in the trace.
+    fn enq(rob_id: u64) -> [(&'static str, u64); 2] {
+        [("rob.enq.valid", 1), ("rob.enq.rob_id", rob_id)]
+    }
+
+    fn commit(rob_id: u64) -> [(&'static str, u64); 2] {
+        [("rob.commit.valid", 1), ("rob.commit.rob_id", rob_id)]
+    }
+
+    #[test]
+    fn full_lifecycle() {
+        let insts = run(
+            16,
+            &[
+                &[
+                    ("ifu.out.valid", 1),
+                    ("ifu.out.ready", 1),
+                    ("ifu.out.pc", 0x100),
+                ],
+                &[
+                    ("idu.in.valid", 1),
+                    ("idu.in.ready", 1),
+                    ("idu.in.pc", 0x100),
+                ],
+                &[
+                    ("idu.out.valid", 1),
+                    ("idu.out.pc", 0x100),
+                    ("idu.out.p_rd", 33),
+                ],
+                &[
+                    ("rob.enq.valid", 1),
+                    ("rob.enq.rob_id", 5),
+                    ("rob.enq.p_rd", 33),
+                ],
+                &[
+                    ("issue[alu].valid", 1),
+                    ("issue[alu].ready", 0),
+                    ("issue[alu].rob_id", 5),
+                ],
+                &[
+                    ("issue[alu].valid", 1),
+                    ("issue[alu].ready", 1),
+                    ("issue[alu].rob_id", 5),
+                ],
+                &[("exu.fu[alu].in_valid", 1), ("exu.fu[alu].in_rob_id", 5)],
+                &[
+                    ("rob.access[0].valid", 1),
+                    ("rob.access[0].is_done", 1),
+                    ("rob.access[0].rob_id", 5),
+                ],
+                &[
+                    ("rob.commit.valid", 1),
+                    ("rob.commit.rob_id", 5),
+                    ("rob.commit.next_pc", 0x104),
+                ],
+            ],
+        );
+        assert_eq!(insts.len(), 1);
+        let i = &insts[0];
+        let stages: Vec<(Stage, Option<u64>)> = i.stages().map(|(st, s)| (st, s.cycle)).collect();
+        assert_eq!(
+            stages,
+            [
+                (Stage::Fetch, Some(0)),
+                (Stage::Decode, Some(1)),
+                (Stage::Rename, Some(2)),
+                (Stage::Dispatch, Some(3)),
+                // The stalled cycle 4 is not a handshake.
+                (Stage::Issue, Some(5)),
+                (Stage::Exec, Some(6)),
+                (Stage::Writeback, Some(7)),
+                (Stage::Commit, Some(8)),
+            ]
+        );
+        assert_eq!((i.pc, i.p_rd, i.rob_id), (Some(0x100), Some(33), Some(5)));
+        assert_eq!((i.port, i.next_pc), (Some("alu"), Some(0x104)));
+        assert_eq!(i.outcome(), Outcome::Committed);
+    }
+
+    #[test]
+    fn rob_id_reused_after_wraparound() {
+        let insts = run(
+            2,
+            &[
+                &enq(0),
+                &enq(1),
+                &commit(0),
+                &commit(1),
+                &enq(0),
+                &commit(0),
+            ],
+        );
+        assert_eq!(insts.len(), 3);
+        let commits: Vec<_> = insts
+            .iter()
+            .map(|i| (i.rob_id, cycle(i, Stage::Dispatch), cycle(i, Stage::Commit)))
+            .collect();
+        assert_eq!(
+            commits,
+            [
+                (Some(0), Some(0), Some(2)),
+                (Some(1), Some(1), Some(3)),
+                (Some(0), Some(4), Some(5)),
+            ]
+        );
+        let tracker = InstructionTracker { insts };
+        assert_eq!(
+            tracker.by_rob_id(0).map(|i| i.seq).collect::<Vec<_>>(),
+            [0, 2]
+        );
+    }
+
+    #[test]
+    fn flush_squashes_and_bumps_epoch() {
+        let fetch = |pc| {
+            [
+                ("ifu.out.valid", 1),
+                ("ifu.out.ready", 1),
+                ("ifu.out.pc", pc),
+            ]
+        };
+        let insts = run(
+            16,
+            &[
+                &enq(0),
+                &enq(1),
+                &commit(0),
+                &fetch(0x200),
+                &[("rob.do_flush", 1)],
+                &fetch(0x300),
+                &enq(1),
+            ],
+        );
+        let summary: Vec<_> = insts
+            .iter()
+            .map(|i| (i.epoch, i.outcome(), i.flushed.and_then(|s| s.cycle)))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (0, Outcome::Committed, None),
+                // Dispatched and fetched but not committed: squashed by the flush.
+                (0, Outcome::Flushed, Some(4)),
+                (0, Outcome::Flushed, Some(4)),
+                // Fetched after the flush: new epoch; the reused rob_id 1 goes to it.
+                (1, Outcome::InFlight, None),
+            ]
+        );
+        assert_eq!(insts[3].pc, Some(0x300));
+        assert_eq!(cycle(&insts[3], Stage::Dispatch), Some(6));
+    }
+
+    #[test]
+    fn frontend_drops_skipped_instructions() {
+        let fetch = |pc| {
+            [
+                ("ifu.out.valid", 1),
+                ("ifu.out.ready", 1),
+                ("ifu.out.pc", pc),
+            ]
+        };
+        let insts = run(
+            16,
+            &[
+                &fetch(0x100),
+                &fetch(0x104),
+                &[
+                    ("idu.in.valid", 1),
+                    ("idu.in.ready", 1),
+                    ("idu.in.pc", 0x104),
+                ],
+            ],
+        );
+        assert_eq!(insts.len(), 2);
+        assert_eq!(insts[0].outcome(), Outcome::Flushed);
+        assert_eq!(insts[0].flushed.and_then(|s| s.cycle), Some(2));
+        assert_eq!(cycle(&insts[1], Stage::Decode), Some(2));
+        assert_eq!(insts[1].outcome(), Outcome::InFlight);
+    }
+
+    #[test]
+    fn commit_falls_back_to_head_ptr() {
+        // No rob.commit.rob_id: the head pointer, wrap bit and all, modulo the ROB size.
+        let insts = run(
+            4,
+            &[
+                &enq(1),
+                &enq(2),
+                &[("rob.commit.valid", 1), ("rob.head_ptr", 5)],
+                &[("rob.commit.valid", 1), ("rob.head_ptr", 2)],
+            ],
+        );
+        assert_eq!(cycle(&insts[0], Stage::Commit), Some(2));
+        assert_eq!(cycle(&insts[1], Stage::Commit), Some(3));
+    }
+}
@@ -1,179 +1,213 @@
-use wellen::simple::Waveform;
-use wellen::{SignalRef, Time};
+use std::sync::LazyLock;
 
-/// Find cycles where prf_write or commit has p_rd matching the given PR (binary, e.g. "100101").
-pub fn who_produces_pr(
-    wf: &mut Waveform,
-    time_table: &[Time],
-    pr_bin: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let hierarchy = wf.hierarchy();
-    let mut sigs: Vec<(String, SignalRef)> = Vec::new();
-    for var in hierarchy.iter_vars() {
-        let name = var.full_name(hierarchy);
-        if (name.contains("iq.io_prf_write_")
-            && (name.contains("_valid") || name.contains("_bits_addr")))
-            || (name.contains("commit.io_rob_commit")
-                && (name.contains("valid") || name.contains("p_rd")))
-        {
-            sigs.push((name, var.signal_ref()));
-        }
+use clap::{ArgMatches, Args, Command, FromArgMatches};
+
+use super::pass::{AnalysisPass, PassContext};
+use super::words::{self, InstWords};
+use crate::core::{Event, Field, NumArg, Value, snapshot_at};
+
+#[derive(Args)]
+struct PrArgs {
+    /// Physical register (e.g. 37, PR37, 0b100101)
+    p_rd: NumArg,
+}
+
+/// `who-produces`: prf_write / commit traffic for one PR over the last 2000 time units.
+pub struct WhoProduces;
+
+impl AnalysisPass for WhoProduces {
+    fn name(&self) -> &'static str {
+        "who-produces"
+    }
+
+    fn command(&self) -> Command {
+        PrArgs::augment_args(
+            Command::new(self.name())
+                .about("Find who produces a given PR: scan for prf_write/commit with p_rd=PR"),
+        )
     }
-    let to_load: Vec<SignalRef> = sigs.iter().map(|(_, sr)| *sr).collect();
-    wf.load_signals(&to_load);
 
-    let get_val = |name_contains: &str, vals: &[(String, String)]| -> Option<String> {
-        vals.iter()
-            .find(|(n, _)| n.contains(name_contains))
-            .map(|(_, v)| v.clone())
-    };
+    fn output(&self) -> &'static str {
+        "PRF_WRITE events (port, p_rd) and COMMIT events (rd_index, p_rd) that target the PR"
+    }
 
-    let pr_trim = pr_bin.trim();
-    println!("Scanning for PR {} (produces/writes):\n", pr_trim);
+    fn signals(&self) -> &'static [&'static str] {
+        &[
+            "iq.prf_write[i].valid",
+            "iq.prf_write[i].addr",
+            "commit.rob_commit.valid",
+            "commit.rob_commit.p_rd",
+            "commit.rob_commit.rd_index",
+        ]
+    }
 
-    let last_t = time_table.last().copied().unwrap_or(0);
-    let start = last_t.saturating_sub(2000);
+    fn run(
+        &self,
+        ctx: &mut PassContext<'_>,
+        args: &ArgMatches,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let a = PrArgs::from_arg_matches(args)?;
+        who_produces_pr(ctx, a.p_rd.get())
+    }
+}
 
-    for (i, &t) in time_table.iter().enumerate() {
-        if t < start {
-            continue;
-        }
-        let idx = i as u32;
-        let mut vals: Vec<(String, String)> = Vec::new();
-        for (name, sig_ref) in &sigs {
-            if let Some(sig) = wf.get_signal(*sig_ref) {
-                if let Some(offset) = sig.get_offset(idx) {
-                    let val = sig.get_value_at(&offset, 0).to_string();
-                    vals.push((name.clone(), val));
-                }
-            }
-        }
+static FIND_PRD_SIGNALS: LazyLock<Vec<&'static str>> = LazyLock::new(|| {
+    [
+        &[
+            "iq.valid[i]",
+            "iq.entry[i].p_rd",
+            "iq.entry[i].rs1_ready",
+            "iq.entry[i].rs2_ready",
+            "iq.entry[i].pc",
+            "iq.entry[i].fu_type",
+            "rob.slot[i].p_rd",
+            "rob.slot[i].is_done",
+            "rob.slot[i].mem_type",
+            "rob.slot[i].rd_index",
+            "rob.head_ptr",
+            "rob.tail_ptr",
+            "issue[port].valid",
+            "issue[port].p_rd",
+            "isu.out.valid",
+            "isu.out.p_rd",
+            "exu.pipe[i].p_rd",
+            "lsq.slot[i].valid",
+            "lsq.slot[i].p_rd",
+        ],
+        words::SIGNALS,
+    ]
+    .concat()
+});
+
+/// `find-p-rd`: where a p_rd sits in IQ/ROB/EXU/MemUnit over the last 200 time units.
+pub struct FindPrd;
+
+impl AnalysisPass for FindPrd {
+    fn name(&self) -> &'static str {
+        "find-p-rd"
+    }
+
+    fn command(&self) -> Command {
+        PrArgs::augment_args(
+            Command::new(self.name()).about("Find instruction with p_rd=PR in IQ/ROB/EXU/MemUnit"),
+        )
+    }
+
+    fn output(&self) -> &'static str {
+        "one event per sample and pipeline location holding the p_rd: IQ_ENTRY (with pc and disasm), ROB_SLOT, ISSUE, ISU_OUT, EXU_PIPE, LSQ_SLOT"
+    }
+
+    fn signals(&self) -> &'static [&'static str] {
+        &FIND_PRD_SIGNALS
+    }
+
+    fn run(
+        &self,
+        ctx: &mut PassContext<'_>,
+        args: &ArgMatches,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let a = PrArgs::from_arg_matches(args)?;
+        find_p_rd_in_pipeline(ctx, a.p_rd.get())
+    }
+}
+
+/// Find cycles where prf_write or commit has p_rd matching the given PR (binary, e.g. "100101").
+fn who_produces_pr(ctx: &mut PassContext<'_>, pr: u64) -> Result<(), Box<dyn std::error::Error>> {
+    let (wf, tb, sigs, out) = (&*ctx.wf, ctx.tb, ctx.sigs, &mut *ctx.out);
+    let layout = ctx.layout;
 
-        for port in 0..6 {
-            let v = get_val(&format!("iq.io_prf_write_{}_valid", port), &vals);
-            let a = get_val(&format!("iq.io_prf_write_{}_bits_addr", port), &vals);
-            if v.as_deref() == Some("1") && a.as_deref().map(|s| s.trim()) == Some(pr_trim) {
-                println!("t={} prf_write port {} -> PR {}", t, port, pr_trim);
+    out.note(&format!("Scanning for PR {} (produces/writes):\n", pr))?;
+
+    let last_t = tb.last_time();
+    let start = last_t.saturating_sub(2000);
+
+    for s in tb.range(start, last_t) {
+        let idx = s.idx;
+        let snap = snapshot_at(wf, sigs, idx);
+
+        for port in 0..layout.prf_write_ports {
+            let v = snap.is_high(&format!("iq.prf_write[{}].valid", port));
+            let a = snap.get(&format!("iq.prf_write[{}].addr", port));
+            if v && a.is_some_and(|v| *v == pr) {
+                out.event(
+                    &Event::on(s, "PRF_WRITE")
+                        .with("port", port)
+                        .with("p_rd", pr),
+                )?;
             }
         }
 
-        let cv = get_val("commit.io_rob_commit_valid", &vals).map_or(false, |v| v == "1");
-        let cp = get_val("commit.io_rob_commit_bits_p_rd", &vals)
-            .as_deref()
-            .map(|s| s.trim().to_string());
-        if cv && cp.as_deref() == Some(pr_trim) {
-            let rd = get_val("commit.io_rob_commit_bits_rd_index", &vals);
-            println!(
-                "t={} COMMIT rd_index={:?} p_rd={} -> PR {}",
-                t, rd, pr_trim, pr_trim
-            );
+        let cv = snap.is_high("commit.rob_commit.valid");
+        let cp = snap.get("commit.rob_commit.p_rd");
+        if cv && cp.is_some_and(|v| *v == pr) {
+            let rd = snap.get("commit.rob_commit.rd_index");
+            out.event(&Event::on(s, "COMMIT").with("rd_index", rd).with("p_rd", pr))?;
         }
     }
     Ok(())
 }
 
 /// Find instruction with p_rd=PR in IQ, ROB, EXU pipeline, MemUnit, issue ports.
-pub fn find_p_rd_in_pipeline(
-    wf: &mut Waveform,
-    time_table: &[Time],
-    pr_bin: &str,
+fn find_p_rd_in_pipeline(
+    ctx: &mut PassContext<'_>,
+    pr: u64,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let hierarchy = wf.hierarchy();
-    let mut sigs: Vec<(String, SignalRef)> = Vec::new();
-    for var in hierarchy.iter_vars() {
-        let name = var.full_name(hierarchy);
-        if (name.contains("iq.entries_")
-            && (name.contains("p_rd")
-                || name.contains("valids_")
-                || name.contains("rs1_ready")
-                || name.contains("rs2_ready")
-                || name.contains("fu_type")
-                || name.contains("pc")))
-            || (name.contains("rob.slots_p_rd_")
-                || name.contains("rob.slots_is_done_")
-                || name.contains("rob.slots_mem_type_")
-                || name.contains("rob.slots_rd_index_")
-                || name.contains("rob.head_ptr")
-                || name.contains("rob.tail_ptr"))
-            || (name.contains("exu.pipeOut_bits") && name.contains("p_rd"))
-            || (name.contains("exu.io_issuePorts_")
-                && (name.contains("valid") || name.contains("p_rd")))
-            || (name.contains("memUnit.ls_slots_")
-                && (name.contains("p_rd") || name.contains("valid")))
-            || (name.contains("isu.io_out_bits_p_rd") || name.contains("isu.io_out_valid"))
-            || (name.contains("iq.io_issuePorts_")
-                && (name.contains("valid") || name.contains("p_rd")))
-        {
-            sigs.push((name, var.signal_ref()));
-        }
-    }
-    let to_load: Vec<SignalRef> = sigs.iter().map(|(_, sr)| *sr).collect();
-    wf.load_signals(&to_load);
+    let (wf, tb, sigs, out) = (&*ctx.wf, ctx.tb, ctx.sigs, &mut *ctx.out);
+    let layout = ctx.layout;
 
-    let get_val = |name_contains: &str, vals: &[(String, String)]| -> Option<String> {
-        vals.iter()
-            .find(|(n, _)| n.contains(name_contains))
-            .map(|(_, v)| v.clone())
-    };
-
-    let pr_trim = pr_bin.trim();
-    let last_t = time_table.last().copied().unwrap_or(0);
+    let last_t = tb.last_time();
     let start = last_t.saturating_sub(200);
 
-    println!(
-        "Finding instruction with p_rd={} (PR{}) in pipeline, t={}..{}\n",
-        pr_trim,
-        u32::from_str_radix(pr_trim, 2).unwrap_or(99),
-        start,
-        last_t
-    );
-
-    for (i, &t) in time_table.iter().enumerate() {
-        if t < start {
-            continue;
-        }
-        let idx = i as u32;
-        let mut vals: Vec<(String, String)> = Vec::new();
-        for (name, sig_ref) in &sigs {
-            if let Some(sig) = wf.get_signal(*sig_ref) {
-                if let Some(offset) = sig.get_offset(idx) {
-                    let val = sig.get_value_at(&offset, 0).to_string();
-                    vals.push((name.clone(), val));
-                }
-            }
-        }
-
-        for entry in 0..8 {
-            let v = get_val(&format!("iq.entries_{}_p_rd", entry), &vals);
-            if v.as_deref().map(|s| s.trim()) == Some(pr_trim) {
-                let valid =
-                    get_val(&format!("iq.valids_{}", entry), &vals).map_or(false, |v| v == "1");
-                let r1 = get_val(&format!("iq.entries_{}_rs1_ready", entry), &vals)
-                    .map_or(false, |v| v == "1");
-                let r2 = get_val(&format!("iq.entries_{}_rs2_ready", entry), &vals)
-                    .map_or(false, |v| v == "1");
-                let pc = get_val(&format!("iq.entries_{}_pc", entry), &vals);
-                let ft = get_val(&format!("iq.entries_{}_fu_type", entry), &vals);
-                println!(
-                    "t={} IQ entry{}: p_rd={} valid={} r1={} r2={} pc={:?} fu={:?}",
-                    t, entry, pr_trim, valid, r1, r2, pc, ft
-                );
+    out.note(&format!(
+        "Finding instruction with p_rd=PR{} in pipeline, t={}..{}\n",
+        pr, start, last_t
+    ))?;
+    let words = InstWords::collect(wf, tb, sigs);
+
+    for s in tb.range(start, last_t) {
+        let idx = s.idx;
+        let snap = snapshot_at(wf, sigs, idx);
+
+        for entry in 0..layout.iq_entries {
+            let v = snap.get(&format!("iq.entry[{}].p_rd", entry));
+            if v.is_some_and(|v| *v == pr) {
+                let valid = snap.is_high(&format!("iq.valid[{}]", entry));
+                let r1 = snap.is_high(&format!("iq.entry[{}].rs1_ready", entry));
+                let r2 = snap.is_high(&format!("iq.entry[{}].rs2_ready", entry));
+                let pc = snap.get(&format!("iq.entry[{}].pc", entry));
+                let ft = snap.get(&format!("iq.entry[{}].fu_type", entry));
+                out.event(
+                    &Event::on(s, "IQ_ENTRY")
+                        .with("entry", entry)
+                        .with("p_rd", pr)
+                        .with("valid", valid)
+                        .with("r1", r1)
+                        .with("r2", r2)
+                        .with("pc", Field::hex(pc))
+                        .with("disasm", words.disasm(&ctx.isa, pc.and_then(Value::as_u64)))
+                        .with("fu", ft),
+                )?;
             }
         }
 
-        for slot in 0..16 {
-            let v = get_val(&format!("rob.slots_p_rd_{}", slot), &vals);
-            if v.as_deref().map(|s| s.trim()) == Some(pr_trim) {
-                let done = get_val(&format!("rob.slots_is_done_{}", slot), &vals)
-                    .map_or(false, |v| v == "1");
-                let head = get_val("rob.head_ptr", &vals);
-                let tail = get_val("rob.tail_ptr", &vals);
-                let mem_type = get_val(&format!("rob.slots_mem_type_{}", slot), &vals);
-                let rd_idx = get_val(&format!("rob.slots_rd_index_{}", slot), &vals);
-                println!(
-                    "t={} ROB slot{}: p_rd={} is_done={} mem_type={:?} rd_index={:?} head={:?} tail={:?}",
-                    t, slot, pr_trim, done, mem_type, rd_idx, head, tail
-                );
+        for slot in 0..layout.rob_slots {
+            let v = snap.get(&format!("rob.slot[{}].p_rd", slot));
+            if v.is_some_and(|v| *v == pr) {
+                let done = snap.is_high(&format!("rob.slot[{}].is_done", slot));
+                let head = snap.get("rob.head_ptr");
+                let tail = snap.get("rob.tail_ptr");
+                let mem_type = snap.get(&format!("rob.slot[{}].mem_type", slot));
+                let rd_idx = snap.get(&format!("rob.slot[{}].rd_index", slot));
+                out.event(
+                    &Event::on(s, "ROB_SLOT")
+                        .with("slot", slot)
+                        .with("p_rd", pr)
+                        .with("is_done", done)
+                        .with("mem_type", mem_type)
+                        .with("rd_index", rd_idx)
+                        .with("head", head)
+                        .with("tail", tail),
+                )?;
             }
         }
 
@@ -185,40 +219,31 @@ pub fn find_p_rd_in_pipeline(
             ("div", "DIV"),
             ("sysu", "SYSU"),
         ] {
-            let v = get_val(&format!("iq.io_issuePorts_{}_valid", port), &vals)
-                .map_or(false, |x| x == "1");
-            let p = get_val(&format!("iq.io_issuePorts_{}_bits_p_rd", port), &vals);
-            if v && p.as_deref().map(|s| s.trim()) == Some(pr_trim) {
-                println!("t={} ISSUE {}: p_rd={} (being issued)", t, name, pr_trim);
+            let v = snap.is_high(&format!("issue[{}].valid", port));
+            let p = snap.get(&format!("issue[{}].p_rd", port));
+            if v && p.is_some_and(|v| *v == pr) {
+                out.event(&Event::on(s, "ISSUE").with("port", name).with("p_rd", pr))?;
             }
         }
 
-        let isu_valid = get_val("isu.io_out_valid", &vals).map_or(false, |v| v == "1");
-        let isu_p = get_val("isu.io_out_bits_p_rd", &vals)
-            .or_else(|| get_val("isu.io_out_bits_r_p_rd", &vals));
-        if isu_valid && isu_p.as_deref().map(|s| s.trim()) == Some(pr_trim) {
-            println!("t={} ISU out: p_rd={} (on way to IQ)", t, pr_trim);
+        let isu_valid = snap.is_high("isu.out.valid");
+        let isu_p = snap.get("isu.out.p_rd");
+        if isu_valid && isu_p.is_some_and(|v| *v == pr) {
+            out.event(&Event::on(s, "ISU_OUT").with("p_rd", pr))?;
         }
 
-        for pipe in ["", "_1", "_3", "_4", "_5"] {
-            let p = get_val(&format!("exu.pipeOut_bits_r{}_p_rd", pipe), &vals);
-            if p.as_deref().map(|s| s.trim()) == Some(pr_trim) {
-                println!(
-                    "t={} EXU pipeOut{}: p_rd={} (in EX pipeline)",
-                    t, pipe, pr_trim
-                );
+        for pipe in 0..layout.prf_write_ports {
+            let p = snap.get(&format!("exu.pipe[{}].p_rd", pipe));
+            if p.is_some_and(|v| *v == pr) {
+                out.event(&Event::on(s, "EXU_PIPE").with("pipe", pipe).with("p_rd", pr))?;
             }
         }
 
-        for ls in 0..8 {
-            let v = get_val(&format!("memUnit.ls_slots_{}_valid", ls), &vals)
-                .map_or(false, |x| x == "1");
-            let p = get_val(&format!("memUnit.ls_slots_{}_p_rd", ls), &vals);
-            if v && p.as_deref().map(|s| s.trim()) == Some(pr_trim) {
-                println!(
-                    "t={} MemUnit ls_slot{}: p_rd={} (load/store in flight)",
-                    t, ls, pr_trim
-                );
+        for ls in 0..layout.ls_slots {
+            let v = snap.is_high(&format!("lsq.slot[{}].valid", ls));
+            let p = snap.get(&format!("lsq.slot[{}].p_rd", ls));
+            if v && p.is_some_and(|v| *v == pr) {
+                out.event(&Event::on(s, "LSQ_SLOT").with("slot", ls).with("p_rd", pr))?;
             }
         }
     }
@@ -0,0 +1,57 @@
+//! Instruction words by PC, for passes that only see a PC, and the `disasm` field shown
+//! next to PCs and instruction words.
+
+use std::collections::HashMap;
+
+use wellen::{SignalRef, simple::Waveform};
+
+use crate::core::{Field, Isa, Timebase, snapshot_at};
+
+/// Fetch and decode ports, where each PC is seen with its instruction word.
+pub const SIGNALS: &[&str] = &[
+    "ifu.out.valid",
+    "ifu.out.pc",
+    "ifu.out.inst",
+    "idu.in.valid",
+    "idu.in.pc",
+    "idu.in.inst",
+];
+
+/// Instruction word of every PC fetched or decoded in the trace.
+pub struct InstWords(HashMap<u64, u64>);
+
+impl InstWords {
+    pub fn collect(wf: &Waveform, tb: &Timebase, sigs: &[(String, SignalRef)]) -> Self {
+        let mut words = HashMap::new();
+        for s in tb.samples() {
+            let snap = snapshot_at(wf, sigs, s.idx);
+            for port in ["ifu.out", "idu.in"] {
+                if !snap.is_high(&format!("{}.valid", port)) {
+                    continue;
+                }
+                let pc = snap.u64(&format!("{}.pc", port));
+                let inst = snap.u64(&format!("{}.inst", port));
+                if let (Some(pc), Some(inst)) = (pc, inst) {
+                    words.insert(pc, inst);
+                }
+            }
+        }
+        InstWords(words)
+    }
+
+    pub fn get(&self, pc: u64) -> Option<u64> {
+        self.0.get(&pc).copied()
+    }
+
+    /// `disasm` field for `pc`, from the word seen at that PC.
+    pub fn disasm(&self, isa: &Isa, pc: Option<u64>) -> Field {
+        disasm(isa, pc, pc.and_then(|pc| self.get(pc)))
+    }
+}
+
+/// Disassembly of `inst` at `pc` (branch targets need the PC), or null without a word.
+pub fn disasm(isa: &Isa, pc: Option<u64>, inst: Option<u64>) -> Field {
+    inst.map_or(Field::Null, |w| {
+        Field::Text(isa.disasm(pc.unwrap_or(0), w as u32))
+    })
+}
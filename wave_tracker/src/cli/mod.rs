@@ -1,5 +1,8 @@
 mod args;
+mod repl;
 mod run;
+mod session;
 
 pub use args::Args;
-pub use run::run;
+pub use run::{command, run};
+pub use session::Session;
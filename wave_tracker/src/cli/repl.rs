@@ -0,0 +1,420 @@
+//! Interactive session (`wave_tracker repl`): the waveform is parsed once, and signals
+//! stay loaded between commands, so follow-up queries only read what they add.
+//!
+//! Every pass subcommand works as in a one-shot run; the REPL adds a cursor (one cycle
+//! sample) that `at`, `next`, `prev`, `back`, `step` and `find` move and `show` reads.
+
+use std::path::PathBuf;
+
+use clap::{Command, FromArgMatches, Subcommand};
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::FileHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use wellen::SignalRef;
+
+use crate::analysis::AnalysisPass;
+use crate::core::{
+    Event, Expr, Format, NumArg, Sample, SignalMap, Sink, TimeArg, in_scope, open_sink,
+    snapshot_at, value_at,
+};
+
+use super::session::Session;
+
+/// Subcommand name.
+pub const NAME: &str = "repl";
+
+/// `repl` entry in the top-level command.
+pub fn command_about() -> Command {
+    Command::new(NAME).about(
+        "Interactive session: parse the waveform once, then run subcommands and cursor commands (next, back, find, show) with history and tab completion",
+    )
+}
+
+/// Commands that only exist in the REPL.
+#[derive(Subcommand)]
+enum Builtin {
+    /// Values at the cursor of logical signals under PATTERN (rob, iq.entry[3]), else of signals whose name contains it
+    Show { pattern: String },
+
+    /// Move to the next cycle where EXPR holds (as in `scan --where`); one cycle without EXPR
+    Next {
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        expr: Vec<String>,
+    },
+
+    /// Move to the previous cycle where EXPR holds; one cycle without EXPR
+    Prev {
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        expr: Vec<String>,
+    },
+
+    /// Move the cursor back N cycles
+    Back {
+        #[arg(default_value_t = 1)]
+        n: usize,
+    },
+
+    /// Move the cursor forward N cycles
+    Step {
+        #[arg(default_value_t = 1)]
+        n: usize,
+    },
+
+    /// Move to the next cycle where a signal whose name contains NAME has VALUE (e.g. p_rd=37)
+    Find { query: String },
+
+    /// Leave the REPL (or Ctrl-D)
+    #[command(visible_alias = "exit")]
+    Quit,
+}
+
+/// Tab completion: command names first, then signal names (logical and hierarchical).
+struct Names {
+    commands: Vec<String>,
+    signals: Vec<String>,
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '[' | ']' | '$')
+}
+
+impl Completer for Names {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let start = line[..pos]
+            .rfind(|c: char| !is_name_char(c))
+            .map_or(0, |i| i + 1);
+        let word = &line[start..pos];
+        let pool = if line[..start].trim().is_empty() {
+            &self.commands
+        } else {
+            &self.signals
+        };
+        let found = pool.iter().filter(|n| n.starts_with(word)).cloned();
+        Ok((start, found.collect()))
+    }
+}
+
+impl Hinter for Names {
+    type Hint = String;
+}
+
+impl Highlighter for Names {}
+
+impl Validator for Names {}
+
+impl Helper for Names {}
+
+fn history_path() -> Option<PathBuf> {
+    std::env::home_dir().map(|h| h.join(".wave_tracker_history"))
+}
+
+/// Every schema key resolved to concrete logical names. Keys that fail to resolve (e.g.
+/// ambiguous without `--scope`) are left out instead of failing the session.
+fn logical_signals(session: &Session) -> Vec<(String, SignalRef)> {
+    let (schema, scope) = (&session.schema, session.scope.as_deref());
+    let hierarchy = session.wf.hierarchy();
+    let keys: Vec<&str> = schema.signals.keys().map(String::as_str).collect();
+    match SignalMap::resolve(schema, hierarchy, &keys, scope) {
+        Ok(map) => map.into_sigs(),
+        Err(_) => keys
+            .iter()
+            .filter_map(|k| SignalMap::resolve(schema, hierarchy, &[k], scope).ok())
+            .flat_map(SignalMap::into_sigs)
+            .collect(),
+    }
+}
+
+/// `name` is `pattern` or lies under it (`rob` covers `rob.enq.valid`, `iq.entry[3]`
+/// covers `iq.entry[3].p_rd`).
+fn under(name: &str, pattern: &str) -> bool {
+    name.strip_prefix(pattern)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(['.', '[']))
+}
+
+struct Repl<'a> {
+    session: &'a mut Session,
+    passes: &'a [Box<dyn AnalysisPass>],
+    logical: Vec<(String, SignalRef)>,
+    /// Index of the current sample in the timebase.
+    cursor: usize,
+}
+
+impl Repl<'_> {
+    fn sample(&self) -> Sample {
+        self.session.tb.samples()[self.cursor]
+    }
+
+    fn prompt(&self) -> String {
+        let s = self.sample();
+        match s.cycle {
+            Some(c) => format!("c{} t={}> ", c, s.time),
+            None => format!("t={}> ", s.time),
+        }
+    }
+
+    /// Move the cursor by `delta` samples, clamped to the waveform.
+    fn step(&mut self, delta: isize, out: &mut dyn Sink) -> Result<(), Box<dyn std::error::Error>> {
+        let last = self.session.tb.samples().len() - 1;
+        self.cursor = self.cursor.saturating_add_signed(delta).min(last);
+        out.event(&Event::on(&self.sample(), "CURSOR"))?;
+        Ok(())
+    }
+
+    fn show(
+        &mut self,
+        pattern: &str,
+        out: &mut dyn Sink,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut sigs: Vec<_> = self
+            .logical
+            .iter()
+            .filter(|(n, _)| under(n, pattern))
+            .cloned()
+            .collect();
+        if sigs.is_empty() {
+            let scope = self.session.scope.as_deref();
+            let hierarchy = self.session.wf.hierarchy();
+            sigs = hierarchy
+                .iter_vars()
+                .map(|v| (v.full_name(hierarchy), v.signal_ref()))
+                .filter(|(n, _)| scope.is_none_or(|s| in_scope(n, s)) && n.contains(pattern))
+                .collect();
+        }
+        if sigs.is_empty() {
+            return Err(format!("No signal matches `{}`", pattern).into());
+        }
+        let to_load: Vec<SignalRef> = sigs.iter().map(|(_, s)| *s).collect();
+        self.session.wf.load_signals(&to_load);
+        let s = self.sample();
+        for (name, val) in snapshot_at(&self.session.wf, &sigs, s.idx).iter() {
+            out.event(&Event::on(&s, "VALUE").with("name", name).with("value", val))?;
+        }
+        Ok(())
+    }
+
+    /// Move to the nearest cycle after (or before) the cursor where `expr` holds.
+    fn seek(
+        &mut self,
+        expr: &[String],
+        forward: bool,
+        out: &mut dyn Sink,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if expr.is_empty() {
+            return self.step(if forward { 1 } else { -1 }, out);
+        }
+        let mut cond: Expr = expr.join(" ").parse()?;
+        let mut sigs = Vec::new();
+        {
+            let mut ctx = self.session.context(&[], &mut *out);
+            cond.bind(|name| -> Result<bool, Box<dyn std::error::Error>> {
+                let sig = ctx.lookup(name)?;
+                sigs.extend(sig.map(|s| (name.to_string(), s)));
+                Ok(sig.is_some())
+            })?;
+        }
+
+        let (wf, samples) = (&self.session.wf, self.session.tb.samples());
+        let order: Box<dyn Iterator<Item = usize>> = if forward {
+            Box::new(self.cursor + 1..samples.len())
+        } else {
+            Box::new((0..self.cursor).rev())
+        };
+        for i in order {
+            let snap = snapshot_at(wf, &sigs, samples[i].idx);
+            let prev = i
+                .checked_sub(1)
+                .map(|j| snapshot_at(wf, &sigs, samples[j].idx));
+            if cond.matches(&snap, prev.as_ref()) {
+                let mut ev = Event::on(&samples[i], "MATCH");
+                for (name, v) in snap.iter() {
+                    ev = ev.with(name, v);
+                }
+                self.cursor = i;
+                out.event(&ev)?;
+                return Ok(());
+            }
+        }
+        out.note(&format!(
+            "No {} cycle where {}",
+            if forward { "later" } else { "earlier" },
+            cond
+        ))?;
+        Ok(())
+    }
+
+    /// Move to the next cycle where a signal whose name contains NAME equals VALUE.
+    fn find(&mut self, query: &str, out: &mut dyn Sink) -> Result<(), Box<dyn std::error::Error>> {
+        let (pattern, value) = query
+            .split_once('=')
+            .ok_or("expected NAME=VALUE, e.g. p_rd=37")?;
+        let value = value.parse::<NumArg>()?.get();
+        let scope = self.session.scope.as_deref();
+        let hierarchy = self.session.wf.hierarchy();
+        let sigs: Vec<(String, SignalRef)> = hierarchy
+            .iter_vars()
+            .map(|v| (v.full_name(hierarchy), v.signal_ref()))
+            .filter(|(n, _)| scope.is_none_or(|s| in_scope(n, s)) && n.contains(pattern))
+            .collect();
+        if sigs.is_empty() {
+            return Err(format!("No signal name contains `{}`", pattern).into());
+        }
+        let to_load: Vec<SignalRef> = sigs.iter().map(|(_, s)| *s).collect();
+        self.session.wf.load_signals(&to_load);
+
+        let (wf, samples) = (&self.session.wf, self.session.tb.samples());
+        for (i, s) in samples.iter().enumerate().skip(self.cursor + 1) {
+            let hits: Vec<&str> = sigs
+                .iter()
+                .filter(|(_, sig)| value_at(wf, *sig, s.idx).is_some_and(|v| v == value))
+                .map(|(n, _)| n.as_str())
+                .collect();
+            if !hits.is_empty() {
+                self.cursor = i;
+                let mut ev = Event::on(s, "FOUND");
+                for name in hits {
+                    ev = ev.with(name, value);
+                }
+                out.event(&ev)?;
+                return Ok(());
+            }
+        }
+        out.note(&format!("No later cycle where {}={}", pattern, value))?;
+        Ok(())
+    }
+
+    /// Run one parsed command line; `Ok(false)` ends the session.
+    fn exec(
+        &mut self,
+        matches: &clap::ArgMatches,
+        out: &mut dyn Sink,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let Some((name, sub)) = matches.subcommand() else {
+            return Ok(true);
+        };
+        if !Builtin::has_subcommand(name) {
+            let passes = self.passes;
+            let pass = passes
+                .iter()
+                .find(|p| p.name() == name)
+                .ok_or_else(|| format!("Unknown command: {}", name))?;
+            self.session.run_pass(pass.as_ref(), sub, out)?;
+            // `at` also moves the cursor.
+            if let Ok(Some(time)) = sub.try_get_one::<TimeArg>("time") {
+                let t = time.resolve(&self.session.tb)?;
+                if let Some(s) = self.session.tb.at_or_before(t) {
+                    let samples = self.session.tb.samples();
+                    self.cursor = samples.partition_point(|x| x.time < s.time);
+                }
+            }
+            return Ok(true);
+        }
+        match Builtin::from_arg_matches(matches)? {
+            Builtin::Show { pattern } => self.show(&pattern, out)?,
+            Builtin::Next { expr } => self.seek(&expr, true, out)?,
+            Builtin::Prev { expr } => self.seek(&expr, false, out)?,
+            Builtin::Back { n } => self.step(-(n as isize), out)?,
+            Builtin::Step { n } => self.step(n as isize, out)?,
+            Builtin::Find { query } => self.find(&query, out)?,
+            Builtin::Quit => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+/// Read commands until `quit` or end of input. Errors are printed and the session goes
+/// on; results are written to stdout in `format`.
+pub fn run(
+    session: &mut Session,
+    passes: &[Box<dyn AnalysisPass>],
+    format: Format,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let cmd = passes.iter().fold(
+        Builtin::augment_subcommands(
+            Command::new(NAME)
+                .no_binary_name(true)
+                .subcommand_required(true)
+                .override_usage("<COMMAND> [ARGS]"),
+        ),
+        |cmd, pass| {
+            cmd.subcommand(
+                pass.command()
+                    .after_help(format!("Output: {}", pass.output())),
+            )
+        },
+    );
+
+    let logical = logical_signals(session);
+    let mut signals: Vec<String> = logical.iter().map(|(n, _)| n.clone()).collect();
+    let hierarchy = session.wf.hierarchy();
+    signals.extend(hierarchy.iter_vars().map(|v| v.full_name(hierarchy)));
+    signals.sort();
+    signals.dedup();
+    let commands = cmd
+        .get_subcommands()
+        .flat_map(|c| std::iter::once(c.get_name()).chain(c.get_visible_aliases()))
+        .map(String::from)
+        .collect();
+
+    let mut rl: Editor<Names, FileHistory> = Editor::new()?;
+    rl.set_helper(Some(Names { commands, signals }));
+    let history = history_path();
+    if let Some(h) = &history {
+        // A missing history file is fine on first use.
+        let _ = rl.load_history(h);
+    }
+
+    let first = session.tb.samples().iter().position(|s| s.cycle.is_some());
+    let mut repl = Repl {
+        session,
+        passes,
+        logical,
+        cursor: first.unwrap_or(0),
+    };
+    println!("Type `help` for commands, `quit` or Ctrl-D to leave.");
+    loop {
+        let line = match rl.readline(&repl.prompt()) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        };
+        let Some(words) = shlex::split(&line) else {
+            eprintln!("Error: unbalanced quotes");
+            continue;
+        };
+        if words.is_empty() {
+            continue;
+        }
+        rl.add_history_entry(line.as_str())?;
+        let matches = match cmd.clone().try_get_matches_from(&words) {
+            Ok(m) => m,
+            Err(e) => {
+                e.print()?;
+                continue;
+            }
+        };
+        let mut out = open_sink(format, Box::new(std::io::stdout()));
+        let res = repl.exec(&matches, out.as_mut());
+        out.finish()?;
+        match res {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) => eprintln!("Error: {}", e),
+        }
+    }
+    if let Some(h) = &history {
+        rl.save_history(h)?;
+    }
+    Ok(())
+}
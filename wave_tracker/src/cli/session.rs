@@ -0,0 +1,129 @@
+use clap::ArgMatches;
+use wellen::{SignalRef, Time, simple};
+
+use crate::analysis::{AnalysisPass, PassContext};
+use crate::core::{
+    ClockSpec, CoreLayout, Isa, Schema, SignalMap, Sink, Timebase, default_wave_path, in_scope,
+};
+
+use super::Args;
+
+/// A parsed waveform with its layout and cycle axis. One-shot runs use it for a single
+/// pass; the REPL keeps it (and every signal loaded so far) across commands.
+pub struct Session {
+    pub wf: simple::Waveform,
+    pub time_table: Vec<Time>,
+    pub tb: Timebase,
+    pub schema: Schema,
+    pub scope: Option<String>,
+    pub layout: CoreLayout,
+    pub isa: Isa,
+}
+
+impl Session {
+    /// Parse the waveform named by `args`, detect layout and clock, and note each on `out`.
+    pub fn open(
+        args: &Args,
+        schema: Schema,
+        out: &mut dyn Sink,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = args.file.clone().unwrap_or_else(default_wave_path);
+        if !path.exists() {
+            return Err(format!("Waveform file not found: {}", path.display()).into());
+        }
+
+        let mut wf = simple::read(&path)?;
+        let time_table = wf.time_table().to_vec();
+        if time_table.is_empty() {
+            return Err("Empty waveform: no time samples in file".into());
+        }
+        let hierarchy = wf.hierarchy();
+
+        let timescale = hierarchy
+            .timescale()
+            .map(|ts| format!("{}{:?}", ts.factor, ts.unit))
+            .unwrap_or_else(|| "?".into());
+
+        let var_count = hierarchy.iter_vars().count();
+        out.note(&format!(
+            "Loaded waveform: timescale {}, {} signals",
+            timescale, var_count
+        ))?;
+        let layout = args.layout.apply(CoreLayout::detect(hierarchy));
+        out.note(&format!("Core layout: {}", layout))?;
+
+        let scope = args.scope.clone();
+        let tb = Timebase::detect(
+            &mut wf,
+            &ClockSpec {
+                clock: args.clock.as_deref(),
+                reset: args.reset.as_deref(),
+                scope: scope.as_deref(),
+            },
+        )?;
+        out.note(&format!("Clock: {}", tb))?;
+
+        Ok(Self {
+            wf,
+            time_table,
+            tb,
+            schema,
+            scope,
+            layout,
+            isa: args.isa,
+        })
+    }
+
+    /// Resolve and load the signals `pass` asks for with these arguments: its schema keys,
+    /// then every in-scope signal it [wants](AnalysisPass::wants_signal).
+    pub fn pass_signals(
+        &mut self,
+        pass: &dyn AnalysisPass,
+        matches: &ArgMatches,
+    ) -> Result<Vec<(String, SignalRef)>, Box<dyn std::error::Error>> {
+        let scope = self.scope.as_deref();
+        let hierarchy = self.wf.hierarchy();
+        let mut sigs =
+            SignalMap::resolve(&self.schema, hierarchy, pass.signals(), scope)?.into_sigs();
+        sigs.extend(
+            hierarchy
+                .iter_vars()
+                .map(|v| (v.full_name(hierarchy), v.signal_ref()))
+                .filter(|(n, _)| scope.is_none_or(|s| in_scope(n, s)))
+                .filter(|(n, _)| pass.wants_signal(n, matches)),
+        );
+        let to_load: Vec<SignalRef> = sigs.iter().map(|(_, sr)| *sr).collect();
+        self.wf.load_signals(&to_load);
+        Ok(sigs)
+    }
+
+    /// Context over this session for `sigs` (already loaded).
+    pub fn context<'a>(
+        &'a mut self,
+        sigs: &'a [(String, SignalRef)],
+        out: &'a mut dyn Sink,
+    ) -> PassContext<'a> {
+        PassContext {
+            wf: &mut self.wf,
+            time_table: &self.time_table,
+            sigs,
+            tb: &self.tb,
+            schema: &self.schema,
+            scope: self.scope.as_deref(),
+            layout: self.layout,
+            isa: self.isa,
+            out,
+        }
+    }
+
+    /// Load the signals of `pass` and run it.
+    pub fn run_pass(
+        &mut self,
+        pass: &dyn AnalysisPass,
+        matches: &ArgMatches,
+        out: &mut dyn Sink,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let sigs = self.pass_signals(pass, matches)?;
+        pass.run(&mut self.context(&sigs, out), matches)
+    }
+}
@@ -0,0 +1,327 @@
+//! Cycle axis: one sample per rising edge of the core clock, cycles numbered from reset
+//! deassertion.
+
+use std::fmt;
+
+use wellen::{Hierarchy, SignalRef, Time, TimeTableIdx, Timescale, simple::Waveform};
+
+use super::query::value_at;
+use super::schema::in_scope;
+
+/// Leaf names tried for the clock, in order.
+const CLOCK_NAMES: &[&str] = &["clock", "clk"];
+/// Leaf names tried for the reset, in order.
+const RESET_NAMES: &[&str] = &["reset", "rst", "reset_n", "rst_n", "resetn", "rstn"];
+
+/// One point on the analysis time axis.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sample {
+    /// Time table index the values are read at.
+    pub idx: TimeTableIdx,
+    /// `time_table[idx]`, in timescale units.
+    pub time: Time,
+    /// Cycle number, counted from the first rising edge after reset deassertion; `None`
+    /// while in reset or when no clock was found.
+    pub cycle: Option<u64>,
+}
+
+/// A 1-bit signal picked from the hierarchy.
+#[derive(Clone, Debug)]
+pub struct Port {
+    pub name: String,
+    pub signal: SignalRef,
+}
+
+/// Reset polarity from its name: `*_n` / `*n` resets are active low.
+fn active_low(name: &str) -> bool {
+    let leaf = name.rsplit('.').next().unwrap_or(name);
+    leaf.ends_with("_n") || leaf == "resetn" || leaf == "rstn"
+}
+
+/// Shallowest 1-bit signal named `explicit` (full name or a suffix at a scope boundary),
+/// or, without `explicit`, whose leaf name is one of `leaves` (earlier names win).
+/// Signals under `scope` are preferred.
+pub fn find_port(
+    hierarchy: &Hierarchy,
+    leaves: &[&str],
+    explicit: Option<&str>,
+    scope: Option<&str>,
+) -> Option<Port> {
+    let mut best: Option<((bool, usize, usize), Port)> = None;
+    for var in hierarchy.iter_vars() {
+        if var.length() != Some(1) {
+            continue;
+        }
+        let name = var.full_name(hierarchy);
+        let rank = match explicit {
+            Some(e) => {
+                if name != e && !name.ends_with(&format!(".{}", e)) {
+                    continue;
+                }
+                0
+            }
+            None => match leaves.iter().position(|l| *l == var.name(hierarchy)) {
+                Some(r) => r,
+                None => continue,
+            },
+        };
+        let outside = scope.is_some_and(|s| !in_scope(&name, s));
+        let key = (outside, name.matches('.').count(), rank);
+        if best.as_ref().is_none_or(|(k, _)| key < *k) {
+            best = Some((
+                key,
+                Port {
+                    name,
+                    signal: var.signal_ref(),
+                },
+            ));
+        }
+    }
+    best.map(|(_, p)| p)
+}
+
+/// Clock/reset selection; `None` fields are detected.
+#[derive(Clone, Debug, Default)]
+pub struct ClockSpec<'a> {
+    pub clock: Option<&'a str>,
+    pub reset: Option<&'a str>,
+    pub scope: Option<&'a str>,
+}
+
+/// The samples passes iterate: rising clock edges when a clock is known, else every
+/// time table entry.
+pub struct Timebase {
+    samples: Vec<Sample>,
+    pub clock: Option<Port>,
+    pub reset: Option<Port>,
+    pub timescale: Option<Timescale>,
+}
+
+impl Timebase {
+    /// Find clock and reset (see [`find_port`]) and build the cycle axis.
+    ///
+    /// Each cycle is sampled at the last time table entry before its rising edge, i.e.
+    /// the settled values the flops capture on that edge; glitches and the falling edge
+    /// are skipped. Cycle 0 is the first edge at which reset reads deasserted; with no
+    /// reset signal it is the first edge.
+    pub fn detect(
+        wf: &mut Waveform,
+        spec: &ClockSpec<'_>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let hierarchy = wf.hierarchy();
+        let timescale = hierarchy.timescale();
+        let clock = find_port(hierarchy, CLOCK_NAMES, spec.clock, spec.scope);
+        if let (Some(name), None) = (spec.clock, &clock) {
+            return Err(
+                format!("Clock signal `{}` not found (1-bit signal expected)", name).into(),
+            );
+        }
+        let reset = find_port(hierarchy, RESET_NAMES, spec.reset, spec.scope);
+        if let (Some(name), None) = (spec.reset, &reset) {
+            return Err(
+                format!("Reset signal `{}` not found (1-bit signal expected)", name).into(),
+            );
+        }
+        let time_table = wf.time_table().to_vec();
+
+        let Some(clk) = &clock else {
+            let samples = time_table
+                .iter()
+                .enumerate()
+                .map(|(i, &time)| Sample {
+                    idx: i as TimeTableIdx,
+                    time,
+                    cycle: None,
+                })
+                .collect();
+            return Ok(Self {
+                samples,
+                clock,
+                reset,
+                timescale,
+            });
+        };
+
+        let mut to_load = vec![clk.signal];
+        to_load.extend(reset.iter().map(|r| r.signal));
+        wf.load_signals(&to_load);
+        let level = |wf: &Waveform, sig: SignalRef, idx: TimeTableIdx| {
+            value_at(wf, sig, idx).and_then(|v| v.as_bool())
+        };
+
+        let mut samples = Vec::new();
+        let mut next_cycle: Option<u64> = None;
+        let mut prev = level(wf, clk.signal, 0);
+        for i in 1..time_table.len() as TimeTableIdx {
+            let cur = level(wf, clk.signal, i);
+            let rising = prev == Some(false) && cur == Some(true);
+            prev = cur;
+            if !rising {
+                continue;
+            }
+            let idx = i - 1;
+            if next_cycle.is_none() {
+                // X/Z reset counts as asserted.
+                let in_reset = reset.as_ref().is_some_and(|r| {
+                    level(wf, r.signal, idx).is_none_or(|v| v != active_low(&r.name))
+                });
+                if !in_reset {
+                    next_cycle = Some(0);
+                }
+            }
+            samples.push(Sample {
+                idx,
+                time: time_table[idx as usize],
+                cycle: next_cycle,
+            });
+            next_cycle = next_cycle.map(|c| c + 1);
+        }
+        if samples.is_empty() {
+            return Err(format!("Clock `{}` has no rising edge", clk.name).into());
+        }
+        Ok(Self {
+            samples,
+            clock,
+            reset,
+            timescale,
+        })
+    }
+
+    pub fn samples(&self) -> &[Sample] {
+        &self.samples
+    }
+
+    /// Samples with `start <= time <= end`.
+    pub fn range(&self, start: Time, end: Time) -> &[Sample] {
+        let lo = self.samples.partition_point(|s| s.time < start);
+        let hi = self.samples.partition_point(|s| s.time <= end);
+        &self.samples[lo..hi.max(lo)]
+    }
+
+    /// Last sample at or before `t`.
+    pub fn at_or_before(&self, t: Time) -> Option<&Sample> {
+        let n = self.samples.partition_point(|s| s.time <= t);
+        n.checked_sub(1).map(|i| &self.samples[i])
+    }
+
+    /// Sample of cycle `c`.
+    pub fn cycle(&self, c: u64) -> Option<&Sample> {
+        self.cycles()
+            .get(usize::try_from(c).ok()?)
+            .filter(|s| s.cycle == Some(c))
+    }
+
+    /// Samples that count as cycles: from the first edge out of reset on. Edges during
+    /// reset have no cycle number, and the core's registers are not initialised yet. Every
+    /// sample when none has a cycle number (no clock, or reset never released).
+    pub fn cycles(&self) -> &[Sample] {
+        let first = self.samples.iter().position(|s| s.cycle.is_some());
+        &self.samples[first.unwrap_or(0)..]
+    }
+
+    /// [`cycles`](Self::cycles) with `start <= time <= end`.
+    pub fn cycles_in(&self, start: Time, end: Time) -> &[Sample] {
+        let cycles = self.cycles();
+        let lo = cycles.partition_point(|s| s.time < start);
+        let hi = cycles.partition_point(|s| s.time <= end);
+        &cycles[lo..hi.max(lo)]
+    }
+
+    /// Whether samples carry cycle numbers.
+    pub fn has_cycles(&self) -> bool {
+        self.samples.iter().any(|s| s.cycle.is_some())
+    }
+
+    pub fn last_time(&self) -> Time {
+        self.samples.last().map_or(0, |s| s.time)
+    }
+}
+
+impl fmt::Display for Timebase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(clk) = &self.clock else {
+            return write!(
+                f,
+                "no clock found, sampling all {} time steps (set one with --clock)",
+                self.samples.len()
+            );
+        };
+        write!(f, "{} ({} rising edges", clk.name, self.samples.len())?;
+        match self.samples.iter().find(|s| s.cycle == Some(0)) {
+            Some(s) => write!(f, ", cycle 0 at t={}", s.time)?,
+            None => write!(f, ", never out of reset")?,
+        }
+        match &self.reset {
+            Some(r) => write!(f, ", reset {})", r.name),
+            None => write!(f, ", no reset)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wellen::simple::read_from_reader;
+
+    /// `top.clock` with period 10 (rising at 5, 15, 25, 35) and `top.reset` from `resets`.
+    fn waveform(resets: &str) -> Waveform {
+        let vcd = format!(
+            "$timescale 1ns $end\n$scope module top $end\n$var wire 1 ! clock $end\n\
+             $var wire 1 \" reset $end\n$upscope $end\n$enddefinitions $end\n\
+             #0\n0!\n1\"\n#5\n1!\n#10\n0!\n{}#15\n1!\n#20\n0!\n#25\n1!\n#30\n0!\n#35\n1!\n#40\n0!\n",
+            resets
+        );
+        read_from_reader(std::io::Cursor::new(vcd.into_bytes())).unwrap()
+    }
+
+    fn detect(resets: &str) -> Timebase {
+        Timebase::detect(&mut waveform(resets), &ClockSpec::default()).unwrap()
+    }
+
+    fn axis(tb: &Timebase) -> Vec<(Time, Option<u64>)> {
+        tb.samples().iter().map(|s| (s.time, s.cycle)).collect()
+    }
+
+    #[test]
+    fn cycle_zero_at_reset_release() {
+        let tb = detect("#12\n0\"\n");
+        assert_eq!(tb.clock.as_ref().unwrap().name, "top.clock");
+        assert_eq!(tb.reset.as_ref().unwrap().name, "top.reset");
+        // Read just before each edge: the edge at 15 sees reset low since 12.
+        assert_eq!(
+            axis(&tb),
+            [(0, None), (12, Some(0)), (20, Some(1)), (30, Some(2))]
+        );
+        assert!(tb.has_cycles());
+        assert_eq!(tb.cycles().len(), 3);
+        assert_eq!(tb.cycle(2).map(|s| s.time), Some(30));
+        assert_eq!(tb.cycle(3), None);
+        assert_eq!(tb.cycles_in(13, 30).len(), 2);
+    }
+
+    #[test]
+    fn reset_never_released() {
+        let tb = detect("");
+        assert_eq!(axis(&tb), [(0, None), (10, None), (20, None), (30, None)]);
+        assert!(!tb.has_cycles());
+        // Without cycle numbers every sample counts.
+        assert_eq!(tb.cycles().len(), 4);
+        assert_eq!(tb.cycle(0), None);
+    }
+
+    #[test]
+    fn explicit_ports() {
+        let mut wf = waveform("");
+        let spec = ClockSpec {
+            clock: Some("clk"),
+            ..ClockSpec::default()
+        };
+        let err = Timebase::detect(&mut wf, &spec).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Clock signal `clk` not found (1-bit signal expected)"
+        );
+        assert!(active_low("top.rst_n"));
+        assert!(!active_low("top.reset"));
+    }
+}
@@ -0,0 +1,926 @@
+//! RISC-V instruction disassembly for trace output, with ABI register names and the
+//! common pseudo-ops. [`Isa`] selects the extensions decoded beyond RV32I, Zicsr and the
+//! privileged instructions, from the same ISA strings as `nzea_config::IsaConfig`.
+
+use std::fmt;
+use std::str::FromStr;
+
+use super::num::ABI_NAMES;
+
+/// Floating-point register ABI names, indexed by register number.
+const FP_NAMES: [&str; 32] = [
+    "ft0", "ft1", "ft2", "ft3", "ft4", "ft5", "ft6", "ft7", "fs0", "fs1", "fa0", "fa1", "fa2",
+    "fa3", "fa4", "fa5", "fa6", "fa7", "fs2", "fs3", "fs4", "fs5", "fs6", "fs7", "fs8", "fs9",
+    "fs10", "fs11", "ft8", "ft9", "ft10", "ft11",
+];
+
+/// Extensions to decode, parsed like `nzea_config::IsaConfig`: `riscv32im`, `rv32gc`, then
+/// `_`-separated named extensions in any order (`zve32x`, `zve64x`, `zvl128b`, `wjcus0`).
+///
+/// Only RV32 encodings are decoded; letters without instructions here (`b`, `h`, `q`, ...)
+/// are accepted and ignored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Isa {
+    pub xlen: u32,
+    /// RV32E base (decoded like RV32I).
+    pub e: bool,
+    pub m: bool,
+    pub a: bool,
+    pub f: bool,
+    pub d: bool,
+    pub c: bool,
+    /// `v`, Zve32x or Zve64x: vsetvl*, vector loads / stores and integer arithmetic.
+    pub vector: bool,
+    pub zve32x: bool,
+    pub zve64x: bool,
+    /// `zvl{N}b`: minimum VLEN.
+    pub zvl: Option<u32>,
+    /// Custom extension 0: the NNU instructions on the custom-0 opcode.
+    pub wjcus0: bool,
+}
+
+impl Isa {
+    /// The full nzea configuration, used when `--isa` is not given.
+    pub const DEFAULT: &'static str = "riscv32im_zve32x_wjcus0";
+
+    /// Parse an ISA string; unknown letters and named extensions are errors, as in
+    /// `IsaConfig.parse`. An empty string is RV32I.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let lower = s.trim().to_ascii_lowercase();
+        let mut parts = lower.split('_').filter(|p| !p.is_empty());
+        let base = parts.next().unwrap_or("rv32i");
+        let rest = base.strip_prefix("riscv").unwrap_or(base);
+        let rest = rest.strip_prefix("rv").unwrap_or(rest);
+        let (xlen, letters) = if let Some(l) = rest.strip_prefix("32") {
+            (32, l)
+        } else if let Some(l) = rest.strip_prefix("64") {
+            (64, l)
+        } else {
+            return Err(format!(
+                "unrecognized ISA base (expected riscv32/rv32 + optional letters): {}",
+                base
+            ));
+        };
+        let mut letters: Vec<char> = if letters.is_empty() {
+            vec!['i']
+        } else {
+            letters.chars().collect()
+        };
+        let mut unknown: Vec<String> = letters
+            .iter()
+            .filter(|c| !"iemafdqcbhjlnptvg".contains(**c))
+            .map(|c| c.to_string())
+            .collect();
+        unknown.sort();
+        unknown.dedup();
+        if !unknown.is_empty() {
+            return Err(format!(
+                "unknown base extension letter(s): {}",
+                unknown.join(", ")
+            ));
+        }
+        if letters.contains(&'g') {
+            letters.extend(['i', 'm', 'a', 'f', 'd']);
+        }
+        let has = |c: char| letters.contains(&c);
+        if !has('i') && !has('e') {
+            return Err("base ISA must include i or e (or g, which implies i)".into());
+        }
+
+        let mut isa = Isa {
+            xlen,
+            e: !has('i'),
+            m: has('m'),
+            a: has('a'),
+            f: has('f'),
+            d: has('d'),
+            c: has('c'),
+            vector: has('v'),
+            zve32x: false,
+            zve64x: false,
+            zvl: None,
+            wjcus0: false,
+        };
+        for token in parts {
+            match token {
+                "zve32x" => isa.zve32x = true,
+                "zve64x" => isa.zve64x = true,
+                "wjcus0" => isa.wjcus0 = true,
+                _ => match token
+                    .strip_prefix("zvl")
+                    .and_then(|t| t.strip_suffix('b'))
+                    .and_then(|n| n.parse().ok())
+                {
+                    Some(bits) => {
+                        isa.zvl.get_or_insert(bits);
+                    }
+                    None => return Err(format!("unknown named extension(s): {}", token)),
+                },
+            }
+        }
+        isa.vector |= isa.zve32x || isa.zve64x;
+        Ok(isa)
+    }
+
+    /// Disassemble the instruction `word` fetched at `pc` (used for branch and jump
+    /// targets), e.g. `sw s0, 8(sp)` or `bne s1, zero, 0x80000040`. With `c`, a word whose
+    /// low bits are not `11` is a compressed instruction in its low half. Words outside
+    /// the enabled extensions print as `.word 0x...`.
+    pub fn disasm(&self, pc: u64, word: u32) -> String {
+        let decoded = if word & 3 != 3 {
+            self.c
+                .then(|| compressed(self, pc, word & 0xffff))
+                .flatten()
+        } else {
+            decode(self, pc, word)
+        };
+        decoded.unwrap_or_else(|| format!(".word {:#010x}", word))
+    }
+}
+
+impl Default for Isa {
+    fn default() -> Self {
+        Isa::parse(Isa::DEFAULT).expect("default ISA string parses")
+    }
+}
+
+impl FromStr for Isa {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Isa::parse(s)
+    }
+}
+
+/// Canonical string, e.g. `rv32imc_zve32x_zvl128b_wjcus0`.
+impl fmt::Display for Isa {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "rv{}{}", self.xlen, if self.e { 'e' } else { 'i' })?;
+        for (on, letter) in [
+            (self.m, 'm'),
+            (self.a, 'a'),
+            (self.f, 'f'),
+            (self.d, 'd'),
+            (self.c, 'c'),
+            (self.vector && !self.zve32x && !self.zve64x, 'v'),
+        ] {
+            if on {
+                write!(f, "{}", letter)?;
+            }
+        }
+        if self.zve32x {
+            f.write_str("_zve32x")?;
+        }
+        if self.zve64x {
+            f.write_str("_zve64x")?;
+        }
+        if let Some(bits) = self.zvl {
+            write!(f, "_zvl{}b", bits)?;
+        }
+        if self.wjcus0 {
+            f.write_str("_wjcus0")?;
+        }
+        Ok(())
+    }
+}
+
+fn reg(r: u32) -> &'static str {
+    ABI_NAMES[(r & 31) as usize]
+}
+
+fn freg(r: u32) -> &'static str {
+    FP_NAMES[(r & 31) as usize]
+}
+
+/// Sign-extend the low `bits` of `v`.
+fn sext(v: u32, bits: u32) -> i32 {
+    let shift = 32 - bits;
+    ((v << shift) as i32) >> shift
+}
+
+fn imm_i(w: u32) -> i32 {
+    (w as i32) >> 20
+}
+
+fn imm_s(w: u32) -> i32 {
+    sext(((w >> 25) << 5) | ((w >> 7) & 0x1f), 12)
+}
+
+fn imm_b(w: u32) -> i32 {
+    sext(
+        ((w >> 31) << 12)
+            | (((w >> 7) & 1) << 11)
+            | (((w >> 25) & 0x3f) << 5)
+            | ((w >> 8) & 0xf) << 1,
+        13,
+    )
+}
+
+fn imm_j(w: u32) -> i32 {
+    sext(
+        ((w >> 31) << 20)
+            | (((w >> 12) & 0xff) << 12)
+            | (((w >> 20) & 1) << 11)
+            | (((w >> 21) & 0x3ff) << 1),
+        21,
+    )
+}
+
+fn target(pc: u64, off: i32) -> String {
+    format!("{:#x}", (pc as u32).wrapping_add(off as u32))
+}
+
+/// Machine-mode CSR names the core implements, else the address.
+pub(crate) fn csr_name(addr: u32) -> String {
+    match addr {
+        0x300 => "mstatus".into(),
+        0x301 => "misa".into(),
+        0x304 => "mie".into(),
+        0x305 => "mtvec".into(),
+        0x340 => "mscratch".into(),
+        0x341 => "mepc".into(),
+        0x342 => "mcause".into(),
+        0x343 => "mtval".into(),
+        0x344 => "mip".into(),
+        0xb00 => "mcycle".into(),
+        0xb02 => "minstret".into(),
+        0xf11 => "mvendorid".into(),
+        0xf12 => "marchid".into(),
+        0xf13 => "mimpid".into(),
+        0xf14 => "mhartid".into(),
+        _ => format!("{:#x}", addr),
+    }
+}
+
+fn decode(isa: &Isa, pc: u64, w: u32) -> Option<String> {
+    let opcode = w & 0x7f;
+    let rd = (w >> 7) & 31;
+    let funct3 = (w >> 12) & 7;
+    let rs1 = (w >> 15) & 31;
+    let rs2 = (w >> 20) & 31;
+    let funct7 = w >> 25;
+    let (d, s1, s2) = (reg(rd), reg(rs1), reg(rs2));
+
+    Some(match opcode {
+        0x37 => format!("lui {}, {:#x}", d, w >> 12),
+        0x17 => format!("auipc {}, {:#x}", d, w >> 12),
+        0x6f => match rd {
+            0 => format!("j {}", target(pc, imm_j(w))),
+            1 => format!("jal {}", target(pc, imm_j(w))),
+            _ => format!("jal {}, {}", d, target(pc, imm_j(w))),
+        },
+        0x67 if funct3 == 0 => match (rd, imm_i(w)) {
+            (0, 0) if rs1 == 1 => "ret".into(),
+            (0, 0) => format!("jr {}", s1),
+            (1, 0) => format!("jalr {}", s1),
+            (_, imm) => format!("jalr {}, {}({})", d, imm, s1),
+        },
+        0x63 => {
+            let op = ["beq", "bne", "", "", "blt", "bge", "bltu", "bgeu"][funct3 as usize];
+            if op.is_empty() {
+                return None;
+            }
+            let t = target(pc, imm_b(w));
+            match (funct3, rs2) {
+                (0, 0) => format!("beqz {}, {}", s1, t),
+                (1, 0) => format!("bnez {}, {}", s1, t),
+                _ => format!("{} {}, {}, {}", op, s1, s2, t),
+            }
+        }
+        0x03 => {
+            let op = ["lb", "lh", "lw", "", "lbu", "lhu", "", ""][funct3 as usize];
+            if op.is_empty() {
+                return None;
+            }
+            format!("{} {}, {}({})", op, d, imm_i(w), s1)
+        }
+        0x23 => {
+            let op = ["sb", "sh", "sw"].get(funct3 as usize)?;
+            format!("{} {}, {}({})", op, s2, imm_s(w), s1)
+        }
+        0x13 => {
+            let imm = imm_i(w);
+            match funct3 {
+                0 if rd == 0 && rs1 == 0 && imm == 0 => "nop".into(),
+                0 if rs1 == 0 => format!("li {}, {}", d, imm),
+                0 if imm == 0 => format!("mv {}, {}", d, s1),
+                0 => format!("addi {}, {}, {}", d, s1, imm),
+                2 => format!("slti {}, {}, {}", d, s1, imm),
+                3 if imm == 1 => format!("seqz {}, {}", d, s1),
+                3 => format!("sltiu {}, {}, {}", d, s1, imm),
+                4 if imm == -1 => format!("not {}, {}", d, s1),
+                4 => format!("xori {}, {}, {}", d, s1, imm),
+                6 => format!("ori {}, {}, {}", d, s1, imm),
+                7 => format!("andi {}, {}, {}", d, s1, imm),
+                1 if funct7 == 0 => format!("slli {}, {}, {}", d, s1, rs2),
+                5 if funct7 == 0 => format!("srli {}, {}, {}", d, s1, rs2),
+                5 if funct7 == 0x20 => format!("srai {}, {}, {}", d, s1, rs2),
+                _ => return None,
+            }
+        }
+        0x33 => {
+            let op = match (funct7, funct3) {
+                (0x00, 0) => "add",
+                (0x20, 0) => "sub",
+                (0x00, 1) => "sll",
+                (0x00, 2) => "slt",
+                (0x00, 3) => "sltu",
+                (0x00, 4) => "xor",
+                (0x00, 5) => "srl",
+                (0x20, 5) => "sra",
+                (0x00, 6) => "or",
+                (0x00, 7) => "and",
+                (0x01, f) if isa.m => [
+                    "mul", "mulh", "mulhsu", "mulhu", "div", "divu", "rem", "remu",
+                ][f as usize],
+                _ => return None,
+            };
+            match (op, rs1) {
+                ("sub", 0) => format!("neg {}, {}", d, s2),
+                _ => format!("{} {}, {}, {}", op, d, s1, s2),
+            }
+        }
+        0x0f => match funct3 {
+            0 => "fence".into(),
+            1 => "fence.i".into(),
+            _ => return None,
+        },
+        0x73 => {
+            let csr = csr_name(w >> 20);
+            match funct3 {
+                0 => match w {
+                    0x0000_0073 => "ecall".into(),
+                    0x0010_0073 => "ebreak".into(),
+                    0x3020_0073 => "mret".into(),
+                    0x1050_0073 => "wfi".into(),
+                    _ => return None,
+                },
+                1 if rd == 0 => format!("csrw {}, {}", csr, s1),
+                2 if rs1 == 0 => format!("csrr {}, {}", d, csr),
+                1..=3 => {
+                    let op = ["", "csrrw", "csrrs", "csrrc"][funct3 as usize];
+                    format!("{} {}, {}, {}", op, d, csr, s1)
+                }
+                5..=7 => {
+                    let op = ["csrrwi", "csrrsi", "csrrci"][funct3 as usize - 5];
+                    format!("{} {}, {}, {}", op, d, csr, rs1)
+                }
+                _ => return None,
+            }
+        }
+        0x2f => amo(isa, w)?,
+        0x07 | 0x27 => load_store_fp(isa, w)?,
+        0x43 | 0x47 | 0x4b | 0x4f => fma(isa, w)?,
+        0x53 => op_fp(isa, w)?,
+        0x57 if isa.vector => op_v(w)?,
+        0x0b if isa.wjcus0 => custom0(w)?,
+        _ => return None,
+    })
+}
+
+/// A: `lr.w`, `sc.w` and the AMOs, with `.aq` / `.rl` ordering suffixes.
+fn amo(isa: &Isa, w: u32) -> Option<String> {
+    if !isa.a || (w >> 12) & 7 != 2 {
+        return None;
+    }
+    let (rd, rs1, rs2) = (reg(w >> 7), reg(w >> 15), reg(w >> 20));
+    let order = ["", ".rl", ".aq", ".aqrl"][((w >> 25) & 3) as usize];
+    let op = match w >> 27 {
+        0x02 if (w >> 20) & 31 == 0 => return Some(format!("lr.w{} {}, ({})", order, rd, rs1)),
+        0x03 => "sc.w",
+        0x01 => "amoswap.w",
+        0x00 => "amoadd.w",
+        0x04 => "amoxor.w",
+        0x0c => "amoand.w",
+        0x08 => "amoor.w",
+        0x10 => "amomin.w",
+        0x14 => "amomax.w",
+        0x18 => "amominu.w",
+        0x1c => "amomaxu.w",
+        _ => return None,
+    };
+    Some(format!("{}{} {}, {}, ({})", op, order, rd, rs2, rs1))
+}
+
+/// LOAD-FP / STORE-FP: `flw` / `fld` and `fsw` / `fsd`, or vector loads / stores by width.
+fn load_store_fp(isa: &Isa, w: u32) -> Option<String> {
+    let store = w & 0x7f == 0x27;
+    let rs1 = reg(w >> 15);
+    let width = (w >> 12) & 7;
+    let scalar = match width {
+        2 if isa.f => "w",
+        3 if isa.d => "d",
+        0 | 5..=7 if isa.vector => return vector_mem(w, store),
+        _ => return None,
+    };
+    Some(if store {
+        format!("fs{} {}, {}({})", scalar, freg(w >> 20), imm_s(w), rs1)
+    } else {
+        format!("fl{} {}, {}({})", scalar, freg(w >> 7), imm_i(w), rs1)
+    })
+}
+
+/// `, v0.t` when the `vm` bit selects masking.
+fn vmask(w: u32) -> &'static str {
+    if (w >> 25) & 1 == 0 { ", v0.t" } else { "" }
+}
+
+/// Vector unit-stride, strided and indexed loads / stores (no segments).
+fn vector_mem(w: u32, store: bool) -> Option<String> {
+    if w >> 29 != 0 {
+        return None;
+    }
+    let eew = match (w >> 12) & 7 {
+        0 => 8,
+        5 => 16,
+        6 => 32,
+        _ => 64,
+    };
+    let (vd, rs1, rs2) = ((w >> 7) & 31, reg(w >> 15), (w >> 20) & 31);
+    let dir = if store { "s" } else { "l" };
+    Some(match (w >> 26) & 3 {
+        0 if rs2 == 0 => format!("v{}e{}.v v{}, ({}){}", dir, eew, vd, rs1, vmask(w)),
+        0 => return None,
+        2 => format!(
+            "v{}se{}.v v{}, ({}), {}{}",
+            dir,
+            eew,
+            vd,
+            rs1,
+            reg(rs2),
+            vmask(w)
+        ),
+        mop => {
+            let order = if mop == 1 { "u" } else { "o" };
+            format!(
+                "v{}{}xei{}.v v{}, ({}), v{}{}",
+                dir,
+                order,
+                eew,
+                vd,
+                rs1,
+                rs2,
+                vmask(w)
+            )
+        }
+    })
+}
+
+/// `.s` / `.d` suffix for the fmt field, when that precision is enabled.
+fn fp_fmt(isa: &Isa, fmt: u32) -> Option<&'static str> {
+    match fmt {
+        0 if isa.f => Some("s"),
+        1 if isa.d => Some("d"),
+        _ => None,
+    }
+}
+
+/// F / D fused multiply-add.
+fn fma(isa: &Isa, w: u32) -> Option<String> {
+    let fmt = fp_fmt(isa, (w >> 25) & 3)?;
+    let op = match w & 0x7f {
+        0x43 => "fmadd",
+        0x47 => "fmsub",
+        0x4b => "fnmsub",
+        _ => "fnmadd",
+    };
+    Some(format!(
+        "{}.{} {}, {}, {}, {}",
+        op,
+        fmt,
+        freg(w >> 7),
+        freg(w >> 15),
+        freg(w >> 20),
+        freg(w >> 27)
+    ))
+}
+
+/// OP-FP: F / D arithmetic, sign injection, compares, conversions and moves.
+fn op_fp(isa: &Isa, w: u32) -> Option<String> {
+    let funct7 = w >> 25;
+    let fmt = fp_fmt(isa, funct7 & 3)?;
+    let funct3 = (w >> 12) & 7;
+    let rs2 = (w >> 20) & 31;
+    let (fd, fs1, fs2) = (freg(w >> 7), freg(w >> 15), freg(w >> 20));
+    let (xd, xs1) = (reg(w >> 7), reg(w >> 15));
+    Some(match funct7 >> 2 {
+        0x00 => format!("fadd.{} {}, {}, {}", fmt, fd, fs1, fs2),
+        0x01 => format!("fsub.{} {}, {}, {}", fmt, fd, fs1, fs2),
+        0x02 => format!("fmul.{} {}, {}, {}", fmt, fd, fs1, fs2),
+        0x03 => format!("fdiv.{} {}, {}, {}", fmt, fd, fs1, fs2),
+        0x0b if rs2 == 0 => format!("fsqrt.{} {}, {}", fmt, fd, fs1),
+        0x04 => {
+            let op = ["fsgnj", "fsgnjn", "fsgnjx"].get(funct3 as usize)?;
+            match (funct3, (w >> 15) & 31 == rs2) {
+                (0, true) => format!("fmv.{} {}, {}", fmt, fd, fs1),
+                (1, true) => format!("fneg.{} {}, {}", fmt, fd, fs1),
+                (2, true) => format!("fabs.{} {}, {}", fmt, fd, fs1),
+                _ => format!("{}.{} {}, {}, {}", op, fmt, fd, fs1, fs2),
+            }
+        }
+        0x05 => {
+            let op = ["fmin", "fmax"].get(funct3 as usize)?;
+            format!("{}.{} {}, {}, {}", op, fmt, fd, fs1, fs2)
+        }
+        0x14 => {
+            let op = ["fle", "flt", "feq"].get(funct3 as usize)?;
+            format!("{}.{} {}, {}, {}", op, fmt, xd, fs1, fs2)
+        }
+        // fcvt.s.d / fcvt.d.s: the source format is in rs2.
+        0x08 => {
+            let from = fp_fmt(isa, rs2)?;
+            if from == fmt {
+                return None;
+            }
+            format!("fcvt.{}.{} {}, {}", fmt, from, fd, fs1)
+        }
+        0x18 => {
+            let to = ["w", "wu"].get(rs2 as usize)?;
+            format!("fcvt.{}.{} {}, {}", to, fmt, xd, fs1)
+        }
+        0x1a => {
+            let from = ["w", "wu"].get(rs2 as usize)?;
+            format!("fcvt.{}.{} {}, {}", fmt, from, fd, xs1)
+        }
+        0x1c if rs2 == 0 && funct3 == 0 && fmt == "s" => format!("fmv.x.w {}, {}", xd, fs1),
+        0x1c if rs2 == 0 && funct3 == 1 => format!("fclass.{} {}, {}", fmt, xd, fs1),
+        0x1e if rs2 == 0 && funct3 == 0 && fmt == "s" => format!("fmv.w.x {}, {}", fd, xs1),
+        _ => return None,
+    })
+}
+
+/// `e32, m1, ta, ma` for a vtype immediate.
+fn vtype(v: u32) -> Option<String> {
+    let lmul = ["m1", "m2", "m4", "m8", "", "mf8", "mf4", "mf2"][(v & 7) as usize];
+    if lmul.is_empty() || v >> 8 != 0 || (v >> 3) & 7 > 3 {
+        return None;
+    }
+    Some(format!(
+        "e{}, {}, {}, {}",
+        8 << ((v >> 3) & 7),
+        lmul,
+        if (v >> 6) & 1 == 1 { "ta" } else { "tu" },
+        if (v >> 7) & 1 == 1 { "ma" } else { "mu" }
+    ))
+}
+
+/// OP-V subset: `vsetvli` / `vsetivli` / `vsetvl`, integer arithmetic and compares
+/// (OPIVV / OPIVX / OPIVI), multiply / divide, `vredsum` and the scalar moves (OPMVV / OPMVX).
+fn op_v(w: u32) -> Option<String> {
+    let funct3 = (w >> 12) & 7;
+    let (vd, rs1, vs2) = ((w >> 7) & 31, (w >> 15) & 31, (w >> 20) & 31);
+    let funct6 = w >> 26;
+    let m = vmask(w);
+    if funct3 == 7 {
+        return match w >> 30 {
+            0 | 1 => Some(format!(
+                "vsetvli {}, {}, {}",
+                reg(vd),
+                reg(rs1),
+                vtype((w >> 20) & 0x7ff)?
+            )),
+            3 => Some(format!(
+                "vsetivli {}, {}, {}",
+                reg(vd),
+                rs1,
+                vtype((w >> 20) & 0x3ff)?
+            )),
+            _ if w >> 25 == 0x40 => Some(format!("vsetvl {}, {}, {}", reg(vd), reg(rs1), reg(vs2))),
+            _ => None,
+        };
+    }
+
+    // Third operand per category: vs1, rs1 or a 5-bit immediate.
+    let src = match funct3 {
+        0 | 2 => format!("v{}", rs1),
+        4 | 6 => reg(rs1).to_string(),
+        3 if matches!(funct6, 0x25 | 0x28 | 0x29) => rs1.to_string(),
+        3 => sext(rs1, 5).to_string(),
+        _ => return None,
+    };
+    let kind = ["vv", "", "vv", "vi", "vx", "", "vx", ""][funct3 as usize];
+    let op = match funct3 {
+        0 | 3 | 4 => match funct6 {
+            0x00 => "vadd",
+            0x02 if funct3 != 3 => "vsub",
+            0x03 if funct3 != 0 => "vrsub",
+            0x04 if funct3 != 3 => "vminu",
+            0x05 if funct3 != 3 => "vmin",
+            0x06 if funct3 != 3 => "vmaxu",
+            0x07 if funct3 != 3 => "vmax",
+            0x09 => "vand",
+            0x0a => "vor",
+            0x0b => "vxor",
+            0x0c => "vrgather",
+            0x17 if (w >> 25) & 1 == 1 && vs2 == 0 => {
+                return Some(format!("vmv.v.{} v{}, {}", &kind[1..], vd, src));
+            }
+            0x17 if (w >> 25) & 1 == 0 => {
+                return Some(format!("vmerge.{}m v{}, v{}, {}, v0", kind, vd, vs2, src));
+            }
+            0x18 => "vmseq",
+            0x19 => "vmsne",
+            0x1a if funct3 != 3 => "vmsltu",
+            0x1b if funct3 != 3 => "vmslt",
+            0x1c => "vmsleu",
+            0x1d => "vmsle",
+            0x1e if funct3 != 0 => "vmsgtu",
+            0x1f if funct3 != 0 => "vmsgt",
+            0x25 => "vsll",
+            0x28 => "vsrl",
+            0x29 => "vsra",
+            _ => return None,
+        },
+        _ => match funct6 {
+            0x00 if funct3 == 2 => {
+                return Some(format!("vredsum.vs v{}, v{}, v{}{}", vd, vs2, rs1, m));
+            }
+            0x10 if funct3 == 2 && rs1 == 0 => {
+                return Some(format!("vmv.x.s {}, v{}", reg(vd), vs2));
+            }
+            0x10 if funct3 == 6 && vs2 == 0 => {
+                return Some(format!("vmv.s.x v{}, {}", vd, reg(rs1)));
+            }
+            0x20 => "vdivu",
+            0x21 => "vdiv",
+            0x22 => "vremu",
+            0x23 => "vrem",
+            0x24 => "vmulhu",
+            0x25 => "vmul",
+            0x26 => "vmulhsu",
+            0x27 => "vmulh",
+            _ => return None,
+        },
+    };
+    Some(format!("{}.{} v{}, v{}, {}{}", op, kind, vd, vs2, src, m))
+}
+
+/// Wjcus0 on custom-0 (`0001011`): the NNU instructions decoded by `RVInst.wjcus0`.
+fn custom0(w: u32) -> Option<String> {
+    let (rd, funct3, rs1, rs2) = ((w >> 7) & 31, (w >> 12) & 7, (w >> 15) & 31, (w >> 20) & 31);
+    Some(match funct3 {
+        0 if w >> 25 == 0 && rd == 0 => format!("nn_load_act {}, {}", reg(rs1), reg(rs2)),
+        1 if w == 0x0000_100b => "nn_start".into(),
+        2 if w >> 20 == 0 => format!("nn_load {}, {}", reg(rd), reg(rs1)),
+        _ => return None,
+    })
+}
+
+/// RVC: the 16-bit instruction `h`, printed with its `c.` mnemonic.
+fn compressed(isa: &Isa, pc: u64, h: u32) -> Option<String> {
+    let bit = |i: u32| (h >> i) & 1;
+    let field = |hi: u32, lo: u32| (h >> lo) & ((1 << (hi - lo + 1)) - 1);
+    // rd' / rs1' / rs2': x8..x15.
+    let rd_p = 8 + field(4, 2);
+    let rs1_p = 8 + field(9, 7);
+    let rd = field(11, 7);
+    let rs2 = field(6, 2);
+    let imm6 = sext((bit(12) << 5) | field(6, 2), 6);
+    let funct3 = field(15, 13);
+    // CL / CS offsets: word (lw / sw / flw / fsw) and double (fld / fsd).
+    let off_w = (field(12, 10) << 3) | (bit(6) << 2) | (bit(5) << 6);
+    let off_d = (field(12, 10) << 3) | (field(6, 5) << 6);
+    let j_off = || {
+        sext(
+            (bit(12) << 11)
+                | (bit(11) << 4)
+                | (field(10, 9) << 8)
+                | (bit(8) << 10)
+                | (bit(7) << 6)
+                | (bit(6) << 7)
+                | (field(5, 3) << 1)
+                | (bit(2) << 5),
+            12,
+        )
+    };
+
+    Some(match (h & 3, funct3) {
+        (0, 0) => {
+            let imm = (field(12, 11) << 4) | (field(10, 7) << 6) | (bit(6) << 2) | (bit(5) << 3);
+            if imm == 0 {
+                return None;
+            }
+            format!("c.addi4spn {}, sp, {}", reg(rd_p), imm)
+        }
+        (0, 1) if isa.d => format!("c.fld {}, {}({})", freg(rd_p), off_d, reg(rs1_p)),
+        (0, 2) => format!("c.lw {}, {}({})", reg(rd_p), off_w, reg(rs1_p)),
+        (0, 3) if isa.f => format!("c.flw {}, {}({})", freg(rd_p), off_w, reg(rs1_p)),
+        (0, 5) if isa.d => format!("c.fsd {}, {}({})", freg(rd_p), off_d, reg(rs1_p)),
+        (0, 6) => format!("c.sw {}, {}({})", reg(rd_p), off_w, reg(rs1_p)),
+        (0, 7) if isa.f => format!("c.fsw {}, {}({})", freg(rd_p), off_w, reg(rs1_p)),
+        (1, 0) if rd == 0 => "c.nop".into(),
+        (1, 0) => format!("c.addi {}, {}", reg(rd), imm6),
+        (1, 1) => format!("c.jal {}", target(pc, j_off())),
+        (1, 2) => format!("c.li {}, {}", reg(rd), imm6),
+        (1, 3) if rd == 2 => {
+            let imm = sext(
+                (bit(12) << 9) | (bit(6) << 4) | (bit(5) << 6) | (field(4, 3) << 7) | (bit(2) << 5),
+                10,
+            );
+            if imm == 0 {
+                return None;
+            }
+            format!("c.addi16sp sp, {}", imm)
+        }
+        (1, 3) if imm6 != 0 => format!("c.lui {}, {:#x}", reg(rd), (imm6 as u32) & 0xfffff),
+        (1, 4) => {
+            let d = reg(rs1_p);
+            match field(11, 10) {
+                0 if bit(12) == 0 => format!("c.srli {}, {}", d, field(6, 2)),
+                1 if bit(12) == 0 => format!("c.srai {}, {}", d, field(6, 2)),
+                2 => format!("c.andi {}, {}", d, imm6),
+                3 if bit(12) == 0 => {
+                    let op = ["c.sub", "c.xor", "c.or", "c.and"][field(6, 5) as usize];
+                    format!("{} {}, {}", op, d, reg(rd_p))
+                }
+                _ => return None,
+            }
+        }
+        (1, 5) => format!("c.j {}", target(pc, j_off())),
+        (1, 6 | 7) => {
+            let off = sext(
+                (bit(12) << 8)
+                    | (field(11, 10) << 3)
+                    | (field(6, 5) << 6)
+                    | (field(4, 3) << 1)
+                    | (bit(2) << 5),
+                9,
+            );
+            let op = if funct3 == 6 { "c.beqz" } else { "c.bnez" };
+            format!("{} {}, {}", op, reg(rs1_p), target(pc, off))
+        }
+        (2, 0) if bit(12) == 0 && rd != 0 => format!("c.slli {}, {}", reg(rd), field(6, 2)),
+        (2, 1) if isa.d => {
+            let off = (bit(12) << 5) | (field(6, 5) << 3) | (field(4, 2) << 6);
+            format!("c.fldsp {}, {}(sp)", freg(rd), off)
+        }
+        (2, 2 | 3) => {
+            let off = (bit(12) << 5) | (field(6, 4) << 2) | (field(3, 2) << 6);
+            match funct3 {
+                2 if rd != 0 => format!("c.lwsp {}, {}(sp)", reg(rd), off),
+                3 if isa.f => format!("c.flwsp {}, {}(sp)", freg(rd), off),
+                _ => return None,
+            }
+        }
+        (2, 4) => match (bit(12), rd, rs2) {
+            (0, 0, _) => return None,
+            (0, _, 0) => format!("c.jr {}", reg(rd)),
+            (0, _, _) => format!("c.mv {}, {}", reg(rd), reg(rs2)),
+            (_, 0, 0) => "c.ebreak".into(),
+            (_, _, 0) => format!("c.jalr {}", reg(rd)),
+            _ => format!("c.add {}, {}", reg(rd), reg(rs2)),
+        },
+        (2, 5) if isa.d => {
+            let off = (field(12, 10) << 3) | (field(9, 7) << 6);
+            format!("c.fsdsp {}, {}(sp)", freg(rs2), off)
+        }
+        (2, 6 | 7) => {
+            let off = (field(12, 9) << 2) | (field(8, 7) << 6);
+            match funct3 {
+                6 => format!("c.swsp {}, {}(sp)", reg(rs2), off),
+                _ if isa.f => format!("c.fswsp {}, {}(sp)", freg(rs2), off),
+                _ => return None,
+            }
+        }
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PC: u64 = 0x8000_0000;
+
+    /// Disassemble each `(word, text)` at [`PC`] with the ISA string `isa`.
+    fn check(isa: &str, cases: &[(u32, &str)]) {
+        let isa = Isa::parse(isa).unwrap();
+        for &(word, text) in cases {
+            assert_eq!(isa.disasm(PC, word), text, "{:#010x}", word);
+        }
+    }
+
+    #[test]
+    fn base_formats() {
+        check(
+            "rv32i",
+            &[
+                // R
+                (0x00c5_8533, "add a0, a1, a2"),
+                (0x4054_8433, "sub s0, s1, t0"),
+                (0x40b0_0533, "neg a0, a1"),
+                // I
+                (0xff01_0113, "addi sp, sp, -16"),
+                (0x0050_0513, "li a0, 5"),
+                (0x00c1_2783, "lw a5, 12(sp)"),
+                (0xfff5_c503, "lbu a0, -1(a1)"),
+                (0x4036_5593, "srai a1, a2, 3"),
+                (0x0000_8067, "ret"),
+                (0x0045_82e7, "jalr t0, 4(a1)"),
+                // S
+                (0x0081_2423, "sw s0, 8(sp)"),
+                (0xfea5_8e23, "sb a0, -4(a1)"),
+                // B
+                (0x00b5_0863, "beq a0, a1, 0x80000010"),
+                (0xfe05_1ce3, "bnez a0, 0x7ffffff8"),
+                (0x1062_e063, "bltu t0, t1, 0x80000100"),
+                // U
+                (0x8000_0537, "lui a0, 0x80000"),
+                (0x0000_1317, "auipc t1, 0x1"),
+                // J
+                (0x0010_00ef, "jal 0x80000800"),
+                (0xffdf_f06f, "j 0x7ffffffc"),
+                (0x0080_056f, "jal a0, 0x80000008"),
+            ],
+        );
+    }
+
+    #[test]
+    fn system() {
+        check(
+            "rv32i",
+            &[
+                (0x3055_12f3, "csrrw t0, mtvec, a0"),
+                (0xb000_2573, "csrr a0, mcycle"),
+                (0x3004_7073, "csrrci zero, mstatus, 8"),
+                (0x7c05_15f3, "csrrw a1, 0x7c0, a0"),
+                (0x0000_0073, "ecall"),
+                (0x3020_0073, "mret"),
+                (0x0ff0_000f, "fence"),
+                (0x0000_100f, "fence.i"),
+            ],
+        );
+    }
+
+    #[test]
+    fn mul_div() {
+        let cases = [
+            (0x02c5_8533, "mul a0, a1, a2"),
+            (0x0273_52b3, "divu t0, t1, t2"),
+            (0x02b5_7533, "remu a0, a0, a1"),
+        ];
+        check("rv32im", &cases);
+        let rv32i = Isa::parse("rv32i").unwrap();
+        for (w, _) in cases {
+            assert_eq!(rv32i.disasm(PC, w), format!(".word {:#010x}", w));
+        }
+    }
+
+    #[test]
+    fn wjcus0() {
+        let cases = [
+            (0x0000_100b, "nn_start"),
+            (0x00b5_000b, "nn_load_act a0, a1"),
+            (0x0005_260b, "nn_load a2, a0"),
+        ];
+        check("rv32i_wjcus0", &cases);
+        check("rv32i", &[(0x0000_100b, ".word 0x0000100b")]);
+        check("rv32i_wjcus0", &[(0x0000_300b, ".word 0x0000300b")]);
+    }
+
+    #[test]
+    fn compressed_halves() {
+        check(
+            "rv32imc",
+            &[
+                (0x1141, "c.addi sp, -16"),
+                (0x4515, "c.li a0, 5"),
+                (0x557d, "c.li a0, -1"),
+                (0x40c8, "c.lw a0, 4(s1)"),
+                (0xc11c, "c.sw a5, 0(a0)"),
+                (0x47b2, "c.lwsp a5, 12(sp)"),
+                (0xc422, "c.swsp s0, 8(sp)"),
+                (0x852e, "c.mv a0, a1"),
+                (0x8c05, "c.sub s0, s1"),
+                (0x8082, "c.jr ra"),
+                (0x9002, "c.ebreak"),
+                (0xfd65, "c.bnez a0, 0x7ffffff8"),
+                (0xc019, "c.beqz s0, 0x80000006"),
+                (0xbff5, "c.j 0x7ffffffc"),
+                (0x2005, "c.jal 0x80000020"),
+                // Only the low half is decoded.
+                (0x1234_1141, "c.addi sp, -16"),
+            ],
+        );
+        // Without C a compressed half is not an instruction.
+        check("rv32im", &[(0x1141, ".word 0x00001141")]);
+    }
+
+    #[test]
+    fn illegal_words() {
+        check(
+            "rv32imc_wjcus0",
+            &[
+                // All-zero half: c.addi4spn with a zero immediate is reserved.
+                (0x0000_0000, ".word 0x00000000"),
+                (0xffff_ffff, ".word 0xffffffff"),
+                // Branch funct3 2, load funct3 3 and an unknown SYSTEM word.
+                (0x00b5_2863, ".word 0x00b52863"),
+                (0x0005_3503, ".word 0x00053503"),
+                (0x0020_0073, ".word 0x00200073"),
+                // OP with a funct7 outside RV32IM.
+                (0x10c5_8533, ".word 0x10c58533"),
+            ],
+        );
+    }
+}
@@ -0,0 +1,149 @@
+//! Pass results as typed records: every finding is an [`Event`] handed to a [`super::Sink`].
+
+use std::fmt;
+
+use wellen::Time;
+
+use super::cycle::Sample;
+use super::value::Value;
+
+/// One field value of an [`Event`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Field {
+    /// Signal missing from the waveform.
+    Null,
+    Bool(bool),
+    Num(u64),
+    /// Number usually read in hex (PCs, instructions, addresses).
+    Hex(u64),
+    /// Ratio or average (IPC, mean occupancy).
+    Float(f64),
+    /// Free text, or a signal value that is X/Z or wider than 64 bits (as a bit string).
+    Text(String),
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Field::Null => f.write_str("?"),
+            Field::Bool(b) => write!(f, "{}", b),
+            Field::Num(n) => write!(f, "{}", n),
+            Field::Hex(n) => write!(f, "0x{:08x}", n),
+            Field::Float(x) => write!(f, "{:.3}", x),
+            Field::Text(s) => f.write_str(s),
+        }
+    }
+}
+
+impl From<bool> for Field {
+    fn from(b: bool) -> Self {
+        Field::Bool(b)
+    }
+}
+
+impl From<u64> for Field {
+    fn from(n: u64) -> Self {
+        Field::Num(n)
+    }
+}
+
+impl From<u32> for Field {
+    fn from(n: u32) -> Self {
+        Field::Num(n.into())
+    }
+}
+
+impl From<usize> for Field {
+    fn from(n: usize) -> Self {
+        Field::Num(n as u64)
+    }
+}
+
+impl From<f64> for Field {
+    fn from(x: f64) -> Self {
+        Field::Float(x)
+    }
+}
+
+impl From<&str> for Field {
+    fn from(s: &str) -> Self {
+        Field::Text(s.to_string())
+    }
+}
+
+impl From<String> for Field {
+    fn from(s: String) -> Self {
+        Field::Text(s)
+    }
+}
+
+impl<T: Into<Field>> From<Option<T>> for Field {
+    fn from(v: Option<T>) -> Self {
+        v.map_or(Field::Null, Into::into)
+    }
+}
+
+impl From<&Value> for Field {
+    fn from(v: &Value) -> Self {
+        match v.as_u64() {
+            Some(n) => Field::Num(n),
+            None => Field::Text(v.to_string()),
+        }
+    }
+}
+
+impl Field {
+    /// `v` as [`Field::Hex`] when fully known, else its bit string.
+    pub fn hex(v: Option<&Value>) -> Self {
+        match v.map(|v| (v, v.as_u64())) {
+            Some((_, Some(n))) => Field::Hex(n),
+            Some((v, None)) => Field::Text(v.to_string()),
+            None => Field::Null,
+        }
+    }
+}
+
+/// One result record: where (time, cycle), what (kind) and the details (fields).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Event {
+    /// Sample time in timescale units; `None` for results not tied to a time (e.g. `list`).
+    pub time: Option<Time>,
+    pub cycle: Option<u64>,
+    /// Upper-case record type, e.g. `ROB_ENQ` or `PRF_IQ_MISMATCH`.
+    pub kind: &'static str,
+    pub fields: Vec<(String, Field)>,
+}
+
+impl Event {
+    pub fn new(kind: &'static str) -> Self {
+        Self {
+            time: None,
+            cycle: None,
+            kind,
+            fields: Vec::new(),
+        }
+    }
+
+    /// Event of `kind` at sample time `time`.
+    pub fn at(time: Time, kind: &'static str) -> Self {
+        Self {
+            time: Some(time),
+            ..Self::new(kind)
+        }
+    }
+
+    /// Event of `kind` at a sample, with its cycle when known.
+    pub fn on(sample: &Sample, kind: &'static str) -> Self {
+        Self {
+            time: Some(sample.time),
+            cycle: sample.cycle,
+            ..Self::new(kind)
+        }
+    }
+
+    /// Append a field.
+    pub fn with(mut self, name: impl Into<String>, value: impl Into<Field>) -> Self {
+        self.fields.push((name.into(), value.into()));
+        self
+    }
+}
@@ -0,0 +1,645 @@
+//! Per-cycle predicate expressions for `scan --where`, e.g.
+//! `rob.do_flush == 1 && iq.count >= 3 && rob.commit.rd_index == x2`.
+//!
+//! Grammar, loosest binding first (Rust precedence):
+//!
+//! ```text
+//! expr := expr '||' expr | expr '&&' expr
+//!       | sum ('==' | '!=' | '<' | '<=' | '>' | '>=') sum
+//! sum  := sum ('|' | '^' | '&' | '+' | '-') sum          (`|` < `^` < `&` < `+ -`)
+//!       | '!' sum | atom ('[' bit ']' | '[' hi ':' lo ']')*
+//! atom := number | signal | func '(' expr ')' | '(' expr ')'
+//! func := prev | rose | fell | changed
+//! ```
+//!
+//! Numbers use [`NumArg`](super::NumArg) notation (`37`, `0x25`, `0b100101`, `x2`,
+//! `sp`, `PR37`); anything else is a signal name, looked up through the schema or the
+//! hierarchy. Values are at most 64 bits wide; X/Z makes a value unknown, and a
+//! condition that is unknown does not match.
+
+use std::fmt;
+
+use super::num::NumArg;
+use super::query::Snapshot;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum BinOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Add,
+    Sub,
+}
+
+impl BinOp {
+    /// Binding strength; higher binds tighter.
+    fn prec(self) -> u8 {
+        match self {
+            BinOp::Or => 1,
+            BinOp::And => 2,
+            BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => 3,
+            BinOp::BitOr => 4,
+            BinOp::BitXor => 5,
+            BinOp::BitAnd => 6,
+            BinOp::Add | BinOp::Sub => 7,
+        }
+    }
+}
+
+/// History functions: value in the previous cycle, or edges between it and this one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Func {
+    Prev,
+    Rose,
+    Fell,
+    Changed,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Num(u64),
+    Sig(String),
+    /// Bits `hi..=lo` of the operand.
+    Slice(Box<Node>, u32, u32),
+    Not(Box<Node>),
+    Bin(BinOp, Box<Node>, Box<Node>),
+    Call(Func, Box<Node>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Tok {
+    Num(u64),
+    Name(String),
+    Op(&'static str),
+}
+
+/// Two-character operators first so `<=` is not read as `<`.
+const OPS: &[&str] = &[
+    "||", "&&", "==", "!=", "<=", ">=", "<", ">", "|", "^", "&", "+", "-", "!", "(", ")", "[", "]",
+    ":", ",",
+];
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '$'
+}
+
+/// Split into tokens with their byte offsets. A name keeps `[N]` groups that are
+/// directly attached to it (`iq.entry[3].p_rd`, `rmt.map[1]`); [`Expr::bind`] turns a
+/// trailing one back into a bit select when the name is not a signal.
+fn lex(src: &str) -> Result<Vec<(usize, Tok)>, String> {
+    let mut toks = Vec::new();
+    let mut i = 0;
+    while i < src.len() {
+        let rest = &src[i..];
+        let c = rest.chars().next().unwrap_or(' ');
+        if c.is_whitespace() {
+            i += c.len_utf8();
+            continue;
+        }
+        if is_name_char(c) {
+            let mut end = 0;
+            loop {
+                end += rest[end..]
+                    .find(|c: char| !is_name_char(c))
+                    .unwrap_or(rest.len() - end);
+                // `[digits]` glued to the name is part of it.
+                let tail = &rest[end..];
+                let Some(close) = tail.strip_prefix('[').and_then(|t| t.find(']')) else {
+                    break;
+                };
+                let inner = &tail[1..close + 1];
+                if inner.is_empty() || !inner.bytes().all(|b| b.is_ascii_digit()) {
+                    break;
+                }
+                end += close + 2;
+                if !rest[end..].starts_with(is_name_char) {
+                    break;
+                }
+            }
+            let word = &rest[..end];
+            let tok = if word.starts_with(|c: char| c.is_ascii_digit()) {
+                Tok::Num(word.parse::<NumArg>()?.get())
+            } else if !word.contains(['.', '[']) && word.parse::<NumArg>().is_ok() {
+                // Register / PR literals: x2, sp, PR37.
+                Tok::Num(word.parse::<NumArg>()?.get())
+            } else {
+                Tok::Name(word.to_string())
+            };
+            toks.push((i, tok));
+            i += end;
+            continue;
+        }
+        match OPS.iter().find(|op| rest.starts_with(**op)) {
+            Some(op) => {
+                toks.push((i, Tok::Op(op)));
+                i += op.len();
+            }
+            None => return Err(format!("unexpected `{}` at column {}", c, i + 1)),
+        }
+    }
+    Ok(toks)
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    toks: Vec<(usize, Tok)>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Tok> {
+        self.toks.get(self.pos).map(|(_, t)| t)
+    }
+
+    fn err(&self, what: &str) -> String {
+        match self.toks.get(self.pos) {
+            Some((col, _)) => format!("{} at column {} of `{}`", what, col + 1, self.src),
+            None => format!("{} at end of `{}`", what, self.src),
+        }
+    }
+
+    fn eat(&mut self, op: &str) -> bool {
+        let hit = matches!(self.peek(), Some(Tok::Op(o)) if *o == op);
+        if hit {
+            self.pos += 1;
+        }
+        hit
+    }
+
+    fn expect(&mut self, op: &str) -> Result<(), String> {
+        if self.eat(op) {
+            Ok(())
+        } else {
+            Err(self.err(&format!("expected `{}`", op)))
+        }
+    }
+
+    fn bin_op(&self) -> Option<BinOp> {
+        let Some(Tok::Op(op)) = self.peek() else {
+            return None;
+        };
+        Some(match *op {
+            "||" => BinOp::Or,
+            "&&" => BinOp::And,
+            "==" => BinOp::Eq,
+            "!=" => BinOp::Ne,
+            "<" => BinOp::Lt,
+            "<=" => BinOp::Le,
+            ">" => BinOp::Gt,
+            ">=" => BinOp::Ge,
+            "|" => BinOp::BitOr,
+            "^" => BinOp::BitXor,
+            "&" => BinOp::BitAnd,
+            "+" => BinOp::Add,
+            "-" => BinOp::Sub,
+            _ => return None,
+        })
+    }
+
+    /// Precedence climbing over [`BinOp::prec`]; comparisons do not chain.
+    fn expr(&mut self, min_prec: u8) -> Result<Node, String> {
+        let mut lhs = self.unary()?;
+        while let Some(op) = self.bin_op() {
+            if op.prec() < min_prec {
+                break;
+            }
+            self.pos += 1;
+            let rhs = self.expr(op.prec() + 1)?;
+            if op.prec() == 3 && self.bin_op().is_some_and(|next| next.prec() == 3) {
+                return Err(self.err("comparisons cannot be chained; use `&&`"));
+            }
+            lhs = Node::Bin(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Node, String> {
+        if self.eat("!") {
+            return Ok(Node::Not(Box::new(self.unary()?)));
+        }
+        let mut node = self.atom()?;
+        while self.eat("[") {
+            let hi = self.index()?;
+            let lo = if self.eat(":") { self.index()? } else { hi };
+            self.expect("]")?;
+            if lo > hi || hi - lo >= 64 {
+                return Err(self.err(&format!(
+                    "bad slice [{}:{}] (hi >= lo, at most 64 bits)",
+                    hi, lo
+                )));
+            }
+            node = Node::Slice(Box::new(node), hi, lo);
+        }
+        Ok(node)
+    }
+
+    fn index(&mut self) -> Result<u32, String> {
+        match self.peek() {
+            Some(Tok::Num(n)) if *n < u32::MAX as u64 => {
+                let n = *n as u32;
+                self.pos += 1;
+                Ok(n)
+            }
+            _ => Err(self.err("expected a bit index")),
+        }
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        let tok = self.peek().cloned();
+        match tok {
+            Some(Tok::Num(n)) => {
+                self.pos += 1;
+                Ok(Node::Num(n))
+            }
+            Some(Tok::Name(name)) => {
+                self.pos += 1;
+                if !self.eat("(") {
+                    return Ok(Node::Sig(name));
+                }
+                let func = match name.as_str() {
+                    "prev" => Func::Prev,
+                    "rose" => Func::Rose,
+                    "fell" => Func::Fell,
+                    "changed" => Func::Changed,
+                    _ => {
+                        self.pos -= 2;
+                        return Err(self.err(&format!(
+                            "unknown function `{}` (prev, rose, fell, changed)",
+                            name
+                        )));
+                    }
+                };
+                let arg = self.expr(0)?;
+                self.expect(")")?;
+                Ok(Node::Call(func, Box::new(arg)))
+            }
+            Some(Tok::Op("(")) => {
+                self.pos += 1;
+                let e = self.expr(0)?;
+                self.expect(")")?;
+                Ok(e)
+            }
+            _ => Err(self.err("expected a number, signal, function or `(`")),
+        }
+    }
+}
+
+/// Parsed predicate; call [`bind`](Self::bind) before evaluating.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expr {
+    src: String,
+    root: Node,
+}
+
+impl std::str::FromStr for Expr {
+    type Err = String;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        let mut p = Parser {
+            src,
+            toks: lex(src)?,
+            pos: 0,
+        };
+        let root = p.expr(0)?;
+        if p.pos < p.toks.len() {
+            return Err(p.err("unexpected token"));
+        }
+        Ok(Self {
+            src: src.to_string(),
+            root,
+        })
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.src)
+    }
+}
+
+/// Bits `hi..=lo` of a known value.
+fn slice(v: u64, hi: u32, lo: u32) -> u64 {
+    let v = if lo >= 64 { 0 } else { v >> lo };
+    let w = hi - lo + 1;
+    if w >= 64 { v } else { v & ((1u64 << w) - 1) }
+}
+
+fn truth(b: bool) -> Option<u64> {
+    Some(b as u64)
+}
+
+impl Expr {
+    /// Check every signal name with `find`, which returns whether the signal exists
+    /// (and typically loads it). A name ending in `[N]` that is not a signal itself is
+    /// read as bit N of the name before it. Returns the signal names, in order of first use.
+    pub fn bind<E: From<String>>(
+        &mut self,
+        mut find: impl FnMut(&str) -> Result<bool, E>,
+    ) -> Result<Vec<String>, E> {
+        let mut names = Vec::new();
+        bind_node(&mut self.root, &mut find, &mut names)?;
+        Ok(names)
+    }
+
+    /// Value in the current cycle; `prev` is the snapshot of the cycle before, if any.
+    pub fn eval(&self, cur: &Snapshot, prev: Option<&Snapshot>) -> Option<u64> {
+        eval(&self.root, cur, prev)
+    }
+
+    /// Known and nonzero.
+    pub fn matches(&self, cur: &Snapshot, prev: Option<&Snapshot>) -> bool {
+        self.eval(cur, prev).is_some_and(|v| v != 0)
+    }
+}
+
+fn bind_node<E: From<String>>(
+    node: &mut Node,
+    find: &mut impl FnMut(&str) -> Result<bool, E>,
+    names: &mut Vec<String>,
+) -> Result<(), E> {
+    match node {
+        Node::Num(_) => Ok(()),
+        Node::Sig(name) => {
+            if find(name)? {
+                if !names.contains(name) {
+                    names.push(name.clone());
+                }
+                return Ok(());
+            }
+            let bit = name.strip_suffix(']').and_then(|n| n.rsplit_once('['));
+            if let Some((base, Ok(b))) = bit.map(|(base, b)| (base, b.parse::<u32>())) {
+                let mut inner = Node::Sig(base.to_string());
+                bind_node(&mut inner, find, names)?;
+                *node = Node::Slice(Box::new(inner), b, b);
+                return Ok(());
+            }
+            Err(E::from(format!("Signal `{}` not found", name)))
+        }
+        Node::Slice(n, _, _) | Node::Not(n) | Node::Call(_, n) => bind_node(n, find, names),
+        Node::Bin(_, a, b) => {
+            bind_node(a, find, names)?;
+            bind_node(b, find, names)
+        }
+    }
+}
+
+fn eval(node: &Node, cur: &Snapshot, prev: Option<&Snapshot>) -> Option<u64> {
+    match node {
+        Node::Num(n) => Some(*n),
+        Node::Sig(name) => cur.u64(name),
+        Node::Slice(inner, hi, lo) => match &**inner {
+            // Slice the signal itself so bits of wide or partly unknown values still work.
+            Node::Sig(name) => {
+                let v = cur.get(name)?;
+                (*lo..=*hi).rev().try_fold(0u64, |acc, i| {
+                    let bit = if i < v.width() { v.bit(i)? } else { false };
+                    Some(acc << 1 | bit as u64)
+                })
+            }
+            other => eval(other, cur, prev).map(|v| slice(v, *hi, *lo)),
+        },
+        Node::Not(n) => eval(n, cur, prev).map(|v| (v == 0) as u64),
+        Node::Bin(BinOp::And, a, b) => match (eval(a, cur, prev), eval(b, cur, prev)) {
+            (Some(0), _) | (_, Some(0)) => truth(false),
+            (Some(_), Some(_)) => truth(true),
+            _ => None,
+        },
+        Node::Bin(BinOp::Or, a, b) => match (eval(a, cur, prev), eval(b, cur, prev)) {
+            (Some(x), _) | (_, Some(x)) if x != 0 => truth(true),
+            (Some(_), Some(_)) => truth(false),
+            _ => None,
+        },
+        Node::Bin(op, a, b) => {
+            let (x, y) = (eval(a, cur, prev)?, eval(b, cur, prev)?);
+            match op {
+                BinOp::Eq => truth(x == y),
+                BinOp::Ne => truth(x != y),
+                BinOp::Lt => truth(x < y),
+                BinOp::Le => truth(x <= y),
+                BinOp::Gt => truth(x > y),
+                BinOp::Ge => truth(x >= y),
+                BinOp::BitOr => Some(x | y),
+                BinOp::BitXor => Some(x ^ y),
+                BinOp::BitAnd => Some(x & y),
+                BinOp::Add => Some(x.wrapping_add(y)),
+                BinOp::Sub => Some(x.wrapping_sub(y)),
+                BinOp::And | BinOp::Or => unreachable!("handled above"),
+            }
+        }
+        Node::Call(func, n) => {
+            // One cycle of history: `prev()` inside `prev()` is unknown.
+            let before = eval(n, prev?, None);
+            let now = eval(n, cur, prev);
+            match func {
+                Func::Prev => before,
+                Func::Rose => truth(before? == 0 && now? != 0),
+                Func::Fell => truth(before? != 0 && now? == 0),
+                Func::Changed => truth(before != now),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::value::Value;
+
+    fn parse(src: &str) -> Node {
+        src.parse::<Expr>().unwrap().root
+    }
+
+    fn num(n: u64) -> Box<Node> {
+        Box::new(Node::Num(n))
+    }
+
+    fn sig(name: &str) -> Box<Node> {
+        Box::new(Node::Sig(name.to_string()))
+    }
+
+    fn snap(vals: &[(&str, &str)]) -> Snapshot {
+        vals.iter()
+            .map(|&(n, bits)| (n, Value::from_bit_string(bits)))
+            .collect()
+    }
+
+    /// Bind against `cur` and evaluate.
+    fn eval_in(src: &str, cur: &Snapshot, prev: Option<&Snapshot>) -> Option<u64> {
+        let mut e: Expr = src.parse().unwrap();
+        e.bind(|n| Ok::<_, String>(cur.get(n).is_some())).unwrap();
+        e.eval(cur, prev)
+    }
+
+    #[test]
+    fn precedence() {
+        use BinOp::*;
+        assert_eq!(
+            parse("1 + 2 & 3 ^ 4 | 5"),
+            Node::Bin(
+                BitOr,
+                Box::new(Node::Bin(
+                    BitXor,
+                    Box::new(Node::Bin(
+                        BitAnd,
+                        Box::new(Node::Bin(Add, num(1), num(2))),
+                        num(3)
+                    )),
+                    num(4),
+                )),
+                num(5),
+            )
+        );
+        assert_eq!(
+            parse("a == 1 || b < 2 && c"),
+            Node::Bin(
+                Or,
+                Box::new(Node::Bin(Eq, sig("a"), num(1))),
+                Box::new(Node::Bin(
+                    And,
+                    Box::new(Node::Bin(Lt, sig("b"), num(2))),
+                    sig("c")
+                )),
+            )
+        );
+        // Left associative.
+        assert_eq!(
+            parse("5 - 2 - 1"),
+            Node::Bin(Sub, Box::new(Node::Bin(Sub, num(5), num(2))), num(1))
+        );
+        let empty = Snapshot::default();
+        assert_eq!(eval_in("5 - 2 - 1", &empty, None), Some(2));
+        assert_eq!(eval_in("2 + 3 & 6", &empty, None), Some(4));
+        assert_eq!(eval_in("!0 + 1", &empty, None), Some(2));
+    }
+
+    #[test]
+    fn parentheses() {
+        assert_eq!(
+            parse("(1 | 2) & 3"),
+            Node::Bin(
+                BinOp::BitAnd,
+                Box::new(Node::Bin(BinOp::BitOr, num(1), num(2))),
+                num(3)
+            )
+        );
+        let empty = Snapshot::default();
+        assert_eq!(eval_in("(1 | 2) & 2", &empty, None), Some(2));
+        assert_eq!(eval_in("1 | 2 & 2", &empty, None), Some(3));
+        assert_eq!(eval_in("!(1 == 2)", &empty, None), Some(1));
+    }
+
+    #[test]
+    fn bit_slices() {
+        let cur = snap(&[
+            ("rob.head", "10110100"),
+            ("iq.entry[3].p_rd", "100101"),
+            ("v", "1x01"),
+        ]);
+        assert_eq!(eval_in("rob.head[7:4]", &cur, None), Some(0b1011));
+        assert_eq!(eval_in("rob.head[2]", &cur, None), Some(1));
+        assert_eq!(eval_in("rob.head[1:0] == 0", &cur, None), Some(1));
+        // Bits above the width read as zero.
+        assert_eq!(eval_in("rob.head[9:6]", &cur, None), Some(0b10));
+        // `[3]` glued to a name is part of it; a trailing one is a bit select.
+        assert_eq!(eval_in("iq.entry[3].p_rd", &cur, None), Some(37));
+        assert_eq!(eval_in("rob.head[5]", &cur, None), Some(1));
+        // Slicing an expression.
+        assert_eq!(eval_in("(rob.head + 1)[1:0]", &cur, None), Some(1));
+        // Known bits of a partly unknown signal.
+        assert_eq!(eval_in("v[1:0]", &cur, None), Some(1));
+        assert_eq!(eval_in("v[2]", &cur, None), None);
+    }
+
+    #[test]
+    fn history() {
+        let prev = snap(&[("v", "0"), ("n", "0011")]);
+        let cur = snap(&[("v", "1"), ("n", "0011")]);
+        assert_eq!(eval_in("rose(v)", &cur, Some(&prev)), Some(1));
+        assert_eq!(eval_in("fell(v)", &cur, Some(&prev)), Some(0));
+        assert_eq!(eval_in("fell(v)", &prev, Some(&cur)), Some(1));
+        assert_eq!(eval_in("changed(v)", &cur, Some(&prev)), Some(1));
+        assert_eq!(eval_in("changed(n)", &cur, Some(&prev)), Some(0));
+        assert_eq!(eval_in("prev(n) + 1", &cur, Some(&prev)), Some(4));
+        assert_eq!(eval_in("prev(v) == 0 && v", &cur, Some(&prev)), Some(1));
+        // No cycle before the first, and only one cycle of history.
+        assert_eq!(eval_in("rose(v)", &cur, None), None);
+        assert_eq!(eval_in("prev(prev(n))", &cur, Some(&prev)), None);
+    }
+
+    #[test]
+    fn register_literals() {
+        assert_eq!(parse("x2"), Node::Num(2));
+        assert_eq!(parse("sp"), Node::Num(2));
+        assert_eq!(parse("a0"), Node::Num(10));
+        assert_eq!(parse("PR37"), Node::Num(37));
+        assert_eq!(parse("0x25"), Node::Num(37));
+        assert_eq!(parse("0b100101"), Node::Num(37));
+        // Dotted and indexed words are always signals.
+        assert_eq!(parse("rob.sp"), Node::Sig("rob.sp".to_string()));
+        assert_eq!(parse("x2[1]"), Node::Sig("x2[1]".to_string()));
+        let cur = snap(&[("rob.commit.rd_index", "00010"), ("p_rd", "100101")]);
+        assert_eq!(eval_in("rob.commit.rd_index == sp", &cur, None), Some(1));
+        assert_eq!(eval_in("p_rd == PR37", &cur, None), Some(1));
+    }
+
+    #[test]
+    fn unknown_operands() {
+        let cur = snap(&[("x", "x"), ("z", "zz"), ("one", "1"), ("zero", "0")]);
+        assert_eq!(eval_in("x", &cur, None), None);
+        assert_eq!(eval_in("z == 0", &cur, None), None);
+        assert_eq!(eval_in("x + 1", &cur, None), None);
+        assert_eq!(eval_in("!x", &cur, None), None);
+        // `&&` / `||` are decided by a known side.
+        assert_eq!(eval_in("zero && x", &cur, None), Some(0));
+        assert_eq!(eval_in("x || one", &cur, None), Some(1));
+        assert_eq!(eval_in("one && x", &cur, None), None);
+        assert_eq!(eval_in("x || zero", &cur, None), None);
+        let mut e: Expr = "x == 1".parse().unwrap();
+        e.bind(|n| Ok::<_, String>(cur.get(n).is_some())).unwrap();
+        assert!(!e.matches(&cur, None));
+        assert_eq!(eval_in("one || zero", &cur, None), Some(1));
+    }
+
+    #[test]
+    fn bind_names() {
+        let cur = snap(&[("a", "1"), ("b", "0")]);
+        let mut e: Expr = "a && (b || rose(a)) || b[0]".parse().unwrap();
+        let names = e.bind(|n| Ok::<_, String>(cur.get(n).is_some()));
+        assert_eq!(names, Ok(vec!["a".to_string(), "b".to_string()]));
+        let mut e: Expr = "a && missing".parse().unwrap();
+        let err = e.bind(|n| Ok::<_, String>(cur.get(n).is_some()));
+        assert_eq!(err, Err("Signal `missing` not found".to_string()));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let err = |src: &str| src.parse::<Expr>().unwrap_err();
+        assert_eq!(
+            err("a == 1 == b"),
+            "comparisons cannot be chained; use `&&` at column 8 of `a == 1 == b`"
+        );
+        assert_eq!(err("(a && b"), "expected `)` at end of `(a && b`");
+        assert_eq!(
+            err("a &&"),
+            "expected a number, signal, function or `(` at end of `a &&`"
+        );
+        assert_eq!(err("a b"), "unexpected token at column 3 of `a b`");
+        assert_eq!(err("a @ 1"), "unexpected `@` at column 3");
+        assert_eq!(
+            err("edge(a)"),
+            "unknown function `edge` (prev, rose, fell, changed) at column 1 of `edge(a)`"
+        );
+        assert!(err("a[3:5]").starts_with("bad slice [3:5]"));
+        assert!(err("a[70:0]").starts_with("bad slice [70:0]"));
+        assert_eq!(err("a[b]"), "expected a bit index at column 3 of `a[b]`");
+        assert!(err("0x").contains("0x"));
+    }
+}
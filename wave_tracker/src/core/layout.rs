@@ -0,0 +1,116 @@
+//! Structure sizes of the traced core (`nzea_config::CoreConfig`), discovered from the hierarchy.
+
+use std::fmt;
+
+use wellen::Hierarchy;
+
+/// Sizes the passes iterate over. Discovered with [`CoreLayout::detect`]; any field
+/// can be overridden from the command line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CoreLayout {
+    /// Issue queue entries (`iq.entries_N_*`, `iq.valids_N`); `issueQueueDepth`.
+    pub iq_entries: usize,
+    /// ROB slots (`rob.slots_*_N`); `robDepth`.
+    pub rob_slots: usize,
+    /// PRF write ports into the IQ (`iq.io_prf_write_N_*`).
+    pub prf_write_ports: usize,
+    /// Load/store queue slots (`memUnit.ls_slots_N_*`).
+    pub ls_slots: usize,
+    /// FreeList ring buffer slots (`freeList.buf_N`).
+    pub freelist_slots: usize,
+    /// Registers per PRF bank (`bank_ready_B_I`); `prfDepth` / banks.
+    pub prf_bank_size: usize,
+}
+
+impl Default for CoreLayout {
+    /// Upper bounds the passes used before detection existed.
+    fn default() -> Self {
+        Self {
+            iq_entries: 8,
+            rob_slots: 16,
+            prf_write_ports: 6,
+            ls_slots: 8,
+            freelist_slots: 11,
+            prf_bank_size: 16,
+        }
+    }
+}
+
+/// `N` in `<prefix>N<rest>` for the first occurrence of `prefix` in `name`.
+fn index_after(name: &str, prefix: &str) -> Option<usize> {
+    let rest = &name[name.find(prefix)? + prefix.len()..];
+    let end = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    rest[..end].parse().ok()
+}
+
+/// Trailing `_N` of `name`.
+fn trailing_index(name: &str) -> Option<usize> {
+    name.rsplit_once('_')?.1.parse().ok()
+}
+
+impl CoreLayout {
+    /// Count indexed signals in the hierarchy; sizes with no matching signal keep the default.
+    pub fn detect(hierarchy: &Hierarchy) -> Self {
+        let mut max: [Option<usize>; 6] = [None; 6];
+        let mut see = |slot: usize, idx: Option<usize>| {
+            if let Some(i) = idx {
+                max[slot] = Some(max[slot].map_or(i, |m| m.max(i)));
+            }
+        };
+        for var in hierarchy.iter_vars() {
+            let name = var.full_name(hierarchy);
+            see(0, index_after(&name, "iq.entries_"));
+            see(0, index_after(&name, "iq.valids_"));
+            if name.contains("rob.slots_") {
+                see(1, trailing_index(&name));
+            }
+            see(2, index_after(&name, "iq.io_prf_write_"));
+            see(3, index_after(&name, "memUnit.ls_slots_"));
+            if name.contains("freeList.buf_") {
+                see(4, trailing_index(&name));
+            }
+            if name.contains("bank_ready_") {
+                see(5, trailing_index(&name));
+            }
+        }
+        let d = Self::default();
+        let n = |m: Option<usize>, default: usize| m.map_or(default, |i| i + 1);
+        Self {
+            iq_entries: n(max[0], d.iq_entries),
+            rob_slots: n(max[1], d.rob_slots),
+            prf_write_ports: n(max[2], d.prf_write_ports),
+            ls_slots: n(max[3], d.ls_slots),
+            freelist_slots: n(max[4], d.freelist_slots),
+            prf_bank_size: n(max[5], d.prf_bank_size),
+        }
+    }
+
+    /// Physical registers behind the ring buffer FreeList: PR1..PR31 start in the RMT and
+    /// the buffer holds the rest (PR0 is x0).
+    pub fn ring_prs(&self) -> usize {
+        32 + self.freelist_slots
+    }
+
+    /// `(bank, index)` of physical register `pr`, as in `bank_ready_{bank}_{index}`.
+    pub fn prf_bank(&self, pr: u64) -> (u64, u64) {
+        let size = self.prf_bank_size as u64;
+        (pr / size, pr % size)
+    }
+}
+
+impl fmt::Display for CoreLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "iq={} rob={} prf_write={} ls={} freelist={} prf_bank={}",
+            self.iq_entries,
+            self.rob_slots,
+            self.prf_write_ports,
+            self.ls_slots,
+            self.freelist_slots,
+            self.prf_bank_size
+        )
+    }
+}
@@ -1,13 +1,30 @@
-//! Shared primitives: time indexing, paths, string helpers, waveform snapshots.
+//! Shared primitives: time indexing and the cycle axis, paths, core layout, signal schema, numeric arguments,
+//! predicate expressions, instruction disassembly, typed values and snapshots, result events and output sinks.
 
+pub mod cycle;
+pub mod disasm;
+pub mod event;
+pub mod expr;
+pub mod layout;
+pub mod num;
 pub mod paths;
 pub mod query;
 pub mod range;
-pub mod strings;
+pub mod schema;
+pub mod sink;
 pub mod time;
+pub mod value;
 
+pub use cycle::{ClockSpec, Sample, Timebase};
+pub use disasm::Isa;
+pub use event::{Event, Field};
+pub use expr::Expr;
+pub use layout::CoreLayout;
+pub use num::NumArg;
 pub use paths::default_wave_path;
-pub use query::{snapshot_at, val_by_substring};
+pub use query::{Snapshot, snapshot_at, value_at};
 pub use range::for_each_sample_in_range;
-pub use strings::{binary_to_hex_contains, pc_hex_to_binary};
-pub use time::find_time_idx_at_or_before;
+pub use schema::{Schema, SignalMap, in_scope};
+pub use sink::{Format, Sink, open_sink};
+pub use time::{TimeArg, find_time_idx_at_or_before};
+pub use value::{Value, show};
@@ -0,0 +1,155 @@
+//! Numeric CLI arguments: PR / rob_id / lsq_id / PC given in any common notation.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Integer register ABI names, indexed by register number.
+pub(crate) const ABI_NAMES: [&str; 32] = [
+    "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4",
+    "a5", "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4",
+    "t5", "t6",
+];
+
+/// Number given on the command line; compared against signals by value, so the
+/// width always comes from the waveform.
+///
+/// Accepts `37`, `0x25`, `0b100101`, `0o45`, `PR37` / `p37`, and integer register
+/// names `x2` / `sp` / `fp`. `_` separators are ignored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NumArg(pub u64);
+
+impl NumArg {
+    pub fn get(self) -> u64 {
+        self.0
+    }
+
+    /// Hex with an optional `0x`, for PCs and addresses such as `80005c9c`; register names
+    /// and the other prefixes are not recognised, so `a0` is 0xa0 and `0b80` is 0xb80.
+    pub fn parse_hex(s: &str) -> Result<Self, String> {
+        parse(s, Notation::Hex)
+    }
+}
+
+/// What a bare number and the prefixes mean.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Notation {
+    /// Decimal by default; `0x` / `0b` / `0o` / `PR` prefixes and register names.
+    Any,
+    /// Hex by default; only the `0x` prefix.
+    Hex,
+}
+
+impl Notation {
+    fn prefixes(self) -> &'static [(&'static str, u32)] {
+        match self {
+            Notation::Any => &[("0x", 16), ("0b", 2), ("0o", 8), ("pr", 10), ("p", 10)],
+            Notation::Hex => &[("0x", 16)],
+        }
+    }
+
+    fn default_radix(self) -> u32 {
+        match self {
+            Notation::Any => 10,
+            Notation::Hex => 16,
+        }
+    }
+}
+
+fn parse(s: &str, notation: Notation) -> Result<NumArg, String> {
+    let t: String = s.trim().chars().filter(|c| *c != '_').collect();
+    let lower = t.to_ascii_lowercase();
+    if notation == Notation::Any {
+        if let Some(i) = ABI_NAMES.iter().position(|n| *n == lower) {
+            return Ok(NumArg(i as u64));
+        }
+        if lower == "fp" {
+            return Ok(NumArg(8));
+        }
+        if let Some(r) = lower.strip_prefix('x') {
+            return match r.parse::<u64>() {
+                Ok(n) if n < 32 => Ok(NumArg(n)),
+                _ => Err(format!("invalid register `{}` (x0..x31)", s)),
+            };
+        }
+    }
+    let (digits, radix) = notation
+        .prefixes()
+        .iter()
+        .find_map(|&(p, radix)| Some((lower.strip_prefix(p)?, radix)))
+        .unwrap_or((&lower, notation.default_radix()));
+    u64::from_str_radix(digits, radix)
+        .map(NumArg)
+        .map_err(|e| format!("invalid number `{}`: {}", s, e))
+}
+
+impl FromStr for NumArg {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse(s, Notation::Any)
+    }
+}
+
+impl fmt::Display for NumArg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn num(s: &str) -> Result<u64, String> {
+        s.parse::<NumArg>().map(NumArg::get)
+    }
+
+    fn hex(s: &str) -> Result<u64, String> {
+        NumArg::parse_hex(s).map(NumArg::get)
+    }
+
+    #[test]
+    fn notations() {
+        assert_eq!(num("37"), Ok(37));
+        assert_eq!(num("0x25"), Ok(37));
+        assert_eq!(num("0b10_0101"), Ok(37));
+        assert_eq!(num("0o45"), Ok(37));
+        assert_eq!(num("PR37"), Ok(37));
+        assert_eq!(num("p37"), Ok(37));
+    }
+
+    #[test]
+    fn registers() {
+        assert_eq!(num("x2"), Ok(2));
+        assert_eq!(num("sp"), Ok(2));
+        assert_eq!(num("a0"), Ok(10));
+        assert_eq!(num("fp"), Ok(8));
+        assert!(num("x32").is_err());
+    }
+
+    #[test]
+    fn hex_is_only_hex() {
+        assert_eq!(hex("80000000"), Ok(0x8000_0000));
+        assert_eq!(hex("80005c9c"), Ok(0x8000_5c9c));
+        assert_eq!(hex("0x8000_5c9c"), Ok(0x8000_5c9c));
+        assert_eq!(hex("0X25"), Ok(0x25));
+        assert_eq!(hex("a0"), Ok(0xa0));
+        assert_eq!(hex("0b80"), Ok(0xb80));
+        assert!(hex("sp").is_err());
+        assert!(hex("x2").is_err());
+        assert!(hex("PR37").is_err());
+        assert!(hex("0o45").is_err());
+        assert!(hex("0x").is_err());
+    }
+
+    #[test]
+    fn overflow() {
+        assert_eq!(num("18446744073709551615"), Ok(u64::MAX));
+        assert!(num("18446744073709551616").is_err());
+        assert_eq!(hex("ffff_ffff_ffff_ffff"), Ok(u64::MAX));
+        assert!(hex("1_0000_0000_0000_0000").is_err());
+        assert!(num("0x1_0000_0000_0000_0000").is_err());
+        assert!(num(&format!("0b{}", "1".repeat(65))).is_err());
+        assert!(num("PR99999999999999999999").is_err());
+    }
+}
@@ -1,28 +1,74 @@
-//! Snapshot of signal values at one time sample; substring lookup by signal name.
+//! Snapshot of typed signal values at one time sample; lookup by signal name.
 
 use wellen::{SignalRef, simple::Waveform};
 
-/// All `(full_name, value_string)` at sample index `idx` for preloaded signals.
-pub fn snapshot_at(
-    wf: &Waveform,
-    sigs: &[(String, SignalRef)],
-    sample_idx: u32,
-) -> Vec<(String, String)> {
+use super::value::Value;
+
+/// Value of one preloaded signal at sample index `idx`.
+pub fn value_at(wf: &Waveform, sig_ref: SignalRef, sample_idx: u32) -> Option<Value> {
+    let sig = wf.get_signal(sig_ref)?;
+    let offset = sig.get_offset(sample_idx)?;
+    Some(Value::from_signal(&sig.get_value_at(&offset, 0)))
+}
+
+/// `(full_name, value)` for every preloaded signal at one sample.
+#[derive(Clone, Debug, Default)]
+pub struct Snapshot {
+    vals: Vec<(String, Value)>,
+}
+
+impl Snapshot {
+    /// Value of the signal named exactly `name` (a concrete logical name such as
+    /// `iq.entry[3].p_rd`); names are unique, so there is no ambiguity to resolve here.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.vals.iter().find(|(n, _)| n == name).map(|(_, v)| v)
+    }
+
+    /// Signal found and known nonzero; X/Z counts as low, as for every control bit the
+    /// passes branch on.
+    pub fn is_high(&self, name: &str) -> bool {
+        self.get(name).and_then(Value::as_bool) == Some(true)
+    }
+
+    /// Signal found and known zero; like [`is_high`](Self::is_high), false for X/Z, so a
+    /// missing or unknown ready does not count as backpressure.
+    pub fn is_low(&self, name: &str) -> bool {
+        self.get(name).and_then(Value::as_bool) == Some(false)
+    }
+
+    /// Signal found and fully known.
+    pub fn u64(&self, name: &str) -> Option<u64> {
+        self.get(name).and_then(Value::as_u64)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.vals.iter().map(|(n, v)| (n.as_str(), v))
+    }
+
+    pub fn len(&self) -> usize {
+        self.vals.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vals.is_empty()
+    }
+}
+
+impl<S: Into<String>> FromIterator<(S, Value)> for Snapshot {
+    fn from_iter<I: IntoIterator<Item = (S, Value)>>(iter: I) -> Self {
+        Snapshot {
+            vals: iter.into_iter().map(|(n, v)| (n.into(), v)).collect(),
+        }
+    }
+}
+
+/// All preloaded signals at sample index `idx`.
+pub fn snapshot_at(wf: &Waveform, sigs: &[(String, SignalRef)], sample_idx: u32) -> Snapshot {
     let mut vals = Vec::with_capacity(sigs.len());
     for (name, sig_ref) in sigs {
-        if let Some(sig) = wf.get_signal(*sig_ref)
-            && let Some(offset) = sig.get_offset(sample_idx)
-        {
-            let val = sig.get_value_at(&offset, 0).to_string();
+        if let Some(val) = value_at(wf, *sig_ref, sample_idx) {
             vals.push((name.clone(), val));
         }
     }
-    vals
-}
-
-/// First value whose signal full name contains `name_contains`.
-pub fn val_by_substring(vals: &[(String, String)], name_contains: &str) -> Option<String> {
-    vals.iter()
-        .find(|(n, _)| n.contains(name_contains))
-        .map(|(_, v)| v.clone())
+    Snapshot { vals }
 }
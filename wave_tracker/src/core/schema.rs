@@ -0,0 +1,605 @@
+//! Signal schema: logical signal names mapped to candidate RTL paths (`schema/nzea.toml`).
+//!
+//! Passes ask for logical names such as `rob.enq.valid` or `iq.entry[i].p_rd`; a
+//! [`SignalMap`] resolves them against the waveform hierarchy, so an RTL rename only
+//! needs a new candidate path in the schema.
+
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
+
+use serde::Deserialize;
+use wellen::{Hierarchy, SignalRef};
+
+/// Schema format version understood by this build.
+pub const SCHEMA_VERSION: u32 = 1;
+
+const BUILTIN: &str = include_str!("../../schema/nzea.toml");
+
+/// Logical name -> candidate path templates (highest priority first).
+#[derive(Debug, Deserialize)]
+pub struct Schema {
+    pub version: u32,
+    pub signals: BTreeMap<String, Vec<String>>,
+}
+
+impl Schema {
+    /// The schema shipped with the tool.
+    pub fn builtin() -> Self {
+        Self::parse(BUILTIN, "built-in schema").expect("built-in schema is valid")
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let text =
+            std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Self::parse(&text, &path.display().to_string())
+    }
+
+    /// Parse and check version, key placeholders and candidate templates.
+    fn parse(text: &str, origin: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let schema: Schema = toml::from_str(text).map_err(|e| format!("{}: {}", origin, e))?;
+        if schema.version != SCHEMA_VERSION {
+            return Err(format!(
+                "{}: schema version {} is not supported (expected {})",
+                origin, schema.version, SCHEMA_VERSION
+            )
+            .into());
+        }
+        for (key, candidates) in &schema.signals {
+            let vars = key_vars(key).map_err(|e| format!("{}: `{}`: {}", origin, key, e))?;
+            if candidates.is_empty() {
+                return Err(format!("{}: `{}` has no candidate paths", origin, key).into());
+            }
+            for c in candidates {
+                let t = Template::parse(c).map_err(|e| format!("{}: `{}`: {}", origin, c, e))?;
+                if t.vars() != vars {
+                    return Err(format!(
+                        "{}: `{}` must capture exactly the placeholders of `{}`",
+                        origin, c, key
+                    )
+                    .into());
+                }
+            }
+        }
+        Ok(schema)
+    }
+}
+
+/// Placeholder names of a logical key, e.g. `{b, i}` for `prf.bank[b].ready[i]`.
+fn key_vars(key: &str) -> Result<BTreeSet<&str>, String> {
+    let mut vars = BTreeSet::new();
+    let mut rest = key;
+    while let Some(open) = rest.find('[') {
+        let close = rest[open..]
+            .find(']')
+            .ok_or_else(|| "unclosed `[`".to_string())?;
+        let var = &rest[open + 1..open + close];
+        if var.is_empty() || !var.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_') {
+            return Err(format!("bad placeholder `[{}]`", var));
+        }
+        vars.insert(var);
+        rest = &rest[open + close + 1..];
+    }
+    Ok(vars)
+}
+
+/// Concrete logical name: every `[x]` in `key` replaced by `[<capture of x>]`.
+fn concrete_name(key: &str, caps: &[(&str, &str)]) -> String {
+    let mut out = String::with_capacity(key.len());
+    let mut rest = key;
+    while let Some(open) = rest.find('[') {
+        let close = open + rest[open..].find(']').unwrap_or(rest.len() - open);
+        let var = &rest[open + 1..close];
+        let val = caps
+            .iter()
+            .find(|(n, _)| *n == var)
+            .map_or(var, |(_, v)| *v);
+        out.push_str(&rest[..=open]);
+        out.push_str(val);
+        out.push(']');
+        rest = rest.get(close + 1..).unwrap_or("");
+    }
+    out.push_str(rest);
+    out
+}
+
+enum Part {
+    Lit(String),
+    /// `{x}`: one or more letters/digits.
+    Var(String),
+    /// `{x=v}`: matches nothing, binds `x` to `v`.
+    Fixed(String, String),
+}
+
+/// One candidate path, e.g. `iq.entries_{i}_p_rd`.
+struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    fn parse(s: &str) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut rest = s;
+        while let Some(open) = rest.find('{') {
+            if open > 0 {
+                parts.push(Part::Lit(rest[..open].to_string()));
+            }
+            let close = rest[open..]
+                .find('}')
+                .ok_or_else(|| "unclosed `{`".to_string())?;
+            let inner = &rest[open + 1..open + close];
+            let part = match inner.split_once('=') {
+                Some((name, val)) => Part::Fixed(name.to_string(), val.to_string()),
+                None => Part::Var(inner.to_string()),
+            };
+            if let Part::Var(name) | Part::Fixed(name, _) = &part
+                && name.is_empty()
+            {
+                return Err("empty placeholder `{}`".into());
+            }
+            if matches!(parts.last(), Some(Part::Var(_))) && matches!(part, Part::Var(_)) {
+                return Err("adjacent placeholders need a separator".into());
+            }
+            parts.push(part);
+            rest = &rest[open + close + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Lit(rest.to_string()));
+        }
+        if parts.is_empty() {
+            return Err("empty path".into());
+        }
+        Ok(Self { parts })
+    }
+
+    fn vars(&self) -> BTreeSet<&str> {
+        self.parts
+            .iter()
+            .filter_map(|p| match p {
+                Part::Var(n) | Part::Fixed(n, _) => Some(n.as_str()),
+                Part::Lit(_) => None,
+            })
+            .collect()
+    }
+
+    /// Captures if `full_name` ends with this template at a scope boundary.
+    fn match_suffix<'a>(&'a self, full_name: &'a str) -> Option<Vec<(&'a str, &'a str)>> {
+        if let Some(Part::Lit(last)) = self.parts.last()
+            && !full_name.ends_with(last.as_str())
+        {
+            return None;
+        }
+        let starts = std::iter::once(0).chain(
+            full_name
+                .bytes()
+                .enumerate()
+                .filter(|&(_, b)| b == b'.')
+                .map(|(i, _)| i + 1),
+        );
+        let mut caps = Vec::new();
+        for start in starts {
+            if match_parts(&self.parts, &full_name[start..], &mut caps) {
+                return Some(caps);
+            }
+            caps.clear();
+        }
+        None
+    }
+}
+
+fn match_parts<'a>(parts: &'a [Part], rest: &'a str, caps: &mut Vec<(&'a str, &'a str)>) -> bool {
+    let Some((part, tail)) = parts.split_first() else {
+        return rest.is_empty();
+    };
+    match part {
+        Part::Lit(lit) => rest
+            .strip_prefix(lit.as_str())
+            .is_some_and(|r| match_parts(tail, r, caps)),
+        Part::Fixed(name, val) => {
+            caps.push((name, val));
+            if match_parts(tail, rest, caps) {
+                return true;
+            }
+            caps.pop();
+            false
+        }
+        Part::Var(name) => {
+            let max = rest.bytes().take_while(u8::is_ascii_alphanumeric).count();
+            for len in 1..=max {
+                caps.push((name, &rest[..len]));
+                if match_parts(tail, &rest[len..], caps) {
+                    return true;
+                }
+                caps.pop();
+            }
+            false
+        }
+    }
+}
+
+/// Whether `full_name` lies under `scope` (a dotted hierarchy path such as `top.core`
+/// or `core.rob`), matched at scope boundaries.
+pub fn in_scope(full_name: &str, scope: &str) -> bool {
+    let scope = scope.trim_matches('.');
+    scope.is_empty()
+        || full_name
+            .strip_prefix(scope)
+            .is_some_and(|rest| rest.starts_with('.'))
+        || full_name.contains(&format!(".{}.", scope))
+}
+
+const AMBIGUOUS_HINT: &str =
+    "Qualify the match with --scope <hierarchy path> (e.g. --scope top.core)";
+
+/// Whether `name` is a concrete instance of schema key `key` (`iq.entry[3].p_rd` of
+/// `iq.entry[i].p_rd`).
+fn is_instance(key: &str, name: &str) -> bool {
+    let Ok(t) = Template::parse(&key.replace('[', "[{").replace(']', "}]")) else {
+        return false;
+    };
+    match_parts(&t.parts, name, &mut Vec::new())
+}
+
+/// Best match so far for one concrete logical name.
+struct Best<'a> {
+    rank: usize,
+    candidate: &'a str,
+    order: usize,
+    /// Every distinct signal matched by the winning candidate, as `(full name, signal)`.
+    matches: Vec<(String, SignalRef)>,
+}
+
+/// Logical signals of one pass, resolved against a waveform hierarchy.
+pub struct SignalMap {
+    sigs: Vec<(String, SignalRef)>,
+}
+
+impl SignalMap {
+    /// Resolve schema `keys` to concrete logical names (`iq.entry[3].p_rd`), looking only
+    /// at signals under `scope` when given.
+    ///
+    /// Per concrete name the earliest matching candidate wins. If that candidate matches
+    /// more than one signal the name is ambiguous and resolution fails, listing the
+    /// matches. Keys with no matching signal are skipped.
+    pub fn resolve(
+        schema: &Schema,
+        hierarchy: &Hierarchy,
+        keys: &[&str],
+        scope: Option<&str>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut templates = Vec::new();
+        for &key in keys {
+            let candidates = schema
+                .signals
+                .get(key)
+                .ok_or_else(|| format!("Signal `{}` is not defined in the schema", key))?;
+            for (rank, c) in candidates.iter().enumerate() {
+                templates.push((key, rank, c.as_str(), Template::parse(c)?));
+            }
+        }
+
+        let mut best: HashMap<String, Best<'_>> = HashMap::new();
+        if !templates.is_empty() {
+            for (order, var) in hierarchy.iter_vars().enumerate() {
+                let name = var.full_name(hierarchy);
+                if scope.is_some_and(|s| !in_scope(&name, s)) {
+                    continue;
+                }
+                let sig = var.signal_ref();
+                for (key, rank, candidate, t) in &templates {
+                    let Some(caps) = t.match_suffix(&name) else {
+                        continue;
+                    };
+                    let found = || Best {
+                        rank: *rank,
+                        candidate,
+                        order,
+                        matches: vec![(name.clone(), sig)],
+                    };
+                    match best.entry(concrete_name(key, &caps)) {
+                        Entry::Vacant(e) => {
+                            e.insert(found());
+                        }
+                        Entry::Occupied(mut e) if *rank < e.get().rank => {
+                            e.insert(found());
+                        }
+                        Entry::Occupied(mut e) if *rank == e.get().rank => {
+                            // Aliases of one signal (same SignalRef) are not ambiguous.
+                            let m = &mut e.get_mut().matches;
+                            if !m.iter().any(|(_, s)| *s == sig) {
+                                m.push((name.clone(), sig));
+                            }
+                        }
+                        Entry::Occupied(_) => {}
+                    }
+                }
+            }
+        }
+
+        let mut sigs: Vec<_> = best.into_iter().collect();
+        sigs.sort_by(|(na, a), (nb, b)| a.order.cmp(&b.order).then_with(|| na.cmp(nb)));
+
+        let ambiguous: Vec<_> = sigs.iter().filter(|(_, b)| b.matches.len() > 1).collect();
+        if !ambiguous.is_empty() {
+            let mut msg = String::new();
+            for (logical, b) in &ambiguous {
+                msg.push_str(&format!(
+                    "Ambiguous signal `{}`: candidate `{}` matches {} signals:\n",
+                    logical,
+                    b.candidate,
+                    b.matches.len()
+                ));
+                for (path, _) in &b.matches {
+                    msg.push_str(&format!("  {}\n", path));
+                }
+            }
+            msg.push_str(AMBIGUOUS_HINT);
+            msg.push_str(", or make the candidate in the schema more specific");
+            return Err(msg.into());
+        }
+
+        Ok(Self {
+            sigs: sigs
+                .into_iter()
+                .map(|(n, mut b)| (n, b.matches.remove(0).1))
+                .collect(),
+        })
+    }
+
+    /// Signal for a name given at run time (e.g. in a `scan --where` expression): a
+    /// concrete logical name (`iq.entry[3].p_rd`) resolved through the schema, otherwise a
+    /// hierarchical name, full or as a suffix at a scope boundary. `None` if nothing
+    /// matches; an error if several signals do.
+    pub fn lookup(
+        schema: &Schema,
+        hierarchy: &Hierarchy,
+        name: &str,
+        scope: Option<&str>,
+    ) -> Result<Option<SignalRef>, Box<dyn std::error::Error>> {
+        for key in schema.signals.keys().filter(|k| is_instance(k, name)) {
+            let found = Self::resolve(schema, hierarchy, &[key.as_str()], scope)?
+                .sigs
+                .into_iter()
+                .find(|(n, _)| n == name);
+            if let Some((_, sig)) = found {
+                return Ok(Some(sig));
+            }
+        }
+
+        let suffix = format!(".{}", name);
+        let mut matches: Vec<(String, SignalRef)> = Vec::new();
+        for var in hierarchy.iter_vars() {
+            let full = var.full_name(hierarchy);
+            if scope.is_some_and(|s| !in_scope(&full, s))
+                || (full != name && !full.ends_with(&suffix))
+            {
+                continue;
+            }
+            if !matches.iter().any(|(_, s)| *s == var.signal_ref()) {
+                matches.push((full, var.signal_ref()));
+            }
+        }
+        if matches.len() > 1 {
+            let mut msg = format!(
+                "Ambiguous signal `{}`: matches {} signals:\n",
+                name,
+                matches.len()
+            );
+            for (path, _) in &matches {
+                msg.push_str(&format!("  {}\n", path));
+            }
+            msg.push_str(AMBIGUOUS_HINT);
+            msg.push_str(", or give a longer path");
+            return Err(msg.into());
+        }
+        Ok(matches.pop().map(|(_, sig)| sig))
+    }
+
+    /// `(logical name, signal)` in hierarchy order.
+    pub fn into_sigs(self) -> Vec<(String, SignalRef)> {
+        self.sigs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wellen::simple::{Waveform, read_from_reader};
+
+    /// A waveform with one 1-bit variable per `(dotted path, VCD id)`; a repeated id is an
+    /// alias of the same signal.
+    fn waveform(vars: &[(&str, &str)]) -> Waveform {
+        let mut vcd = String::from("$timescale 1ns $end\n");
+        let mut open: Vec<&str> = Vec::new();
+        for &(path, id) in vars {
+            let (scopes, name) = path.rsplit_once('.').unwrap();
+            let scopes: Vec<&str> = scopes.split('.').collect();
+            let keep = open.iter().zip(&scopes).take_while(|(a, b)| a == b).count();
+            for _ in keep..open.len() {
+                vcd.push_str("$upscope $end\n");
+            }
+            open.truncate(keep);
+            for s in &scopes[keep..] {
+                vcd.push_str(&format!("$scope module {} $end\n", s));
+                open.push(s);
+            }
+            vcd.push_str(&format!("$var wire 1 {} {} $end\n", id, name));
+        }
+        for _ in &open {
+            vcd.push_str("$upscope $end\n");
+        }
+        vcd.push_str("$enddefinitions $end\n#0\n");
+        read_from_reader(std::io::Cursor::new(vcd.into_bytes())).unwrap()
+    }
+
+    fn schema(signals: &str) -> Schema {
+        Schema::parse(&format!("version = 1\n[signals]\n{}", signals), "test").unwrap()
+    }
+
+    /// Resolved `(logical name, full path)` pairs, or the error text.
+    fn resolve(
+        schema: &Schema,
+        wf: &Waveform,
+        keys: &[&str],
+        scope: Option<&str>,
+    ) -> Result<Vec<(String, String)>, String> {
+        let h = wf.hierarchy();
+        let map = SignalMap::resolve(schema, h, keys, scope).map_err(|e| e.to_string())?;
+        Ok(map
+            .into_sigs()
+            .into_iter()
+            .map(|(n, sig)| (n, path_of(wf, sig)))
+            .collect())
+    }
+
+    fn path_of(wf: &Waveform, sig: SignalRef) -> String {
+        let h = wf.hierarchy();
+        h.iter_vars()
+            .find(|v| v.signal_ref() == sig)
+            .map(|v| v.full_name(h))
+            .unwrap()
+    }
+
+    fn pairs(v: &[(&str, &str)]) -> Vec<(String, String)> {
+        v.iter().map(|&(a, b)| (a.into(), b.into())).collect()
+    }
+
+    #[test]
+    fn suffix_at_scope_boundary() {
+        let t = Template::parse("iq.entries_{i}_p_rd").unwrap();
+        assert_eq!(
+            t.match_suffix("top.core.iq.entries_12_p_rd"),
+            Some(vec![("i", "12")])
+        );
+        assert_eq!(t.match_suffix("iq.entries_3_p_rd"), Some(vec![("i", "3")]));
+        assert_eq!(t.match_suffix("top.xiq.entries_3_p_rd"), None);
+        assert_eq!(t.match_suffix("top.iq.entries__p_rd"), None);
+        assert_eq!(t.match_suffix("top.iq.entries_3_p_rd_x"), None);
+    }
+
+    #[test]
+    fn default_placeholder() {
+        let t = Template::parse("exu.pipeOut_bits_r{i=0}_rob_id").unwrap();
+        assert_eq!(
+            t.match_suffix("exu.pipeOut_bits_r_rob_id"),
+            Some(vec![("i", "0")])
+        );
+        let s = schema(
+            r#""exu.pipe[i].rob_id" = ["exu.pipeOut_bits_r{i=0}_rob_id", "exu.pipeOut_bits_r_{i}_rob_id"]"#,
+        );
+        let wf = waveform(&[
+            ("top.exu.pipeOut_bits_r_rob_id", "!"),
+            ("top.exu.pipeOut_bits_r_1_rob_id", "\""),
+        ]);
+        assert_eq!(
+            resolve(&s, &wf, &["exu.pipe[i].rob_id"], None),
+            Ok(pairs(&[
+                ("exu.pipe[0].rob_id", "top.exu.pipeOut_bits_r_rob_id"),
+                ("exu.pipe[1].rob_id", "top.exu.pipeOut_bits_r_1_rob_id"),
+            ]))
+        );
+    }
+
+    #[test]
+    fn bad_templates() {
+        assert!(Template::parse("a_{i").is_err());
+        assert!(Template::parse("a_{}").is_err());
+        assert!(Template::parse("a_{i}{j}").is_err());
+        let err = |text: &str| {
+            Schema::parse(&format!("version = 1\n[signals]\n{}", text), "test")
+                .unwrap_err()
+                .to_string()
+        };
+        assert!(err(r#""a[i]" = ["a_{j}"]"#).contains("exactly the placeholders"));
+        assert!(err(r#""a" = []"#).contains("no candidate paths"));
+        let v2 = Schema::parse("version = 2\n[signals]\n", "test").unwrap_err();
+        assert!(v2.to_string().contains("version 2 is not supported"));
+    }
+
+    #[test]
+    fn earlier_candidate_wins() {
+        let s = schema(r#""rob.enq.valid" = ["rob.io_enq_valid", "rob.enq_valid"]"#);
+        let wf = waveform(&[("top.rob.enq_valid", "!"), ("top.rob.io_enq_valid", "\"")]);
+        assert_eq!(
+            resolve(&s, &wf, &["rob.enq.valid"], None),
+            Ok(pairs(&[("rob.enq.valid", "top.rob.io_enq_valid")]))
+        );
+        let wf = waveform(&[("top.rob.enq_valid", "!")]);
+        assert_eq!(
+            resolve(&s, &wf, &["rob.enq.valid"], None),
+            Ok(pairs(&[("rob.enq.valid", "top.rob.enq_valid")]))
+        );
+    }
+
+    #[test]
+    fn ambiguous_candidate() {
+        let s = schema(r#""rob.enq.valid" = ["rob.io_enq_valid"]"#);
+        let wf = waveform(&[
+            ("top.a.rob.io_enq_valid", "!"),
+            ("top.b.rob.io_enq_valid", "\""),
+        ]);
+        let err = resolve(&s, &wf, &["rob.enq.valid"], None).unwrap_err();
+        assert!(err.starts_with(
+            "Ambiguous signal `rob.enq.valid`: candidate `rob.io_enq_valid` matches 2 signals:\n  top.a.rob.io_enq_valid\n  top.b.rob.io_enq_valid\n"
+        ));
+        assert!(err.contains("--scope"));
+        assert_eq!(
+            resolve(&s, &wf, &["rob.enq.valid"], Some("top.b")),
+            Ok(pairs(&[("rob.enq.valid", "top.b.rob.io_enq_valid")]))
+        );
+        // Aliases of one signal are a single match.
+        let wf = waveform(&[
+            ("top.a.rob.io_enq_valid", "!"),
+            ("top.b.rob.io_enq_valid", "!"),
+        ]);
+        assert!(resolve(&s, &wf, &["rob.enq.valid"], None).is_ok());
+    }
+
+    #[test]
+    fn missing_signal() {
+        let s = schema(r#""rob.enq.valid" = ["rob.io_enq_valid"]"#);
+        let wf = waveform(&[("top.rob.other", "!")]);
+        assert_eq!(resolve(&s, &wf, &["rob.enq.valid"], None), Ok(vec![]));
+        assert_eq!(
+            resolve(&s, &wf, &["rob.nope"], None),
+            Err("Signal `rob.nope` is not defined in the schema".into())
+        );
+        let lookup = SignalMap::lookup(&s, wf.hierarchy(), "rob.enq.valid", None).unwrap();
+        assert_eq!(lookup, None);
+    }
+
+    #[test]
+    fn lookup_names() {
+        let s = schema(r#""iq.entry[i].p_rd" = ["iq.entries_{i}_p_rd"]"#);
+        let wf = waveform(&[
+            ("top.core.iq.entries_3_p_rd", "!"),
+            ("top.core.idu.io_in_valid", "\""),
+            ("top.core.isu.io_in_valid", "#"),
+        ]);
+        let h = wf.hierarchy();
+        let path = |name: &str| {
+            SignalMap::lookup(&s, h, name, None)
+                .map(|sig| sig.map(|sig| path_of(&wf, sig)))
+                .map_err(|e| e.to_string())
+        };
+        assert_eq!(
+            path("iq.entry[3].p_rd"),
+            Ok(Some("top.core.iq.entries_3_p_rd".into()))
+        );
+        assert_eq!(path("iq.entry[4].p_rd"), Ok(None));
+        assert_eq!(
+            path("idu.io_in_valid"),
+            Ok(Some("top.core.idu.io_in_valid".into()))
+        );
+        assert_eq!(
+            path("top.core.isu.io_in_valid"),
+            Ok(Some("top.core.isu.io_in_valid".into()))
+        );
+        assert!(
+            path("io_in_valid")
+                .unwrap_err()
+                .starts_with("Ambiguous signal `io_in_valid`")
+        );
+        assert_eq!(path("du.io_in_valid"), Ok(None));
+    }
+}
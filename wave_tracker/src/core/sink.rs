@@ -0,0 +1,170 @@
+//! Output sinks for pass results: human text, JSON Lines or CSV.
+
+use std::io::{self, Write};
+
+use super::event::{Event, Field};
+
+/// Output format selected with `--format`.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    /// One `t=<time> KIND name=value ...` line per event, plus headings and summaries
+    #[default]
+    Text,
+    /// One JSON object per event: `{"time", "cycle", "kind", "fields": {...}}`
+    Jsonl,
+    /// Header `time,cycle,kind,<field names...>`, one row per event
+    Csv,
+}
+
+/// Destination of pass results.
+pub trait Sink {
+    fn event(&mut self, ev: &Event) -> io::Result<()>;
+
+    /// Human-oriented text (headings, notes, totals); only the text sink prints it.
+    fn note(&mut self, _text: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Flush buffered output; called once after the pass has run.
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Sink for `format` writing to `out`.
+pub fn open_sink(format: Format, out: Box<dyn Write>) -> Box<dyn Sink> {
+    match format {
+        Format::Text => Box::new(TextSink { out }),
+        Format::Jsonl => Box::new(JsonlSink { out }),
+        Format::Csv => Box::new(CsvSink {
+            out,
+            columns: Vec::new(),
+            rows: Vec::new(),
+        }),
+    }
+}
+
+pub struct TextSink {
+    out: Box<dyn Write>,
+}
+
+impl Sink for TextSink {
+    fn event(&mut self, ev: &Event) -> io::Result<()> {
+        let mut line = String::new();
+        if let Some(c) = ev.cycle {
+            line.push_str(&format!("c={} ", c));
+        }
+        if let Some(t) = ev.time {
+            line.push_str(&format!("t={} ", t));
+        }
+        line.push_str(ev.kind);
+        for (name, value) in &ev.fields {
+            let v = value.to_string();
+            if v.contains(char::is_whitespace) {
+                line.push_str(&format!(" {}={:?}", name, v));
+            } else {
+                line.push_str(&format!(" {}={}", name, v));
+            }
+        }
+        writeln!(self.out, "{}", line)
+    }
+
+    fn note(&mut self, text: &str) -> io::Result<()> {
+        writeln!(self.out, "{}", text)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+fn json_field(f: &Field) -> serde_json::Value {
+    match f {
+        Field::Null => serde_json::Value::Null,
+        Field::Bool(b) => (*b).into(),
+        Field::Num(n) | Field::Hex(n) => (*n).into(),
+        Field::Float(x) => (*x).into(),
+        Field::Text(s) => s.as_str().into(),
+    }
+}
+
+pub struct JsonlSink {
+    out: Box<dyn Write>,
+}
+
+impl Sink for JsonlSink {
+    fn event(&mut self, ev: &Event) -> io::Result<()> {
+        let fields: serde_json::Map<String, serde_json::Value> = ev
+            .fields
+            .iter()
+            .map(|(n, v)| (n.clone(), json_field(v)))
+            .collect();
+        let obj = serde_json::json!({
+            "time": ev.time,
+            "cycle": ev.cycle,
+            "kind": ev.kind,
+            "fields": fields,
+        });
+        writeln!(self.out, "{}", obj)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Buffers all events: the header needs every field name that occurs.
+pub struct CsvSink {
+    out: Box<dyn Write>,
+    /// Field names in first-seen order.
+    columns: Vec<String>,
+    rows: Vec<Event>,
+}
+
+fn csv_cell(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+impl Sink for CsvSink {
+    fn event(&mut self, ev: &Event) -> io::Result<()> {
+        for (name, _) in &ev.fields {
+            if !self.columns.contains(name) {
+                self.columns.push(name.clone());
+            }
+        }
+        self.rows.push(ev.clone());
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        let mut header = vec!["time".to_string(), "cycle".into(), "kind".into()];
+        header.extend(self.columns.iter().map(|c| csv_cell(c)));
+        writeln!(self.out, "{}", header.join(","))?;
+        for ev in &self.rows {
+            let mut row = vec![
+                ev.time.map(|t| t.to_string()).unwrap_or_default(),
+                ev.cycle.map(|c| c.to_string()).unwrap_or_default(),
+                ev.kind.to_string(),
+            ];
+            for col in &self.columns {
+                let cell = ev
+                    .fields
+                    .iter()
+                    .find(|(n, _)| n == col)
+                    .map(|(_, v)| match v {
+                        Field::Null => String::new(),
+                        v => csv_cell(&v.to_string()),
+                    })
+                    .unwrap_or_default();
+                row.push(cell);
+            }
+            writeln!(self.out, "{}", row.join(","))?;
+        }
+        self.rows.clear();
+        self.out.flush()
+    }
+}
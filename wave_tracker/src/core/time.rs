@@ -1,5 +1,12 @@
+//! Time indexing and time arguments (`c1234`, `1234ns`, raw).
+
+use std::fmt;
+use std::str::FromStr;
+
 use wellen::{Time, TimeTableIdx};
 
+use super::cycle::Timebase;
+
 /// Largest time index `i` such that `time_table[i] <= t`.
 pub fn find_time_idx_at_or_before(time_table: &[Time], t: Time) -> Option<TimeTableIdx> {
     if time_table.is_empty() {
@@ -15,3 +22,160 @@ pub fn find_time_idx_at_or_before(time_table: &[Time], t: Time) -> Option<TimeTa
     }
     best
 }
+
+/// Point in time given on the command line.
+///
+/// Accepts `c1234` (cycle, see [`Timebase`]), `1234ns` / `5us` / `800ps` (converted to
+/// timescale units) and bare `1234` (raw timescale units). `_` separators are ignored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeArg {
+    Raw(Time),
+    Cycle(u64),
+    /// Value and power-of-ten exponent of its unit in seconds (`ns` = -9).
+    Real(u64, i8),
+}
+
+const UNITS: &[(&str, i8)] = &[
+    ("fs", -15),
+    ("ps", -12),
+    ("ns", -9),
+    ("us", -6),
+    ("ms", -3),
+    ("s", 0),
+];
+
+impl TimeArg {
+    /// Time in timescale units; cycles map to their sample time.
+    pub fn resolve(self, tb: &Timebase) -> Result<Time, String> {
+        match self {
+            TimeArg::Raw(t) => Ok(t),
+            TimeArg::Cycle(c) => {
+                if tb.clock.is_none() {
+                    return Err(format!("c{}: no clock found (set one with --clock)", c));
+                }
+                tb.cycle(c)
+                    .map(|s| s.time)
+                    .ok_or_else(|| format!("c{}: cycle not in the waveform", c))
+            }
+            TimeArg::Real(v, exp) => {
+                let ts = tb
+                    .timescale
+                    .and_then(|ts| Some((ts.factor as u128, ts.unit.to_exponent()?)))
+                    .ok_or_else(|| format!("{}: waveform timescale unknown", self))?;
+                let (factor, ts_exp) = ts;
+                // v * 10^exp / (factor * 10^ts_exp), exactly.
+                let shift = i32::from(exp) - i32::from(ts_exp);
+                let (num, den) = if shift >= 0 {
+                    (v as u128 * 10u128.pow(shift as u32), factor)
+                } else {
+                    (v as u128, factor * 10u128.pow((-shift) as u32))
+                };
+                if num % den != 0 {
+                    return Err(format!(
+                        "{}: not a whole number of timescale units ({}e{}s)",
+                        self, factor, ts_exp
+                    ));
+                }
+                Time::try_from(num / den).map_err(|_| format!("{}: out of range", self))
+            }
+        }
+    }
+}
+
+impl FromStr for TimeArg {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let t: String = s.trim().chars().filter(|c| *c != '_').collect();
+        let lower = t.to_ascii_lowercase();
+        let num = |d: &str| {
+            d.parse::<u64>()
+                .map_err(|e| format!("invalid time `{}`: {}", s, e))
+        };
+        if let Some(d) = lower.strip_prefix('c') {
+            return num(d).map(TimeArg::Cycle);
+        }
+        for (unit, exp) in UNITS {
+            if let Some(d) = lower.strip_suffix(unit) {
+                return num(d).map(|v| TimeArg::Real(v, *exp));
+            }
+        }
+        num(&lower).map(TimeArg::Raw)
+    }
+}
+
+impl fmt::Display for TimeArg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeArg::Raw(t) => write!(f, "{}", t),
+            TimeArg::Cycle(c) => write!(f, "c{}", c),
+            TimeArg::Real(v, exp) => {
+                let unit = UNITS.iter().find(|(_, e)| e == exp).map_or("?", |(u, _)| u);
+                write!(f, "{}{}", v, unit)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::cycle::ClockSpec;
+    use wellen::simple::read_from_reader;
+
+    /// 1ns timescale; clock rising at 5, 15, 25, reset released at 12 (cycle 0 at t=12).
+    fn timebase() -> Timebase {
+        let vcd = "$timescale 1ns $end\n$scope module top $end\n$var wire 1 ! clock $end\n\
+                   $var wire 1 \" reset $end\n$upscope $end\n$enddefinitions $end\n\
+                   #0\n0!\n1\"\n#5\n1!\n#10\n0!\n#12\n0\"\n#15\n1!\n#20\n0!\n#25\n1!\n#30\n0!\n";
+        let mut wf = read_from_reader(std::io::Cursor::new(vcd.as_bytes().to_vec())).unwrap();
+        Timebase::detect(&mut wf, &ClockSpec::default()).unwrap()
+    }
+
+    fn parse(s: &str) -> Result<TimeArg, String> {
+        s.parse()
+    }
+
+    #[test]
+    fn notations() {
+        assert_eq!(parse("c1234"), Ok(TimeArg::Cycle(1234)));
+        assert_eq!(parse("C12"), Ok(TimeArg::Cycle(12)));
+        assert_eq!(parse("1234ns"), Ok(TimeArg::Real(1234, -9)));
+        assert_eq!(parse("5us"), Ok(TimeArg::Real(5, -6)));
+        assert_eq!(parse("2s"), Ok(TimeArg::Real(2, 0)));
+        assert_eq!(parse("1_234"), Ok(TimeArg::Raw(1234)));
+        assert!(parse("12xs").is_err());
+        assert!(parse("12 min").is_err());
+        assert!(parse("c").is_err());
+        assert!(parse("-5").is_err());
+        for s in ["c1234", "1234ns", "800ps", "1234"] {
+            assert_eq!(parse(s).unwrap().to_string(), s);
+        }
+    }
+
+    #[test]
+    fn resolve() {
+        let tb = timebase();
+        let at = |s: &str| parse(s).unwrap().resolve(&tb);
+        assert_eq!(at("7"), Ok(7));
+        assert_eq!(at("20ns"), Ok(20));
+        assert_eq!(at("2us"), Ok(2000));
+        assert_eq!(at("3000ps"), Ok(3));
+        assert_eq!(
+            at("1500ps"),
+            Err("1500ps: not a whole number of timescale units (1e-9s)".into())
+        );
+        assert_eq!(at("c0"), Ok(12));
+        assert_eq!(at("c1"), Ok(20));
+        assert_eq!(at("c2"), Err("c2: cycle not in the waveform".into()));
+    }
+
+    #[test]
+    fn time_index() {
+        let table = [0, 5, 10, 12];
+        assert_eq!(find_time_idx_at_or_before(&table, 11), Some(2));
+        assert_eq!(find_time_idx_at_or_before(&table, 12), Some(3));
+        assert_eq!(find_time_idx_at_or_before(&table[1..], 0), None);
+        assert_eq!(find_time_idx_at_or_before(&[], 3), None);
+    }
+}
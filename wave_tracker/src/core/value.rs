@@ -0,0 +1,157 @@
+//! Typed signal value: width, bit vector and an optional X/Z mask.
+
+use std::fmt;
+
+use wellen::SignalValue;
+
+/// One sampled signal value.
+///
+/// Bits are stored LSB-first in 64-bit words. Any bit that is not a plain `0`/`1`
+/// (X, Z, U, W, `-`) is marked in the unknown mask; H/L are read as 1/0.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Value {
+    width: u32,
+    bits: Vec<u64>,
+    unknown: Option<Vec<u64>>,
+}
+
+fn words(width: u32) -> usize {
+    (width as usize).div_ceil(64).max(1)
+}
+
+impl Value {
+    /// Known value `v` truncated to `width` bits.
+    pub fn from_u64(v: u64, width: u32) -> Self {
+        let mut bits = vec![0u64; words(width)];
+        bits[0] = if width >= 64 {
+            v
+        } else {
+            v & ((1u64 << width) - 1)
+        };
+        Self {
+            width,
+            bits,
+            unknown: None,
+        }
+    }
+
+    /// Parse an MSB-first bit string as printed by wellen (`0`, `1`, `x`, `z`, ...).
+    pub fn from_bit_string(s: &str) -> Self {
+        let width = s.len() as u32;
+        let mut bits = vec![0u64; words(width)];
+        let mut unknown = vec![0u64; words(width)];
+        for (i, c) in s.bytes().rev().enumerate() {
+            let (w, b) = (i / 64, 1u64 << (i % 64));
+            match c.to_ascii_lowercase() {
+                b'0' | b'l' => {}
+                b'1' | b'h' => bits[w] |= b,
+                _ => unknown[w] |= b,
+            }
+        }
+        let unknown = unknown.iter().any(|&w| w != 0).then_some(unknown);
+        Self {
+            width,
+            bits,
+            unknown,
+        }
+    }
+
+    /// Convert a wellen sample. Real signals keep their IEEE-754 bits; strings their bytes.
+    pub fn from_signal(v: &SignalValue<'_>) -> Self {
+        match v {
+            SignalValue::Real(r) => Self::from_u64(r.to_bits(), 64),
+            SignalValue::String(s) => {
+                let bit_str: String = s.bytes().map(|b| format!("{:08b}", b)).collect();
+                Self::from_bit_string(&bit_str)
+            }
+            other => Self::from_bit_string(&other.to_bit_string().unwrap_or_default()),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// True if any bit is X/Z.
+    pub fn is_unknown(&self) -> bool {
+        self.unknown.is_some()
+    }
+
+    /// Bit `i` (LSB = 0); `None` if out of range or X/Z.
+    pub fn bit(&self, i: u32) -> Option<bool> {
+        if i >= self.width {
+            return None;
+        }
+        let (w, b) = ((i / 64) as usize, 1u64 << (i % 64));
+        if self.unknown.as_ref().is_some_and(|u| u[w] & b != 0) {
+            return None;
+        }
+        Some(self.bits[w] & b != 0)
+    }
+
+    /// Numeric value; `None` if any bit is X/Z or the value does not fit in 64 bits.
+    pub fn as_u64(&self) -> Option<u64> {
+        if self.is_unknown() || self.bits[1..].iter().any(|&w| w != 0) {
+            return None;
+        }
+        Some(self.bits[0])
+    }
+
+    /// Nonzero test; `None` if any bit is X/Z.
+    pub fn as_bool(&self) -> Option<bool> {
+        if self.is_unknown() {
+            return None;
+        }
+        Some(self.bits.iter().any(|&w| w != 0))
+    }
+}
+
+impl PartialEq<u64> for Value {
+    fn eq(&self, other: &u64) -> bool {
+        self.as_u64() == Some(*other)
+    }
+}
+
+/// MSB-first bit string, `x` for unknown bits (same shape as wellen's output).
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for i in (0..self.width).rev() {
+            let c = match self.bit(i) {
+                Some(true) => '1',
+                Some(false) => '0',
+                None => 'x',
+            };
+            write!(f, "{}", c)?;
+        }
+        Ok(())
+    }
+}
+
+/// Hex digits, `x` for any nibble containing an unknown bit.
+impl fmt::LowerHex for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let nibbles = self.width.div_ceil(4).max(1);
+        for n in (0..nibbles).rev() {
+            let mut digit = 0u32;
+            let mut unknown = false;
+            for b in 0..4 {
+                match self.bit(n * 4 + b) {
+                    Some(true) => digit |= 1 << b,
+                    Some(false) => {}
+                    None => unknown |= n * 4 + b < self.width,
+                }
+            }
+            if unknown {
+                write!(f, "x")?;
+            } else {
+                write!(f, "{:x}", digit)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `v` as a bit string, or `?` when the signal is missing.
+pub fn show(v: Option<&Value>) -> String {
+    v.map_or_else(|| "?".into(), Value::to_string)
+}
@@ -1,8 +1,21 @@
 //! Wave tracker binary: thin entrypoint over [`wave_tracker::cli`].
 
-use clap::Parser;
-use wave_tracker::cli::{Args, run};
+use std::process::ExitCode;
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    run(Args::parse())
+fn main() -> ExitCode {
+    // Print errors with Display: ambiguity reports span several lines.
+    match wave_tracker::cli::run(std::env::args_os()) {
+        Ok(()) => ExitCode::SUCCESS,
+        // Reader went away (e.g. `| head`): not an error.
+        Err(e)
+            if e.downcast_ref::<std::io::Error>()
+                .is_some_and(|e| e.kind() == std::io::ErrorKind::BrokenPipe) =>
+        {
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
 }